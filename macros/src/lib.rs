@@ -0,0 +1,507 @@
+//! Derive macros for the standup bot, generating the option schema and parsing of slash commands from a struct
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Fields, GenericArgument,
+    Ident, Lit, Meta, NestedMeta, PathArguments, Type,
+};
+
+/// Derive `CommandOptions` for a struct with named fields, each field becoming one option of the command.
+///
+/// Fields are required unless they are an `Option`, and are configured with `#[option(...)]`:
+/// - `description = "..."`, falling back to the doc comment of the field
+/// - `name = "..."`, falling back to the name of the field
+/// - `min_length = n` and `max_length = n`, for string options
+/// - `min_value = n` and `max_value = n`, for numeric options
+/// - `autocomplete`, if the command answers autocomplete requests for the option
+/// - `channel_types(Text, News)`, for channel options, limiting them to the listed `ChannelType`s
+/// - `flatten`, taking the options of another `CommandOptions` struct, such as those shared by subcommands
+///
+/// A `TryFrom<&CommandInteraction>` implementation is generated which parses each option by name,
+/// failing with `OptionError::Missing` if a required option is missing or `OptionError::OutOfBounds`
+/// if a value is out of bounds.
+#[proc_macro_derive(CommandOptions, attributes(option))]
+pub fn derive_command_options(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// a field of the struct, and the option it is parsed from
+struct OptionField {
+    ident: Ident,
+    /// the type of the value, with any `Option` removed
+    ty: Type,
+    name: String,
+    description: String,
+    required: bool,
+    min_length: Option<Lit>,
+    max_length: Option<Lit>,
+    min_value: Option<Lit>,
+    max_value: Option<Lit>,
+    autocomplete: bool,
+    /// the variants of `ChannelType` a channel option is limited to, any if empty
+    channel_types: Vec<Ident>,
+    /// whether the field is another struct of options, declared and parsed in its place
+    flatten: bool,
+}
+
+/// get the type inside an `Option`, if the type is one
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// join the doc comments of a field into a single line
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(s) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines.join(" ")),
+    }
+}
+
+/// read the `#[option(...)]` attributes and doc comment of a field
+fn parse_field(field: &syn::Field) -> syn::Result<OptionField> {
+    let ident = field
+        .ident
+        .clone()
+        .ok_or_else(|| syn::Error::new(field.span(), "options must be named fields"))?;
+    let (ty, required) = match option_inner(&field.ty) {
+        Some(inner) => (inner.clone(), false),
+        None => (field.ty.clone(), true),
+    };
+
+    let mut parsed = OptionField {
+        name: ident.to_string(),
+        ident,
+        ty,
+        description: String::new(),
+        required,
+        min_length: None,
+        max_length: None,
+        min_value: None,
+        max_value: None,
+        autocomplete: false,
+        channel_types: vec![],
+        flatten: false,
+    };
+    let mut description = None;
+
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("option")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "expected #[option(...)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("autocomplete") => {
+                    parsed.autocomplete = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                    parsed.flatten = true;
+                }
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("channel_types") => {
+                    for nested in list.nested {
+                        let ident = match &nested {
+                            NestedMeta::Meta(Meta::Path(path)) => path.get_ident().cloned(),
+                            _ => None,
+                        };
+                        let ident = ident.ok_or_else(|| {
+                            syn::Error::new(nested.span(), "expected the name of a channel type")
+                        })?;
+                        parsed.channel_types.push(ident);
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    let key = nv
+                        .path
+                        .get_ident()
+                        .map(|i| i.to_string())
+                        .unwrap_or_default();
+                    match (key.as_str(), nv.lit) {
+                        ("description", Lit::Str(s)) => description = Some(s.value()),
+                        ("name", Lit::Str(s)) => parsed.name = s.value(),
+                        ("min_length", lit @ Lit::Int(_)) => parsed.min_length = Some(lit),
+                        ("max_length", lit @ Lit::Int(_)) => parsed.max_length = Some(lit),
+                        ("min_value", lit @ (Lit::Int(_) | Lit::Float(_))) => {
+                            parsed.min_value = Some(lit)
+                        }
+                        ("max_value", lit @ (Lit::Int(_) | Lit::Float(_))) => {
+                            parsed.max_value = Some(lit)
+                        }
+                        (_, lit) => {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                format!("unsupported option attribute `{}`", key),
+                            ))
+                        }
+                    }
+                }
+                nested => {
+                    return Err(syn::Error::new(
+                        nested.span(),
+                        "unsupported option attribute",
+                    ))
+                }
+            }
+        }
+    }
+
+    // the options of a flattened struct are described by that struct
+    if parsed.flatten {
+        if !required {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                "flattened options can't be optional, make their fields optional instead",
+            ));
+        }
+        return Ok(parsed);
+    }
+
+    parsed.description = description
+        .or_else(|| doc_comment(&field.attrs))
+        .ok_or_else(|| {
+            syn::Error::new(
+                field.span(),
+                "options need a description, either a doc comment or #[option(description = \"...\")]",
+            )
+        })?;
+
+    Ok(parsed)
+}
+
+/// add the `CreateCommandOption` of a field to `options`, or every option of a flattened field
+fn schema(field: &OptionField) -> TokenStream2 {
+    let OptionField {
+        ty,
+        name,
+        description,
+        required,
+        autocomplete,
+        ..
+    } = field;
+
+    if field.flatten {
+        return quote! {
+            options.extend(<#ty as crate::discord_bot::commands::options::CommandOptions>::options());
+        };
+    }
+
+    let mut builder = quote! {
+        ::serenity::builder::CreateCommandOption::new(
+            <#ty as crate::discord_bot::commands::options::OptionValue>::KIND,
+            #name,
+            #description,
+        )
+        .required(#required)
+    };
+    if let Some(lit) = &field.min_length {
+        builder = quote! { #builder.min_length(#lit) };
+    }
+    if let Some(lit) = &field.max_length {
+        builder = quote! { #builder.max_length(#lit) };
+    }
+    for (lit, int, number) in [
+        (
+            &field.min_value,
+            quote!(min_int_value),
+            quote!(min_number_value),
+        ),
+        (
+            &field.max_value,
+            quote!(max_int_value),
+            quote!(max_number_value),
+        ),
+    ] {
+        builder = match lit {
+            Some(lit @ Lit::Int(_)) => quote! { #builder.#int(#lit) },
+            Some(lit) => quote! { #builder.#number(#lit) },
+            None => builder,
+        };
+    }
+    if *autocomplete {
+        builder = quote! { #builder.set_autocomplete(true) };
+    }
+    if !field.channel_types.is_empty() {
+        let channel_types = &field.channel_types;
+        builder = quote! {
+            #builder.channel_types(vec![#(::serenity::all::ChannelType::#channel_types),*])
+        };
+    }
+    quote! { options.push(#builder); }
+}
+
+/// parse a field from an invocation, checking it is within its bounds
+fn parse(field: &OptionField) -> syn::Result<TokenStream2> {
+    let OptionField {
+        ident,
+        ty,
+        name,
+        required,
+        ..
+    } = field;
+
+    if field.flatten {
+        return Ok(quote! {
+            let #ident = <#ty as crate::discord_bot::commands::options::CommandOptions>::parse(invocation)?;
+        });
+    }
+
+    // the bounds are re-emitted as unsuffixed literals, so they take on the type of the bound
+    let usize_bound = |lit: &Option<Lit>| -> syn::Result<TokenStream2> {
+        match lit {
+            Some(Lit::Int(int)) => {
+                let value = Literal::usize_unsuffixed(int.base10_parse()?);
+                Ok(quote! { Some(#value) })
+            }
+            _ => Ok(quote! { None }),
+        }
+    };
+    let f64_bound = |lit: &Option<Lit>| -> syn::Result<TokenStream2> {
+        let value: f64 = match lit {
+            Some(Lit::Int(int)) => int.base10_parse::<i64>()? as f64,
+            Some(Lit::Float(float)) => float.base10_parse()?,
+            _ => return Ok(quote! { None }),
+        };
+        let value = Literal::f64_unsuffixed(value);
+        Ok(quote! { Some(#value) })
+    };
+    let (min_length, max_length) = (
+        usize_bound(&field.min_length)?,
+        usize_bound(&field.max_length)?,
+    );
+    let (min_value, max_value) = (f64_bound(&field.min_value)?, f64_bound(&field.max_value)?);
+
+    let value = quote! {
+        crate::discord_bot::commands::options::parse_option::<#ty>(
+            invocation,
+            #name,
+            crate::discord_bot::commands::options::Bounds {
+                min_length: #min_length,
+                max_length: #max_length,
+                min_value: #min_value,
+                max_value: #max_value,
+            },
+        )?
+    };
+
+    Ok(match required {
+        true => quote! {
+            let #ident = #value.ok_or_else(|| {
+                crate::discord_bot::commands::options::OptionError::Missing(String::from(#name))
+            })?;
+        },
+        false => quote! {
+            let #ident = #value;
+        },
+    })
+}
+
+/// generate the implementations for a struct
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "CommandOptions can't be derived for generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(parse_field)
+                .collect::<syn::Result<Vec<_>>>()?,
+            Fields::Unit => vec![],
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new(
+                    fields.span(),
+                    "CommandOptions needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "CommandOptions can only be derived for structs",
+            ))
+        }
+    };
+
+    let schemas = fields.iter().map(schema);
+    let parsers = fields.iter().map(parse).collect::<syn::Result<Vec<_>>>()?;
+    let idents = fields.iter().map(|f| &f.ident);
+
+    Ok(quote! {
+        impl crate::discord_bot::commands::options::CommandOptions for #ident {
+            fn options() -> Vec<::serenity::builder::CreateCommandOption> {
+                let mut options = vec![];
+                #(#schemas)*
+                options
+            }
+
+            fn parse(
+                invocation: &crate::discord_bot::commands::subcommand::Invocation,
+            ) -> Result<Self, crate::discord_bot::commands::options::OptionError> {
+                #(#parsers)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+
+        impl<'a> ::core::convert::TryFrom<&'a ::serenity::all::CommandInteraction> for #ident {
+            type Error = crate::discord_bot::commands::options::OptionError;
+            fn try_from(interaction: &'a ::serenity::all::CommandInteraction) -> Result<Self, Self::Error> {
+                let invocation = crate::discord_bot::commands::subcommand::Invocation::new(interaction);
+                <Self as crate::discord_bot::commands::options::CommandOptions>::parse(&invocation)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    /// the code generated for a struct, with the whitespace quote adds between tokens removed
+    fn expanded(input: DeriveInput) -> String {
+        expand(input).unwrap().to_string().replace(' ', "")
+    }
+
+    #[test]
+    fn required_options_fail_as_missing_when_left_out() {
+        let code = expanded(parse_quote! {
+            struct Example {
+                /// a required option
+                required: String,
+                /// an optional option
+                optional: Option<String>,
+            }
+        });
+
+        assert!(code.contains(".required(true)"));
+        assert!(code.contains(".required(false)"));
+        assert!(code
+            .contains("letrequired=crate::discord_bot::commands::options::parse_option::<String>"));
+        assert!(code.contains("OptionError::Missing(String::from(\"required\"))"));
+        assert_eq!(code.matches("OptionError::Missing").count(), 1);
+    }
+
+    #[test]
+    fn bounds_are_declared_and_checked() {
+        let code = expanded(parse_quote! {
+            struct Example {
+                #[option(min_length = 2, max_length = 32)]
+                /// a bounded string
+                text: String,
+                #[option(min_value = 1, max_value = 2.5)]
+                /// a bounded number
+                number: Option<f64>,
+            }
+        });
+
+        assert!(code.contains(".min_length(2).max_length(32)"));
+        assert!(code.contains(".min_int_value(1).max_number_value(2.5)"));
+        assert!(
+            code.contains("min_length:Some(2),max_length:Some(32),min_value:None,max_value:None")
+        );
+        assert!(code
+            .contains("min_length:None,max_length:None,min_value:Some(1.0),max_value:Some(2.5)"));
+    }
+
+    #[test]
+    fn options_need_a_description() {
+        let input: DeriveInput = parse_quote! {
+            struct Example {
+                undocumented: String,
+            }
+        };
+        assert!(expand(input).is_err());
+    }
+
+    #[test]
+    fn channel_types_are_declared() {
+        let code = expanded(parse_quote! {
+            struct Example {
+                #[option(channel_types(Text, News))]
+                /// a channel
+                channel: ChannelId,
+            }
+        });
+
+        assert!(code.contains(
+            ".channel_types(vec![::serenity::all::ChannelType::Text,::serenity::all::ChannelType::News])"
+        ));
+    }
+
+    #[test]
+    fn flattened_options_are_declared_and_parsed_in_place() {
+        let code = expanded(parse_quote! {
+            struct Example {
+                /// a link
+                link: String,
+                #[option(flatten)]
+                content: Content,
+            }
+        });
+
+        assert!(code.contains(
+            "options.extend(<Contentascrate::discord_bot::commands::options::CommandOptions>::options());"
+        ));
+        assert!(code.contains(
+            "letcontent=<Contentascrate::discord_bot::commands::options::CommandOptions>::parse(invocation)?;"
+        ));
+        assert!(code.find("\"link\"").unwrap() < code.find("options.extend").unwrap());
+
+        let optional: DeriveInput = parse_quote! {
+            struct Example {
+                #[option(flatten)]
+                content: Option<Content>,
+            }
+        };
+        assert!(expand(optional).is_err());
+    }
+
+    #[test]
+    fn unknown_attributes_are_refused() {
+        let input: DeriveInput = parse_quote! {
+            struct Example {
+                #[option(maximum = 3)]
+                /// a mistyped bound
+                number: i64,
+            }
+        };
+        assert!(expand(input).is_err());
+    }
+}
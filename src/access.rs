@@ -0,0 +1,63 @@
+//! Role based access control, letting a guild grant capabilities of the bot to specific roles
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+/// something the bot can do which a guild may restrict to certain roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    /// change how standups work in the guild
+    #[serde(rename = "standup.configure")]
+    StandupConfigure,
+    /// export the submissions of the guild
+    #[serde(rename = "standup.export")]
+    StandupExport,
+    /// make the bot post a message with `/say`
+    #[serde(rename = "say.use")]
+    SayUse,
+    /// see submissions which aren't shown to the whole guild, such as the history of another member
+    #[serde(rename = "standup.view-private")]
+    StandupViewPrivate,
+}
+
+impl Capability {
+    /// every capability which can be granted
+    pub const ALL: [Capability; 4] = [
+        Capability::StandupConfigure,
+        Capability::StandupExport,
+        Capability::SayUse,
+        Capability::StandupViewPrivate,
+    ];
+
+    /// the name used to refer to this capability
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::StandupConfigure => "standup.configure",
+            Capability::StandupExport => "standup.export",
+            Capability::SayUse => "say.use",
+            Capability::StandupViewPrivate => "standup.view-private",
+        }
+    }
+
+    /// parse the name of a capability
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// check if any of the provided roles has been granted a capability in a guild
+pub async fn roles_have_capability(
+    storage: &Storage,
+    guild_id: u64,
+    roles: &[u64],
+    capability: Capability,
+) -> bool {
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| g.role_capabilities.get(&capability))
+        .map_or(false, |granted| granted.iter().any(|r| roles.contains(r)))
+}
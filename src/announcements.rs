@@ -0,0 +1,269 @@
+//! Announcements posted by the bot with `/say`, either straight away or stored until the time they are scheduled for
+
+use std::{error::Error, path::PathBuf, sync::OnceLock};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::error;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+/// the most messages a guild can have waiting to be sent
+pub const MAX_SCHEDULED_MESSAGES: usize = 25;
+
+/// the largest total size of the attachments of a scheduled message, as they are kept on disk until it is sent
+pub const MAX_SCHEDULED_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+
+/// the most entries kept in the log of a guild, the oldest are dropped first
+pub const MAX_SAY_LOG: usize = 500;
+
+/// the format a scheduled time is written in, in the zone of the member scheduling it
+pub const SEND_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// the embed of an announcement
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SayEmbed {
+    /// the title shown at the top of the embed
+    #[serde(default)]
+    pub title: Option<String>,
+    /// the body of the embed
+    #[serde(default)]
+    pub description: Option<String>,
+    /// the colour of the bar down the side of the embed, as rgb
+    #[serde(default)]
+    pub colour: Option<u32>,
+}
+
+/// a file attached to an announcement, downloaded when the announcement is made as discord's links to it expire
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SayAttachment {
+    /// the name the file was uploaded with
+    pub filename: String,
+    /// the file the contents are kept in until the announcement is sent, as they are too large for the store
+    pub path: PathBuf,
+}
+
+/// the contents of an announcement
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SayMessage {
+    /// the plain text of the message
+    #[serde(default)]
+    pub content: Option<String>,
+    /// the embed shown below the text
+    #[serde(default)]
+    pub embed: Option<SayEmbed>,
+    /// the files attached to the message
+    #[serde(default)]
+    pub attachments: Vec<SayAttachment>,
+}
+
+impl SayMessage {
+    /// whether there is nothing to send
+    pub fn is_empty(&self) -> bool {
+        self.content.is_none() && self.embed.is_none() && self.attachments.is_empty()
+    }
+
+    /// a summary of everything the message says, with the names of its attachments, for the log
+    pub fn summary(&self) -> String {
+        let embed = self.embed.as_ref();
+        self.content
+            .iter()
+            .chain(embed.and_then(|e| e.title.as_ref()))
+            .chain(embed.and_then(|e| e.description.as_ref()))
+            .cloned()
+            .chain(self.attachments.iter().map(|a| format!("[{}]", a.filename)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// an announcement waiting to be sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// the id of the scheduled message, taken from the interaction which scheduled it
+    pub id: u64,
+    /// the channel the message will be sent to
+    pub channel_id: u64,
+    /// the member who scheduled the message
+    pub author_id: u64,
+    /// when the message should be sent
+    pub send_at: DateTime<Utc>,
+    /// what will be sent
+    pub message: SayMessage,
+}
+
+/// something a member made the bot do on their behalf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SayLogAction {
+    /// a message was sent, either straight away or once it was due
+    Sent,
+    /// a message was scheduled to be sent later
+    Scheduled,
+    /// a scheduled message was cancelled before it was sent
+    Cancelled,
+    /// a message sent by the bot was edited
+    Edited,
+    /// a message sent by the bot was deleted
+    Deleted,
+}
+
+/// an entry in the log of messages the bot sent on behalf of members, so they can be attributed later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SayLogEntry {
+    /// what was done
+    pub action: SayLogAction,
+    /// the member it was done on behalf of
+    pub user_id: u64,
+    /// when it was done
+    pub at: DateTime<Utc>,
+    /// the channel of the message
+    pub channel_id: u64,
+    /// the message, if one was sent
+    #[serde(default)]
+    pub message_id: Option<u64>,
+    /// a summary of what the message said
+    #[serde(default)]
+    pub content: String,
+}
+
+/// parse a colour written as hex, such as `#ff8800` or `ff8800`
+pub fn parse_colour(raw: &str) -> Option<u32> {
+    let hex = raw.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// parse a link to a message, as copied from discord, into its guild, channel and message ids
+pub fn parse_message_link(link: &str) -> Option<(u64, u64, u64)> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^https://(?:(?:ptb|canary)\.)?discord(?:app)?\.com/channels/(\d+)/(\d+)/(\d+)/?$",
+        )
+        .unwrap()
+    });
+    let captures = pattern.captures(link.trim())?;
+    let id = |i: usize| -> Option<u64> { captures.get(i)?.as_str().parse().ok() };
+    Some((id(1)?, id(2)?, id(3)?))
+}
+
+/// parse the time a message should be sent, written as [SEND_TIME_FORMAT] in the provided zone.
+/// a time skipped by a daylight savings transition is rejected, an ambiguous one is the earlier of the two
+pub fn parse_send_time(raw: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    let local = NaiveDateTime::parse_from_str(raw.trim(), SEND_TIME_FORMAT).ok()?;
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// remove the messages of a guild which are due to be sent, returning them.
+/// they are removed before being sent, so a message is never sent twice, even if the bot stops part way through
+pub async fn take_due_messages(
+    storage: &Storage,
+    guild_id: u64,
+    now: DateTime<Utc>,
+) -> Result<Vec<ScheduledMessage>, Box<dyn Error + Send + Sync>> {
+    let any_due = storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .map_or(false, |g| {
+            g.scheduled_messages.iter().any(|m| m.send_at <= now)
+        });
+    if !any_due {
+        return Ok(vec![]);
+    }
+
+    storage
+        .update(|data| {
+            let scheduled = &mut data.guilds.entry(guild_id).or_default().scheduled_messages;
+            let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(scheduled)
+                .into_iter()
+                .partition(|m| m.send_at <= now);
+            *scheduled = waiting;
+            due
+        })
+        .await
+}
+
+/// keep the contents of an attachment in a file of its own until its announcement is sent,
+/// named after the interaction which made the announcement and the position of the attachment
+pub async fn store_attachment(
+    storage: &Storage,
+    id: u64,
+    index: usize,
+    filename: &str,
+    data: &[u8],
+) -> Result<SayAttachment, Box<dyn Error + Send + Sync>> {
+    let dir = storage.dir().join("attachments");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}-{}", id, index));
+    tokio::fs::write(&path, data).await?;
+    Ok(SayAttachment {
+        filename: filename.to_string(),
+        path,
+    })
+}
+
+/// remove the files of the attachments of an announcement, once it has been sent or cancelled.
+/// the announcement is already done with, so a file which can't be removed is only logged
+pub async fn remove_attachments(attachments: &[SayAttachment]) {
+    for attachment in attachments {
+        match tokio::fs::remove_file(&attachment.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => error!(
+                "failed to remove attachment {}: {}",
+                attachment.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// check if a message was sent with `/say` in a guild and hasn't been deleted since, so it can be edited or deleted.
+/// only messages still in the log are known, as the oldest entries are dropped once it is full
+pub async fn said_message(
+    storage: &Storage,
+    guild_id: u64,
+    channel_id: u64,
+    message_id: u64,
+) -> bool {
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| {
+            g.say_log.iter().rev().find(|e| {
+                e.channel_id == channel_id
+                    && e.message_id == Some(message_id)
+                    && matches!(e.action, SayLogAction::Sent | SayLogAction::Deleted)
+            })
+        })
+        .map_or(false, |e| e.action == SayLogAction::Sent)
+}
+
+/// add an entry to the log of a guild, dropping the oldest entries once it is full
+pub async fn record_say(
+    storage: &Storage,
+    guild_id: u64,
+    entry: SayLogEntry,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    storage
+        .update(|data| {
+            let log = &mut data.guilds.entry(guild_id).or_default().say_log;
+            log.push(entry);
+            if log.len() > MAX_SAY_LOG {
+                let excess = log.len() - MAX_SAY_LOG;
+                log.drain(..excess);
+            }
+        })
+        .await
+}
@@ -0,0 +1,347 @@
+//! Rate limits on commands, each command declaring cooldowns which are tracked with token buckets
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// the number of buckets kept before those which have refilled are dropped
+const PRUNE_THRESHOLD: usize = 1000;
+
+/// who a cooldown is shared between
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CooldownScope {
+    /// each user has their own cooldown
+    User,
+    /// everyone using the command in a channel shares a cooldown
+    Channel,
+    /// everyone using the command in a guild shares a cooldown
+    Guild,
+}
+
+/// a limit on how often a command can be used, allowing `uses` uses in each `per`
+#[derive(Debug, Clone, Copy)]
+pub struct Cooldown {
+    pub scope: CooldownScope,
+    pub uses: u32,
+    pub per: Duration,
+}
+
+impl Cooldown {
+    pub const fn new(scope: CooldownScope, uses: u32, per: Duration) -> Self {
+        Self { scope, uses, per }
+    }
+
+    /// the number of tokens regained each second
+    fn rate(&self) -> f64 {
+        self.uses as f64 / self.per.as_secs_f64()
+    }
+}
+
+/// the user, channel and guild a command was used by, used to find the buckets it draws from
+#[derive(Debug, Clone, Copy)]
+pub struct CooldownKey {
+    pub user_id: u64,
+    pub channel_id: u64,
+    pub guild_id: Option<u64>,
+}
+
+impl CooldownKey {
+    /// the id a cooldown with this scope is tracked by, `None` if it doesn't apply, such as guild cooldowns in a DM
+    fn id(&self, scope: CooldownScope) -> Option<u64> {
+        match scope {
+            CooldownScope::User => Some(self.user_id),
+            CooldownScope::Channel => Some(self.channel_id),
+            CooldownScope::Guild => self.guild_id,
+        }
+    }
+}
+
+/// the tokens left in a bucket, as of when it was last updated
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// the time the bucket takes to refill from empty
+    per: Duration,
+}
+
+impl Bucket {
+    /// refill the bucket with the tokens regained since it was last updated
+    fn refill(&mut self, cooldown: &Cooldown, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * cooldown.rate()).min(cooldown.uses as f64);
+        self.updated = now;
+    }
+}
+
+/// the buckets of every cooldown in use, and the number of times each command has been limited
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    buckets: Mutex<HashMap<(&'static str, CooldownScope, u64), Bucket>>,
+    hits: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl Cooldowns {
+    /// take a use of a command from each of its cooldowns, or if any are empty, the time until they all have a use again
+    pub fn take(
+        &self,
+        command: &'static str,
+        cooldowns: &[Cooldown],
+        key: CooldownKey,
+    ) -> Result<(), Duration> {
+        self.take_at(command, cooldowns, key, Instant::now())
+    }
+
+    /// take a use of a command as of `now`
+    fn take_at(
+        &self,
+        command: &'static str,
+        cooldowns: &[Cooldown],
+        key: CooldownKey,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // a use is only taken once every bucket is known to have one, so being limited by one doesn't drain the others
+        let mut wait = Duration::ZERO;
+        for cooldown in cooldowns {
+            let id = match key.id(cooldown.scope) {
+                Some(id) => id,
+                None => continue,
+            };
+            let bucket = buckets
+                .entry((command, cooldown.scope, id))
+                .or_insert(Bucket {
+                    tokens: cooldown.uses as f64,
+                    updated: now,
+                    per: cooldown.per,
+                });
+            bucket.refill(cooldown, now);
+            if bucket.tokens < 1.0 {
+                wait = wait.max(Duration::from_secs_f64(
+                    (1.0 - bucket.tokens) / cooldown.rate(),
+                ));
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        for cooldown in cooldowns {
+            if let Some(id) = key.id(cooldown.scope) {
+                if let Some(bucket) = buckets.get_mut(&(command, cooldown.scope, id)) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+
+        // buckets which have refilled are the same as ones which were never used, so they can be dropped
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < bucket.per);
+        }
+
+        Ok(())
+    }
+
+    /// count a use of a command which was limited, returning how many times it has been limited
+    pub fn record_hit(&self, command: &'static str) -> u64 {
+        let mut hits = self.hits.lock().unwrap();
+        let count = hits.entry(command).or_default();
+        *count += 1;
+        *count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Cooldown, CooldownKey, CooldownScope, Cooldowns};
+
+    /// a use by a user in a channel of a guild
+    fn key(user_id: u64, channel_id: u64, guild_id: Option<u64>) -> CooldownKey {
+        CooldownKey {
+            user_id,
+            channel_id,
+            guild_id,
+        }
+    }
+
+    /// check a wait is the expected number of seconds, give or take rounding
+    fn waits(result: Result<(), Duration>, seconds: f64) -> bool {
+        match result {
+            Err(wait) => (wait.as_secs_f64() - seconds).abs() < 1e-6,
+            Ok(()) => false,
+        }
+    }
+
+    #[test]
+    fn a_burst_is_allowed_until_the_bucket_is_empty() {
+        let cooldowns = Cooldowns::default();
+        let limits = [Cooldown::new(
+            CooldownScope::User,
+            3,
+            Duration::from_secs(30),
+        )];
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(
+                cooldowns.take_at("test", &limits, key(1, 2, Some(3)), now),
+                Ok(())
+            );
+        }
+        // a use is regained every 10 seconds
+        assert!(waits(
+            cooldowns.take_at("test", &limits, key(1, 2, Some(3)), now),
+            10.0
+        ));
+    }
+
+    #[test]
+    fn the_bucket_refills_over_time() {
+        let cooldowns = Cooldowns::default();
+        let limits = [Cooldown::new(
+            CooldownScope::User,
+            2,
+            Duration::from_secs(20),
+        )];
+        let now = Instant::now();
+        let take = |at: Duration| cooldowns.take_at("test", &limits, key(1, 2, None), now + at);
+
+        assert_eq!(take(Duration::ZERO), Ok(()));
+        assert_eq!(take(Duration::ZERO), Ok(()));
+        assert!(waits(take(Duration::from_secs(4)), 6.0));
+        assert_eq!(take(Duration::from_secs(10)), Ok(()));
+        assert!(waits(take(Duration::from_secs(10)), 10.0));
+
+        // a bucket never holds more than its uses, however long it is left
+        let later = Duration::from_secs(3600);
+        assert_eq!(take(later), Ok(()));
+        assert_eq!(take(later), Ok(()));
+        assert!(take(later).is_err());
+    }
+
+    #[test]
+    fn each_scope_is_tracked_by_its_own_key() {
+        let once = |scope| [Cooldown::new(scope, 1, Duration::from_secs(60))];
+        let now = Instant::now();
+
+        let cooldowns = Cooldowns::default();
+        let user = once(CooldownScope::User);
+        assert_eq!(
+            cooldowns.take_at("test", &user, key(1, 2, Some(3)), now),
+            Ok(())
+        );
+        assert!(cooldowns
+            .take_at("test", &user, key(1, 4, Some(5)), now)
+            .is_err());
+        assert_eq!(
+            cooldowns.take_at("test", &user, key(6, 2, Some(3)), now),
+            Ok(())
+        );
+
+        let cooldowns = Cooldowns::default();
+        let channel = once(CooldownScope::Channel);
+        assert_eq!(
+            cooldowns.take_at("test", &channel, key(1, 2, Some(3)), now),
+            Ok(())
+        );
+        assert!(cooldowns
+            .take_at("test", &channel, key(6, 2, Some(3)), now)
+            .is_err());
+        assert_eq!(
+            cooldowns.take_at("test", &channel, key(1, 4, Some(3)), now),
+            Ok(())
+        );
+
+        let cooldowns = Cooldowns::default();
+        let guild = once(CooldownScope::Guild);
+        assert_eq!(
+            cooldowns.take_at("test", &guild, key(1, 2, Some(3)), now),
+            Ok(())
+        );
+        assert!(cooldowns
+            .take_at("test", &guild, key(6, 4, Some(3)), now)
+            .is_err());
+        assert_eq!(
+            cooldowns.take_at("test", &guild, key(1, 2, Some(5)), now),
+            Ok(())
+        );
+        // guild cooldowns don't apply outside of a guild
+        assert_eq!(
+            cooldowns.take_at("test", &guild, key(1, 2, None), now),
+            Ok(())
+        );
+        assert_eq!(
+            cooldowns.take_at("test", &guild, key(1, 2, None), now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn commands_have_their_own_buckets() {
+        let cooldowns = Cooldowns::default();
+        let limits = [Cooldown::new(
+            CooldownScope::User,
+            1,
+            Duration::from_secs(60),
+        )];
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.take_at("first", &limits, key(1, 2, None), now),
+            Ok(())
+        );
+        assert!(cooldowns
+            .take_at("first", &limits, key(1, 2, None), now)
+            .is_err());
+        assert_eq!(
+            cooldowns.take_at("second", &limits, key(1, 2, None), now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn being_limited_by_one_cooldown_doesnt_drain_the_others() {
+        let cooldowns = Cooldowns::default();
+        let limits = [
+            Cooldown::new(CooldownScope::User, 1, Duration::from_secs(60)),
+            Cooldown::new(CooldownScope::Channel, 2, Duration::from_secs(60)),
+        ];
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.take_at("test", &limits, key(1, 2, None), now),
+            Ok(())
+        );
+        // the user is limited, which must leave the last use of the channel for someone else
+        assert!(waits(
+            cooldowns.take_at("test", &limits, key(1, 2, None), now),
+            60.0
+        ));
+        assert_eq!(
+            cooldowns.take_at("test", &limits, key(6, 2, None), now),
+            Ok(())
+        );
+        // the longest wait of the empty buckets is the one reported
+        assert!(waits(
+            cooldowns.take_at("test", &limits, key(1, 2, None), now),
+            60.0
+        ));
+        assert!(waits(
+            cooldowns.take_at("test", &limits, key(7, 2, None), now),
+            30.0
+        ));
+    }
+
+    #[test]
+    fn hits_are_counted_for_each_command() {
+        let cooldowns = Cooldowns::default();
+        assert_eq!(cooldowns.record_hit("first"), 1);
+        assert_eq!(cooldowns.record_hit("first"), 2);
+        assert_eq!(cooldowns.record_hit("second"), 1);
+    }
+}
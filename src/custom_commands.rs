@@ -0,0 +1,89 @@
+//! Custom commands, simple slash commands a guild defines which reply with a stored text
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+/// the most custom commands a guild can have, leaving room for the built in commands within discord's limit of 100
+pub const MAX_CUSTOM_COMMANDS: usize = 50;
+
+/// a slash command defined by a guild, replying with the same text whenever it is used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomCommand {
+    /// the name the command is used by, without the leading slash
+    pub name: String,
+    /// the description shown by discord next to the command
+    pub description: String,
+    /// the text the command replies with
+    pub content: String,
+    /// whether the text is sent in an embed rather than as a plain message
+    #[serde(default)]
+    pub embed: bool,
+    /// whether the reply is only shown to the member who used the command
+    #[serde(default)]
+    pub ephemeral: bool,
+}
+
+/// the names discord accepts for a slash command, 1 to 32 letters, numbers, dashes or underscores
+fn name_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^[-_\p{L}\p{N}\p{sc=Deva}\p{sc=Thai}]{1,32}$").unwrap())
+}
+
+/// check if a name can be used for a slash command, discord also requires letters to be lowercase where they have a lowercase form
+pub fn valid_name(name: &str) -> bool {
+    name_pattern().is_match(name) && name.to_lowercase() == name
+}
+
+/// get the custom commands a guild has defined
+pub async fn custom_commands(storage: &Storage, guild_id: u64) -> Vec<CustomCommand> {
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .map(|g| g.custom_commands.clone())
+        .unwrap_or_default()
+}
+
+/// find the custom command a guild has defined with a name
+pub async fn find_custom_command(
+    storage: &Storage,
+    guild_id: u64,
+    name: &str,
+) -> Option<CustomCommand> {
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| g.custom_commands.iter().find(|c| c.name == name).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::valid_name;
+
+    #[test]
+    fn names_follow_discord_rules() {
+        for name in ["oncall", "on-call_2", "über", "नमस्ते", "สวัสดี", "日本"]
+        {
+            assert!(valid_name(name), "{} should be valid", name);
+        }
+        let long = "a".repeat(33);
+        for name in [
+            "",
+            "OnCall",
+            "on call",
+            "on.call",
+            "deploy😀",
+            "e\u{301}",
+            &long,
+        ] {
+            assert!(!valid_name(name), "{} should be invalid", name);
+        }
+    }
+}
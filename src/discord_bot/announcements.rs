@@ -0,0 +1,201 @@
+//! Sending announcements made with `/say`, and sending those which were scheduled once they are due.
+
+use chrono::Utc;
+use log::{error, info};
+use serenity::builder::{
+    CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage, EditMessage,
+};
+
+use super::{
+    board::shorten,
+    mentions::allowed_mentions,
+    transport::{Transport, TransportError},
+};
+use crate::{
+    announcements::{
+        record_say, remove_attachments, take_due_messages, SayEmbed, SayLogAction, SayLogEntry,
+        SayMessage,
+    },
+    locale::{self, Locale, Text},
+    mentions::{neutralise, user_mentions_allowed},
+    state::AppState,
+    timezone::{discord_timestamp, TimestampStyle},
+};
+
+/// the longest the content of an entry is shown in the log
+const MAX_LOG_CONTENT_LENGTH: usize = 150;
+
+/// build the embed of an announcement
+fn create_embed(embed: &SayEmbed) -> CreateEmbed {
+    let mut create = CreateEmbed::new();
+    if let Some(title) = &embed.title {
+        create = create.title(neutralise(title));
+    }
+    if let Some(description) = &embed.description {
+        create = create.description(neutralise(description));
+    }
+    if let Some(colour) = embed.colour {
+        create = create.colour(colour);
+    }
+    create
+}
+
+/// build the message posting an announcement, only pinging the users it mentions if `users` is set.
+/// fails if the file of an attachment can't be read
+pub async fn create_message(message: &SayMessage, users: bool) -> std::io::Result<CreateMessage> {
+    let mut create = CreateMessage::new().allowed_mentions(allowed_mentions(users));
+    if let Some(content) = &message.content {
+        create = create.content(neutralise(content));
+    }
+    if let Some(embed) = &message.embed {
+        create = create.embed(create_embed(embed));
+    }
+    for attachment in &message.attachments {
+        let data = tokio::fs::read(&attachment.path).await?;
+        create = create.add_file(CreateAttachment::bytes(data, &attachment.filename));
+    }
+    Ok(create)
+}
+
+/// build the edit of an announcement, replacing only its text or its embed if they are provided
+pub fn edit_message(message: &SayMessage, users: bool) -> EditMessage {
+    let mut edit = EditMessage::new().allowed_mentions(allowed_mentions(users));
+    if let Some(content) = &message.content {
+        edit = edit.content(neutralise(content));
+    }
+    if let Some(embed) = &message.embed {
+        edit = edit.embed(create_embed(embed));
+    }
+    edit
+}
+
+/// describe an entry of the log, with a link to its message and what the message said
+pub fn render_log_entry(locale: Locale, guild_id: u64, entry: &SayLogEntry) -> String {
+    let text = match entry.action {
+        SayLogAction::Sent => Text::SayLogSent,
+        SayLogAction::Scheduled => Text::SayLogScheduled,
+        SayLogAction::Cancelled => Text::SayLogCancelled,
+        SayLogAction::Edited => Text::SayLogEdited,
+        SayLogAction::Deleted => Text::SayLogDeleted,
+    };
+    let mut line = format!(
+        "{} {}",
+        discord_timestamp(entry.at, TimestampStyle::ShortDateTime),
+        text.format(
+            locale,
+            &[
+                ("user", &entry.user_id.to_string()),
+                ("channel", &entry.channel_id.to_string()),
+            ],
+        )
+    );
+    if let Some(message_id) = entry.message_id {
+        line.push_str(&format!(
+            " (https://discord.com/channels/{}/{}/{})",
+            guild_id, entry.channel_id, message_id
+        ));
+    }
+    if !entry.content.is_empty() {
+        line.push_str(&format!(
+            "\n> {}",
+            neutralise(&shorten(&entry.content, MAX_LOG_CONTENT_LENGTH))
+        ));
+    }
+    line
+}
+
+/// record something the bot did on behalf of a member, and mirror it to the audit channel of the guild if one is set.
+/// the action has already happened, so a failure to record it is only logged
+pub async fn audit(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    guild_id: u64,
+    entry: SayLogEntry,
+) {
+    if let Err(e) = record_say(&app_state.storage, guild_id, entry.clone()).await {
+        error!(
+            "failed to record {:?} by {} in guild {}: {}",
+            entry.action, entry.user_id, guild_id, e
+        );
+    }
+
+    let audit_channel = app_state
+        .storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| g.audit_channel);
+    if let Some(channel_id) = audit_channel {
+        let locale = locale::guild_locale(&app_state.storage, guild_id).await;
+        let mirror = CreateMessage::new()
+            .embed(CreateEmbed::new().description(render_log_entry(locale, guild_id, &entry)))
+            .allowed_mentions(CreateAllowedMentions::new());
+        if let Err(e) = transport.send_message(channel_id, mirror).await {
+            error!(
+                "failed to mirror {:?} by {} to the audit channel of guild {}: {}",
+                entry.action, entry.user_id, guild_id, e
+            );
+        }
+    }
+}
+
+/// send the messages scheduled in a guild which are now due, telling their author about any which fail
+pub async fn send_due_messages(transport: &dyn Transport, app_state: &AppState, guild_id: u64) {
+    let due = match take_due_messages(&app_state.storage, guild_id, Utc::now()).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!(
+                "failed to take the scheduled messages of guild {}: {}",
+                guild_id, e
+            );
+            return;
+        }
+    };
+
+    let users = user_mentions_allowed(&app_state.storage, Some(guild_id)).await;
+    for scheduled in due {
+        let sent = match create_message(&scheduled.message, users).await {
+            Ok(message) => transport.send_message(scheduled.channel_id, message).await,
+            Err(e) => Err(TransportError::Other(e.to_string())),
+        };
+        // the message is no longer stored, whether or not it could be sent, so neither are its attachments
+        remove_attachments(&scheduled.message.attachments).await;
+        match sent {
+            Ok(message_id) => {
+                info!(
+                    "sent scheduled message {} to channel {} as {}",
+                    scheduled.id, scheduled.channel_id, message_id
+                );
+                let entry = SayLogEntry {
+                    action: SayLogAction::Sent,
+                    user_id: scheduled.author_id,
+                    at: Utc::now(),
+                    channel_id: scheduled.channel_id,
+                    message_id: Some(message_id),
+                    content: scheduled.message.summary(),
+                };
+                audit(transport, app_state, guild_id, entry).await;
+            }
+            Err(e) => {
+                error!(
+                    "failed to send scheduled message {} to channel {}: {}",
+                    scheduled.id, scheduled.channel_id, e
+                );
+                let locale =
+                    locale::resolve(&app_state.storage, Some(guild_id), scheduled.author_id, "")
+                        .await;
+                let notice = CreateMessage::new().content(
+                    Text::SayScheduledFailed
+                        .format(locale, &[("channel", &scheduled.channel_id.to_string())]),
+                );
+                if let Err(e) = transport.direct_message(scheduled.author_id, notice).await {
+                    error!(
+                        "failed to tell {} their scheduled message {} failed: {}",
+                        scheduled.author_id, scheduled.id, e
+                    );
+                }
+            }
+        }
+    }
+}
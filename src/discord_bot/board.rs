@@ -0,0 +1,561 @@
+//! The board, a single pinned message per guild which the bot keeps editing to show what each member of the
+//! roster is working on this week.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use log::{error, warn};
+use serenity::builder::{CreateAllowedMentions, CreateEmbed, CreateMessage, EditMessage};
+use tokio::sync::OnceCell;
+
+use super::transport::{GuildMember, Transport, TransportError};
+use crate::{
+    holidays::member_on_holiday,
+    locale::{self, Locale, Text},
+    mentions::neutralise,
+    standups::{BoardConfig, Submission, FINISH_QUESTION, THIS_WEEK_QUESTION},
+    state::AppState,
+    timezone::{discord_timestamp, start_of_week, user_timezone, TimestampStyle},
+};
+
+/// the longest an answer may be before it is shortened on the board
+const MAX_ANSWER_LENGTH: usize = 100;
+
+/// the longest the description of an embed may be
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// shorten some text to fit on a single line of the board
+pub fn shorten(text: &str, max: usize) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    match line.chars().count() > max || line.len() != text.len() {
+        true => format!("{}…", line.chars().take(max).collect::<String>()),
+        false => line.to_string(),
+    }
+}
+
+/// check if the latest submission of a member was made this week.
+/// "this week" is the member's own week, so a Monday update in Auckland isn't hidden from London
+async fn posted_this_week(app_state: &AppState, user_id: u64, latest: &Submission) -> bool {
+    let timezone = user_timezone(&app_state.storage, user_id).await;
+    latest.submitted_at >= start_of_week(timezone, Utc::now())
+}
+
+/// render the line shown for a single member
+async fn render_line(
+    app_state: &AppState,
+    locale: Locale,
+    user_id: u64,
+    latest: Option<&Submission>,
+) -> String {
+    let latest = match latest {
+        Some(latest) => latest,
+        None => return format!("<@{}> — {}", user_id, Text::BoardNoUpdate.get(locale)),
+    };
+
+    let this_week = posted_this_week(app_state, user_id, latest).await;
+
+    let mut parts = vec![];
+    match (this_week, latest.answer(THIS_WEEK_QUESTION)) {
+        (true, Some(answer)) => parts.push(neutralise(&shorten(answer, MAX_ANSWER_LENGTH))),
+        _ => parts.push(Text::BoardNoUpdate.get(locale).to_string()),
+    }
+    if this_week {
+        if let Some(finish) = latest.answer(FINISH_QUESTION) {
+            parts.push(
+                Text::BoardFinish.format(locale, &[("date", &neutralise(&shorten(finish, 40)))]),
+            );
+        }
+        if latest.is_blocked() {
+            parts.push(Text::BoardBlocked.get(locale).to_string());
+        }
+    }
+    parts.push(Text::BoardUpdated.format(
+        locale,
+        &[(
+            "time",
+            &discord_timestamp(latest.updated_at(), TimestampStyle::Relative),
+        )],
+    ));
+
+    format!("<@{}> — {}", user_id, parts.join(" · "))
+}
+
+/// the members of a guild, fetched the first time they are needed and then shared,
+/// so the board and panel refreshed after a submission only fetch them once between them
+#[derive(Default)]
+pub struct MemberCache {
+    members: OnceCell<Option<Vec<GuildMember>>>,
+}
+
+impl MemberCache {
+    /// find the members of a role who aren't bots, or `None` if the members of the guild couldn't be fetched
+    pub async fn role_members(
+        &self,
+        transport: &dyn Transport,
+        guild_id: u64,
+        role: u64,
+    ) -> Option<Vec<u64>> {
+        let members = self
+            .members
+            .get_or_init(|| async move {
+                match transport.members(guild_id).await {
+                    Ok(members) => Some(members),
+                    Err(e) => {
+                        warn!("unable to fetch roster for guild {}: {}", guild_id, e);
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()?;
+        Some(
+            members
+                .iter()
+                .filter(|m| !m.bot && m.roles.contains(&role))
+                .map(|m| m.user_id)
+                .collect(),
+        )
+    }
+}
+
+/// find the members shown on the board, either the members of the roster role, or everyone who has submitted
+async fn roster(
+    transport: &dyn Transport,
+    members: &MemberCache,
+    guild_id: u64,
+    roster_role: Option<u64>,
+    latest: &HashMap<u64, Submission>,
+) -> Vec<u64> {
+    let mut roster: Vec<u64> = match roster_role {
+        Some(role) => match members.role_members(transport, guild_id, role).await {
+            Some(members) => members,
+            None => latest.keys().copied().collect(),
+        },
+        None => latest.keys().copied().collect(),
+    };
+
+    // members with the most recent updates first
+    roster.sort_by_key(|id| std::cmp::Reverse(latest.get(id).map(|s| s.updated_at().timestamp())));
+    roster
+}
+
+/// re-render the board of a guild, posting and pinning a new board if the old message is gone
+pub async fn refresh_board(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    members: &MemberCache,
+    guild_id: u64,
+) {
+    let _guard = app_state.board_locks.lock(guild_id).await;
+
+    let (config, latest) = {
+        let data = app_state.storage.read().await;
+        let config = match data.guilds.get(&guild_id).and_then(|g| g.board.clone()) {
+            Some(config) => config,
+            None => return,
+        };
+
+        let mut latest: HashMap<u64, Submission> = HashMap::new();
+        for submission in data.submissions.values().filter(|s| s.guild_id == guild_id) {
+            let newer = latest
+                .get(&submission.user_id)
+                .map_or(true, |s| s.submitted_at < submission.submitted_at);
+            if newer {
+                latest.insert(submission.user_id, submission.clone());
+            }
+        }
+
+        (config, latest)
+    };
+
+    let locale = locale::guild_locale(&app_state.storage, guild_id).await;
+
+    let now = Utc::now();
+    let mut lines = vec![];
+    for user_id in roster(transport, members, guild_id, config.roster_role, &latest).await {
+        let submission = latest.get(&user_id);
+        // members on holiday are left off the board, unless they posted this week before they left
+        let posted = match submission {
+            Some(submission) => posted_this_week(app_state, user_id, submission).await,
+            None => false,
+        };
+        if !posted && member_on_holiday(&app_state.storage, guild_id, user_id, now).await {
+            continue;
+        }
+        lines.push(render_line(app_state, locale, user_id, submission).await);
+    }
+
+    let mut description = String::new();
+    for line in lines {
+        if description.chars().count() + line.chars().count() + 1 > MAX_DESCRIPTION_LENGTH {
+            break;
+        }
+        description.push_str(&line);
+        description.push('\n');
+    }
+    if description.is_empty() {
+        description = Text::BoardEmpty.get(locale).to_string();
+    }
+
+    let embed = CreateEmbed::new()
+        .title(Text::BoardTitle.get(locale))
+        .description(description);
+
+    let channel_id = config.channel_id;
+
+    if let Some(message_id) = config.message_id {
+        match transport
+            .edit_message(
+                channel_id,
+                message_id,
+                EditMessage::new().embed(embed.clone()),
+            )
+            .await
+        {
+            Ok(_) => return,
+            Err(TransportError::NotFound) => {
+                warn!("board for guild {} was deleted, recreating it", guild_id)
+            }
+            Err(e) => {
+                error!("unable to update board for guild {}: {}", guild_id, e);
+                return;
+            }
+        }
+    }
+
+    let message_id = match transport
+        .send_message(
+            channel_id,
+            CreateMessage::new()
+                .embed(embed)
+                .allowed_mentions(CreateAllowedMentions::new()),
+        )
+        .await
+    {
+        Ok(message_id) => message_id,
+        Err(e) => {
+            error!("unable to post board for guild {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    if let Err(e) = transport.pin_message(channel_id, message_id).await {
+        warn!("unable to pin board for guild {}: {}", guild_id, e);
+    }
+
+    let result = app_state
+        .storage
+        .update(|data| {
+            if let Some(board) = data
+                .guilds
+                .get_mut(&guild_id)
+                .and_then(|g| g.board.as_mut())
+            {
+                board.message_id = Some(message_id);
+            }
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("unable to store board for guild {}: {}", guild_id, e);
+    }
+}
+
+/// delete the message a board was posted as, once the board has been moved or cleared, which also unpins it
+pub async fn remove_board(transport: &dyn Transport, guild_id: u64, board: &BoardConfig) {
+    let message_id = match board.message_id {
+        Some(message_id) => message_id,
+        None => return,
+    };
+    match transport.delete_message(board.channel_id, message_id).await {
+        Ok(_) | Err(TransportError::NotFound) => {}
+        Err(e) => warn!("unable to remove old board of guild {}: {}", guild_id, e),
+    }
+}
+
+/// check if a deleted message was the board of a guild, and if so post it again
+pub async fn handle_deleted_message(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    guild_id: u64,
+    message_id: u64,
+) {
+    let is_board = app_state
+        .storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| g.board.as_ref())
+        .map_or(false, |b| b.message_id == Some(message_id));
+
+    if is_board {
+        refresh_board(transport, app_state, &MemberCache::default(), guild_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::Value;
+
+    use crate::{
+        discord_bot::{
+            commands::fixtures::{self, CHANNEL_ID, GUILD_ID},
+            transport::{GuildMember, RecordingTransport, Sent},
+        },
+        standups::{BoardConfig, Submission},
+        state::AppState,
+    };
+
+    use super::{handle_deleted_message, refresh_board, remove_board, shorten, MemberCache};
+
+    /// the message the board was last posted as in the tests which have posted it
+    const BOARD_ID: u64 = 900;
+
+    /// a submission made this week by a user, saying what they are working on
+    fn submission(id: u64, user_id: u64, this_week: &str) -> Submission {
+        Submission {
+            id,
+            guild_id: GUILD_ID,
+            channel_id: CHANNEL_ID,
+            message_id: None,
+            session_id: None,
+            user_id,
+            username: format!("user {}", user_id),
+            questions: vec![String::new(); 4],
+            answers: vec![
+                String::new(),
+                this_week.to_string(),
+                String::new(),
+                String::from("none"),
+            ],
+            submitted_at: Utc::now(),
+            edited_at: None,
+            references: vec![],
+        }
+    }
+
+    /// configure the board of the guild, and store some submissions
+    async fn store(
+        app_state: &AppState,
+        message_id: Option<u64>,
+        roster_role: Option<u64>,
+        submissions: Vec<Submission>,
+    ) {
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds.entry(GUILD_ID).or_default().board = Some(BoardConfig {
+                    channel_id: CHANNEL_ID,
+                    message_id,
+                    roster_role,
+                });
+                for submission in submissions {
+                    data.submissions.insert(submission.id, submission);
+                }
+            })
+            .await
+            .unwrap();
+    }
+
+    /// the message the board of the guild is stored as
+    async fn stored_board(app_state: &AppState) -> Option<u64> {
+        app_state.storage.read().await.guilds[&GUILD_ID]
+            .board
+            .as_ref()
+            .and_then(|b| b.message_id)
+    }
+
+    /// the description of the embed a board was sent with
+    fn description(body: &Value) -> &str {
+        body["embeds"][0]["description"].as_str().unwrap()
+    }
+
+    /// check the board was posted and pinned as a new message, returning its id
+    fn posted(sent: &[Sent]) -> u64 {
+        match sent {
+            [Sent::Message {
+                channel_id: CHANNEL_ID,
+                message_id,
+                body,
+            }, Sent::Pin {
+                channel_id: CHANNEL_ID,
+                message_id: pinned,
+            }] if pinned == message_id => {
+                assert_eq!(body["embeds"][0]["title"], "Who's working on what");
+                *message_id
+            }
+            other => panic!(
+                "expected the board to be posted and pinned, found {:?}",
+                other
+            ),
+        }
+    }
+
+    #[test]
+    fn long_and_multiline_answers_are_shortened() {
+        assert_eq!(shorten("short", 10), "short");
+        assert_eq!(shorten("a long answer", 6), "a long…");
+        assert_eq!(shorten("first\nsecond", 10), "first…");
+    }
+
+    #[tokio::test]
+    async fn a_new_board_is_posted_and_pinned() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(
+            &app_state,
+            None,
+            None,
+            vec![submission(1, 1, "shipping the board")],
+        )
+        .await;
+
+        refresh_board(&transport, &app_state, &MemberCache::default(), GUILD_ID).await;
+
+        let sent = transport.sent();
+        let message_id = posted(&sent);
+        match &sent[0] {
+            Sent::Message { body, .. } => {
+                assert!(description(body).starts_with("<@1> — shipping the board · "))
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(stored_board(&app_state).await, Some(message_id));
+    }
+
+    #[tokio::test]
+    async fn a_posted_board_is_edited() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(
+            &app_state,
+            Some(BOARD_ID),
+            None,
+            vec![submission(1, 1, "shipping the board")],
+        )
+        .await;
+
+        refresh_board(&transport, &app_state, &MemberCache::default(), GUILD_ID).await;
+
+        match transport.sent().as_slice() {
+            [Sent::Edit {
+                channel_id: CHANNEL_ID,
+                message_id: BOARD_ID,
+                body,
+            }] => assert!(description(body).contains("shipping the board")),
+            other => panic!("expected the board to be edited, found {:?}", other),
+        }
+        assert_eq!(stored_board(&app_state).await, Some(BOARD_ID));
+    }
+
+    #[tokio::test]
+    async fn a_deleted_board_is_posted_again() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, Some(BOARD_ID), None, vec![]).await;
+        transport.delete(BOARD_ID);
+
+        refresh_board(&transport, &app_state, &MemberCache::default(), GUILD_ID).await;
+
+        let message_id = posted(&transport.sent());
+        assert_ne!(message_id, BOARD_ID);
+        assert_eq!(stored_board(&app_state).await, Some(message_id));
+    }
+
+    #[tokio::test]
+    async fn a_guild_without_a_board_is_left_alone() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+
+        refresh_board(&transport, &app_state, &MemberCache::default(), GUILD_ID).await;
+
+        assert!(transport.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn the_roster_role_shows_members_who_havent_submitted() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(
+            &app_state,
+            Some(BOARD_ID),
+            Some(7),
+            vec![submission(1, 1, "shipping the board")],
+        )
+        .await;
+        transport.set_members(
+            GUILD_ID,
+            vec![
+                GuildMember {
+                    user_id: 1,
+                    bot: false,
+                    roles: vec![7],
+                },
+                GuildMember {
+                    user_id: 2,
+                    bot: false,
+                    roles: vec![7],
+                },
+                GuildMember {
+                    user_id: 3,
+                    bot: true,
+                    roles: vec![7],
+                },
+            ],
+        );
+
+        refresh_board(&transport, &app_state, &MemberCache::default(), GUILD_ID).await;
+
+        match transport.sent().as_slice() {
+            [Sent::Edit { body, .. }] => {
+                let lines: Vec<&str> = description(body).lines().collect();
+                assert_eq!(lines.len(), 2);
+                assert!(lines[0].starts_with("<@1> — shipping the board"));
+                assert_eq!(lines[1], "<@2> — _no update this week_");
+            }
+            other => panic!("expected the board to be edited, found {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn deleting_the_board_message_posts_it_again() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, Some(BOARD_ID), None, vec![]).await;
+
+        // other messages being deleted don't touch the board
+        handle_deleted_message(&transport, &app_state, GUILD_ID, BOARD_ID + 1).await;
+        assert!(transport.sent().is_empty());
+
+        transport.delete(BOARD_ID);
+        handle_deleted_message(&transport, &app_state, GUILD_ID, BOARD_ID).await;
+
+        let message_id = posted(&transport.sent());
+        assert_eq!(stored_board(&app_state).await, Some(message_id));
+    }
+
+    #[tokio::test]
+    async fn removing_a_board_deletes_its_message() {
+        let transport = RecordingTransport::default();
+        let mut board = BoardConfig {
+            channel_id: CHANNEL_ID,
+            message_id: None,
+            roster_role: None,
+        };
+
+        remove_board(&transport, GUILD_ID, &board).await;
+        assert!(transport.sent().is_empty());
+
+        board.message_id = Some(BOARD_ID);
+        remove_board(&transport, GUILD_ID, &board).await;
+        assert_eq!(
+            transport.sent(),
+            vec![Sent::Delete {
+                channel_id: CHANNEL_ID,
+                message_id: BOARD_ID,
+            }]
+        );
+    }
+}
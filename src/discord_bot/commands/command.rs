@@ -2,20 +2,56 @@ use serenity::{
     all::{AutocompleteOption, CommandInteraction, ComponentInteraction, ModalInteraction},
     async_trait,
     builder::{CreateAutocompleteResponse, CreateCommand},
-    model::{application::CommandType, Permissions},
-    prelude::Context,
+    model::{application::CommandType, guild::Member, Permissions},
 };
 
 use crate::{
+    access::Capability,
+    cooldown::{Cooldown, CooldownKey},
+    custom_commands::find_custom_command,
     discord_bot::commands::{
-        hide::HideCommand, ping::PingCommand, say::SayCommand, standup::StandupCommand,
+        customcmd::{custom_cooldowns, custom_response, CustomCmdCommand, CUSTOM_COMMAND_COOLDOWN},
+        export::ExportCommand,
+        hide::HideCommand,
+        history::HistoryCommand,
+        holidays::HolidaysCommand,
+        language::LanguageCommand,
+        modules::ModulesCommand,
+        permissions::PermissionsCommand,
+        ping::PingCommand,
+        save_standup::SaveStandupCommand,
+        say::SayCommand,
+        standup::StandupCommand,
+        standup_config::StandupConfigCommand,
+        timezone::TimezoneCommand,
+        user_history::UserHistoryCommand,
     },
+    discord_bot::{custom_id::ComponentId, transport::Transport},
+    locale::{self, command_localizations, Locale},
+    mentions::user_mentions_allowed,
+    modules::{disabled_modules, Module},
     state::AppState,
 };
 
-use super::util::CommandResponse;
+use super::{
+    options::OptionError,
+    subcommand::SubCommand,
+    util::{
+        capability_denied, component_expired, cooldown_denied, member_has_capability,
+        module_disabled, CommandResponse,
+    },
+};
 
-const DEFAULT_PERMISSIONS: Permissions = Permissions::SEND_MESSAGES;
+/// the permissions a member needs to use a command, unless the command requires others
+pub const DEFAULT_PERMISSIONS: Permissions = Permissions::SEND_MESSAGES;
+
+/// restrict a command to guilds and to members with the provided permissions, until a server admin changes them.
+/// every command, built in or custom, is registered through this
+pub fn guild_command(command: CreateCommand, permissions: Permissions) -> CreateCommand {
+    command
+        .default_member_permissions(permissions)
+        .dm_permission(false)
+}
 
 /// A command that can be used in a guild, restricted to administrators
 #[async_trait]
@@ -23,18 +59,62 @@ pub trait Command<'a>: TryFrom<&'a CommandInteraction> {
     /// Get the name of the command
     fn name() -> &'static str;
 
-    /// Get the description of the command
+    /// Get the description of the command, only shown for slash commands
     fn description() -> &'static str;
 
+    /// Get the type of this command, a slash command unless it is in the context menu of a user or message
+    fn kind() -> CommandType {
+        CommandType::ChatInput
+    }
+
     /// Get the discord defined usage of this command, to be sent to discord
-    fn get_application_command_options(command: CreateCommand) -> CreateCommand;
+    fn get_application_command_options(command: CreateCommand) -> CreateCommand {
+        command
+    }
+
+    /// Get the permissions a member needs to use this command, until a server admin changes them
+    fn default_member_permissions() -> Permissions {
+        DEFAULT_PERMISSIONS
+    }
+
+    /// Get the capability a member needs to use this command, or any of its components and modals
+    fn capability() -> Option<Capability> {
+        None
+    }
+
+    /// Get the capability a member needs for this invocation in particular, checked once its options are parsed
+    fn invocation_capability(&self, _interaction: &CommandInteraction) -> Option<Capability> {
+        None
+    }
+
+    /// Get the module this command belongs to, which guilds can disable unless it is a core module
+    fn module() -> Module {
+        Module::Core
+    }
+
+    /// Whether the dispatcher may defer this command if it is slow to respond, commands which
+    /// respond to the interaction themselves, or respond with a modal, can't be deferred
+    fn deferrable() -> bool {
+        true
+    }
+
+    /// Get the limits on how often this command can be used, at most one for each scope
+    fn cooldowns() -> Vec<Cooldown> {
+        vec![]
+    }
+
+    /// Get the subcommands and subcommand groups of this command, if it has any.
+    /// a command with subcommands should parse them in `TryFrom` with `route`
+    fn subcommands() -> Vec<SubCommand<Self, <Self as TryFrom<&'a CommandInteraction>>::Error>> {
+        vec![]
+    }
 
     /// handle the execution of this application command
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
         app_state: &'b AppState,
-        context: &'b Context,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
@@ -46,45 +126,66 @@ pub trait AutocompleteCommand<'a>: Command<'a> {
         command: &'c CommandInteraction,
         message: &'c AutocompleteOption,
         app_state: &'c AppState,
-        context: &'c Context,
+        transport: &'c dyn Transport,
     ) -> Result<CreateAutocompleteResponse, CommandResponse>;
 }
 
 /// A command with a followup interaction component which must be handled
 #[async_trait]
 pub trait InteractionCommand<'a>: Command<'a> {
-    /// validate if this message is related to a given command
-    async fn answerable<'b>(
-        interaction: &'b ComponentInteraction,
-        app_state: &'b AppState,
-        context: &'b Context,
-    ) -> bool;
+    /// the namespace of the custom ids of the components and modals this command handles
+    fn namespace() -> &'static str;
+
+    /// Whether the dispatcher may defer a component or modal of this command if it is slow to respond,
+    /// only those which are answered by the response returned from the handler can be deferred
+    fn deferrable(_id: &ComponentId) -> bool {
+        false
+    }
 
     /// handle the generated interaction for this command
     async fn interaction<'b>(
+        id: ComponentId,
         interaction: &'b ComponentInteraction,
         app_state: &'b AppState,
-        context: &'b Context,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
 #[async_trait]
 pub trait ModalSubmit<'a>: Command<'a> + InteractionCommand<'a> {
-    /// check if this modal submission is FOR this command
-    async fn modal_submit<'b>(
-        modal: &'b ModalInteraction,
-        app_state: &'b AppState,
-        context: &'b Context,
-    ) -> bool;
-
     /// handle the modal submit for this command
     async fn handle_modal_submit<'b>(
+        id: ComponentId,
         modal: &'b ModalInteraction,
         app_state: &'b AppState,
-        context: &'b Context,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
+/// an error from parsing the options of a command, turned into the failure sent back to the member
+pub trait ParseFailure {
+    fn into_response(self, command: &str, locale: Locale) -> CommandResponse;
+}
+
+impl ParseFailure for String {
+    fn into_response(self, command: &str, _: Locale) -> CommandResponse {
+        CommandResponse::InternalFailure(format!(
+            "Unable to parse options of {}: {}",
+            command, self
+        ))
+    }
+}
+
+/// options which fail validation were provided by the member, so they are told why rather than logged
+impl ParseFailure for OptionError {
+    fn into_response(self, command: &str, locale: Locale) -> CommandResponse {
+        match self {
+            OptionError::Malformed(reason) => reason.into_response(command, locale),
+            e => CommandResponse::BasicFailure(e.reason(locale)),
+        }
+    }
+}
+
 // #[async_trait]
 // pub trait PaginatedResponse<'a>: Command<'a> {
 //     /// Get the number of pages this response has
@@ -94,39 +195,152 @@ pub trait ModalSubmit<'a>: Command<'a> + InteractionCommand<'a> {
 //     fn get_page(&self, page: usize) -> CommandResponse<'a>;
 // }
 
+/// invoke one of the macros below with the provided arguments followed by every command type, so the commands are only listed once
+macro_rules! with_commands {
+    ( $mac:ident ! ( $( $arg:expr ),* $(,)? ) ) => {
+        $mac!(
+            $( $arg, )*
+            CustomCmdCommand,
+            ExportCommand,
+            HideCommand,
+            HistoryCommand,
+            HolidaysCommand,
+            LanguageCommand,
+            ModulesCommand,
+            PermissionsCommand,
+            PingCommand,
+            SaveStandupCommand,
+            SayCommand,
+            StandupCommand,
+            StandupConfigCommand,
+            TimezoneCommand,
+            UserHistoryCommand,
+        )
+    };
+}
+
 /// match against a list of provided command types, and generate an application command that can be registered with discord
 macro_rules! application_command {
-    ( $base:expr, $( $x:ty ),* $(,)? ) => {
+    ( $base:expr, $disabled:expr, $( $x:ty ),* $(,)? ) => {
         {
             /// ensures that the provided type has relevant traits
-            fn assert_command<'a, T: Command<'a, Error=String>>() {}
+            fn assert_command<'a, T: Command<'a>>()
+            where
+                <T as TryFrom<&'a CommandInteraction>>::Error: ParseFailure,
+            {
+            }
             $(
                 assert_command::<$x>();
-                let mut v_base = <$x>::get_application_command_options(CreateCommand::new("unnamed command"));
-                v_base = v_base
-                    .name(<$x>::name())
-                    .description(<$x>::description())
-                    .default_member_permissions(DEFAULT_PERMISSIONS)
-                    .dm_permission(false)
-                    .kind(CommandType::ChatInput);
-                $base.push(v_base);
+                if !$disabled.contains(&<$x>::module()) {
+                    let mut v_base = <$x>::get_application_command_options(CreateCommand::new("unnamed command"));
+                    for subcommand in <$x>::subcommands() {
+                        v_base = v_base.add_option(subcommand.build());
+                    }
+                    // discord rejects context menu commands which have a description
+                    let slash_command = <$x>::kind() == CommandType::ChatInput;
+                    v_base = guild_command(v_base, <$x>::default_member_permissions())
+                        .name(<$x>::name())
+                        .kind(<$x>::kind());
+                    if slash_command {
+                        v_base = v_base.description(<$x>::description());
+                    }
+                    for (locale, name, description) in command_localizations(<$x>::name()) {
+                        for code in locale.discord_codes() {
+                            v_base = v_base.name_localized(*code, *name);
+                            if slash_command {
+                                v_base = v_base.description_localized(*code, *description);
+                            }
+                        }
+                    }
+                    $base.push(v_base);
+                }
             )*
         }
     };
 }
 
+/// deny an interaction before its handler runs, if it needs a capability the member doesn't have
+macro_rules! require_capability {
+    ( $capability:expr, $cmd:expr, $state:expr ) => {
+        if let Some(capability) = $capability {
+            let guild_id = ($cmd).guild_id.map(u64::from);
+            let member = ($cmd).member.as_ref().map(|m| -> &Member { m });
+            if !member_has_capability($state, guild_id, member, capability).await {
+                let user_id: u64 = ($cmd).user.id.into();
+                let locale =
+                    locale::resolve(&$state.storage, guild_id, user_id, &($cmd).locale).await;
+                return Err(capability_denied(locale, user_id, capability));
+            }
+        }
+    };
+}
+
+/// deny an interaction before its handler runs, if its command is in a module the guild has disabled
+macro_rules! require_module {
+    ( $x:ty, $cmd:expr, $state:expr ) => {
+        let guild_id = ($cmd).guild_id.map(u64::from);
+        if disabled_modules(&$state.storage, guild_id)
+            .await
+            .contains(&<$x>::module())
+        {
+            let user_id: u64 = ($cmd).user.id.into();
+            let locale = locale::resolve(&$state.storage, guild_id, user_id, &($cmd).locale).await;
+            return Err(module_disabled(locale, <$x>::name(), <$x>::module()));
+        }
+    };
+}
+
+/// deny a command before its handler runs, if it has been used too often by the member, in the channel or in the guild
+macro_rules! require_cooldown {
+    ( $name:expr, $cooldowns:expr, $cmd:expr, $state:expr ) => {
+        let cooldowns = $cooldowns;
+        if !cooldowns.is_empty() {
+            let key = CooldownKey {
+                user_id: ($cmd).user.id.into(),
+                channel_id: ($cmd).channel_id.into(),
+                guild_id: ($cmd).guild_id.map(u64::from),
+            };
+            if let Err(wait) = $state.cooldowns.take($name, &cooldowns, key) {
+                let hits = $state.cooldowns.record_hit($name);
+                let locale =
+                    locale::resolve(&$state.storage, key.guild_id, key.user_id, &($cmd).locale)
+                        .await;
+                return Err(cooldown_denied(locale, $name, key.user_id, wait, hits));
+            }
+        }
+    };
+}
+
 /// match against a list of provided command types, and produce a response which can be sent to the user
 macro_rules! command {
-    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+    ( $cmd:expr, $state:expr, $transport:expr, $( $x:ty ),* $(,)? ) => {
         {
             /// ensures that the provided type has relevant traits
-            fn assert_command<'a, T: Command<'a, Error=String>>() {}
+            fn assert_command<'a, T: Command<'a>>()
+            where
+                <T as TryFrom<&'a CommandInteraction>>::Error: ParseFailure,
+            {
+            }
             $(
                 assert_command::<$x>();
                 if ($cmd).data.name == <$x>::name() {
-                    if let Ok(value) = <$x>::try_from($cmd) {
-                        return value.handle_application_command($cmd, $state, $context).await
-                    }
+                    require_module!($x, $cmd, $state);
+                    require_capability!(<$x>::capability(), $cmd, $state);
+
+                    let value = match <$x>::try_from($cmd) {
+                        Ok(value) => value,
+                        Err(e) => {
+                            let guild_id = ($cmd).guild_id.map(u64::from);
+                            let user_id: u64 = ($cmd).user.id.into();
+                            let locale =
+                                locale::resolve(&$state.storage, guild_id, user_id, &($cmd).locale).await;
+                            return Err(e.into_response(<$x>::name(), locale));
+                        }
+                    };
+                    require_capability!(value.invocation_capability($cmd), $cmd, $state);
+                    // a use is only taken once the command is known to run, so a refused invocation isn't counted
+                    require_cooldown!(<$x>::name(), <$x>::cooldowns(), $cmd, $state);
+                    return value.handle_application_command($cmd, $state, $transport).await;
                 }
             )*
             Err(CommandResponse::InternalFailure(String::from("Unsupported Command")))
@@ -134,36 +348,106 @@ macro_rules! command {
     };
 }
 
+/// match against a list of provided command types, and check if the command invoked may be deferred
+macro_rules! deferrable {
+    ( $cmd:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            $(
+                if ($cmd).data.name == <$x>::name() {
+                    return <$x>::deferrable();
+                }
+            )*
+            false
+        }
+    };
+}
+
+/// match against a list of provided command types, and check if a name belongs to one of them
+macro_rules! is_builtin {
+    ( $name:expr, $( $x:ty ),* $(,)? ) => {
+        false $( || $name == <$x>::name() )*
+    };
+}
+
+/// route a custom id to the provided interaction command type which handles its namespace, and check if it may be deferred
+macro_rules! deferrable_component {
+    ( $custom_id:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            let id = match ComponentId::decode($custom_id) {
+                Ok(id) => id,
+                Err(_) => return false,
+            };
+            $(
+                if id.namespace() == <$x>::namespace() {
+                    return <$x>::deferrable(&id);
+                }
+            )*
+            false
+        }
+    };
+}
+
 /// match against a list of provided autocomplete command types, and produce a response which can be sent to the user
-// macro_rules! autocomplete {
-//     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
-//         {
-//             /// ensures that the provided type has relevant traits
-//             fn assert_autocomplete<'a, T: AutocompleteCommand<'a, Error=String>>() {}
-//             $(
-//                 assert_autocomplete::<$x>();
-//                 if ($cmd).data.name == <$x>::name() {
-//                     return match $cmd.data.autocomplete() {
-//                         Some(data) => <$x>::autocomplete($cmd, &data, $state, $context).await,
-//                         None => Err(CommandResponse::InternalFailure(String::from("No Autocomplete Data Provided")))
-//                     }
-//                 }
-//             )*
-//             Err(CommandResponse::InternalFailure(String::from("Unsupported Autocomplete Command")))
-//         }
-//     };
-// }
+macro_rules! autocomplete {
+    ( $cmd:expr, $state:expr, $transport:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_autocomplete<'a, T: AutocompleteCommand<'a>>()
+            where
+                <T as TryFrom<&'a CommandInteraction>>::Error: ParseFailure,
+            {
+            }
+            $(
+                assert_autocomplete::<$x>();
+                if ($cmd).data.name == <$x>::name() {
+                    require_module!($x, $cmd, $state);
+                    return match $cmd.data.autocomplete() {
+                        Some(data) => <$x>::autocomplete($cmd, &data, $state, $transport).await,
+                        None => Err(CommandResponse::InternalFailure(String::from("No Autocomplete Data Provided")))
+                    }
+                }
+            )*
+            Err(CommandResponse::InternalFailure(String::from("Unsupported Autocomplete Command")))
+        }
+    };
+}
+
+/// decode the custom id of a component or modal, replying that it has expired if it can't be
+macro_rules! decode_custom_id {
+    ( $cmd:expr, $state:expr ) => {
+        match ComponentId::decode(&($cmd).data.custom_id) {
+            Ok(id) => id,
+            Err(e) => {
+                let locale = locale::resolve(
+                    &$state.storage,
+                    ($cmd).guild_id.map(u64::from),
+                    ($cmd).user.id.into(),
+                    &($cmd).locale,
+                )
+                .await;
+                return Err(component_expired(locale, &($cmd).data.custom_id, e));
+            }
+        }
+    };
+}
 
-/// match against a list of provided interaction command types, and produce a response which can be sent to the user
+/// route a component to the provided interaction command type which handles its namespace, and produce a response which can be sent to the user
 macro_rules! interaction {
-    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+    ( $cmd:expr, $state:expr, $transport:expr, $( $x:ty ),* $(,)? ) => {
         {
             /// ensures that the provided type has relevant traits
-            fn assert_interaction<'a, T: InteractionCommand<'a, Error=String>>() {}
+            fn assert_interaction<'a, T: InteractionCommand<'a>>()
+            where
+                <T as TryFrom<&'a CommandInteraction>>::Error: ParseFailure,
+            {
+            }
+            let id = decode_custom_id!($cmd, $state);
             $(
                 assert_interaction::<$x>();
-                if <$x>::answerable($cmd, $state, $context).await {
-                    return <$x>::interaction($cmd, $state, $context).await
+                if id.namespace() == <$x>::namespace() {
+                    require_module!($x, $cmd, $state);
+                    require_capability!(<$x>::capability(), $cmd, $state);
+                    return <$x>::interaction(id, $cmd, $state, $transport).await
                 }
             )*
             Err(CommandResponse::InternalFailure(String::from("Unsupported Interaction Command")))
@@ -171,16 +455,23 @@ macro_rules! interaction {
     };
 }
 
-/// match against a list of provided modal submit command types, and produce a response which can be sent to the user
+/// route a modal to the provided modal submit command type which handles its namespace, and produce a response which can be sent to the user
 macro_rules! modal {
-    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+    ( $cmd:expr, $state:expr, $transport:expr, $( $x:ty ),* $(,)? ) => {
         {
             /// ensures that the provided type has relevant traits
-            fn assert_modal<'a, T: ModalSubmit<'a, Error=String>>() {}
+            fn assert_modal<'a, T: ModalSubmit<'a>>()
+            where
+                <T as TryFrom<&'a CommandInteraction>>::Error: ParseFailure,
+            {
+            }
+            let id = decode_custom_id!($cmd, $state);
             $(
                 assert_modal::<$x>();
-                if <$x>::modal_submit($cmd, $state, $context).await {
-                    return <$x>::handle_modal_submit($cmd, $state, $context).await
+                if id.namespace() == <$x>::namespace() {
+                    require_module!($x, $cmd, $state);
+                    require_capability!(<$x>::capability(), $cmd, $state);
+                    return <$x>::handle_modal_submit(id, $cmd, $state, $transport).await
                 }
             )*
             Err(CommandResponse::InternalFailure(String::from("Unsupported Modal Submit Command")))
@@ -188,58 +479,265 @@ macro_rules! modal {
     };
 }
 
-pub fn application_command() -> Vec<CreateCommand> {
+/// build the definitions of every command, leaving out those in disabled modules
+pub fn application_command(disabled: &[Module]) -> Vec<CreateCommand> {
     let mut base = vec![];
-    application_command!(
-        &mut base,
-        HideCommand,
-        PingCommand,
-        SayCommand,
-        StandupCommand,
-    );
+    with_commands!(application_command!(&mut base, disabled));
     base
 }
 
 pub async fn command<'a>(
     command: &'a CommandInteraction,
     app_state: &'a AppState,
-    context: &'a Context,
+    transport: &'a dyn Transport,
 ) -> Result<CommandResponse, CommandResponse> {
-    command!(
-        command,
-        app_state,
-        context,
-        HideCommand,
-        PingCommand,
-        SayCommand,
-        StandupCommand,
-    )
+    // built in names can't be used by custom commands, so only other names are looked up
+    if let Some(guild_id) = command.guild_id.map(u64::from) {
+        if !is_builtin(&command.data.name) {
+            if let Some(custom) =
+                find_custom_command(&app_state.storage, guild_id, &command.data.name).await
+            {
+                if disabled_modules(&app_state.storage, Some(guild_id))
+                    .await
+                    .contains(&Module::Utility)
+                {
+                    let locale = locale::resolve(
+                        &app_state.storage,
+                        Some(guild_id),
+                        command.user.id.into(),
+                        &command.locale,
+                    )
+                    .await;
+                    return Err(module_disabled(locale, &custom.name, Module::Utility));
+                }
+                require_cooldown!(
+                    CUSTOM_COMMAND_COOLDOWN,
+                    custom_cooldowns(),
+                    command,
+                    app_state
+                );
+                let users = user_mentions_allowed(&app_state.storage, Some(guild_id)).await;
+                return Ok(custom_response(&custom, users));
+            }
+        }
+    }
+
+    with_commands!(command!(command, app_state, transport))
+}
+
+/// check if a name belongs to a built in command, which custom commands can't reuse
+pub fn is_builtin(name: &str) -> bool {
+    with_commands!(is_builtin!(name))
+}
+
+pub fn deferrable(command: &CommandInteraction) -> bool {
+    with_commands!(deferrable!(command))
 }
 
-#[allow(dead_code, unused_variables)]
 pub async fn autocomplete<'a>(
     command: &'a CommandInteraction,
     app_state: &'a AppState,
-    context: &'a Context,
+    transport: &'a dyn Transport,
 ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-    // autocomplete!(command, app_state, context)
-    Err(CommandResponse::InternalFailure(String::from(
-        "Unsupported Autocomplete Command",
-    )))
+    autocomplete!(
+        command,
+        app_state,
+        transport,
+        HistoryCommand,
+        TimezoneCommand,
+    )
+}
+
+/// check if a component or modal may be deferred, given its custom id
+pub fn deferrable_component(custom_id: &str) -> bool {
+    deferrable_component!(custom_id, StandupCommand,)
 }
 
 pub async fn interaction<'a>(
     command: &'a ComponentInteraction,
     app_state: &'a AppState,
-    context: &'a Context,
+    transport: &'a dyn Transport,
 ) -> Result<CommandResponse, CommandResponse> {
-    interaction!(command, app_state, context, StandupCommand,)
+    interaction!(command, app_state, transport, StandupCommand,)
 }
 
 pub async fn handle_modal<'a>(
     modal: &'a ModalInteraction,
     app_state: &'a AppState,
-    context: &'a Context,
+    transport: &'a dyn Transport,
 ) -> Result<CommandResponse, CommandResponse> {
-    modal!(modal, app_state, context, StandupCommand,)
+    modal!(modal, app_state, transport, StandupCommand,)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serenity::model::Permissions;
+
+    use crate::{
+        access::Capability,
+        custom_commands::CustomCommand,
+        discord_bot::{
+            commands::{customcmd::custom_application_commands, fixtures},
+            transport::RecordingTransport,
+        },
+    };
+
+    use super::{application_command, command, CommandResponse};
+
+    /// check a response is the denial for a missing capability
+    fn denied(res: &Result<CommandResponse, CommandResponse>, capability: Capability) -> bool {
+        match res {
+            Err(CommandResponse::ComplexFailure { log_message, .. }) => {
+                log_message.ends_with(capability.name())
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn every_command_is_guild_only_with_its_default_permissions() {
+        let custom = CustomCommand {
+            name: String::from("rules"),
+            description: String::from("The rules"),
+            content: String::from("Be nice"),
+            embed: false,
+            ephemeral: false,
+        };
+        let commands = application_command(&[])
+            .into_iter()
+            .chain(custom_application_commands(&[custom]))
+            .map(|c| serde_json::to_value(c).unwrap())
+            .collect::<Vec<_>>();
+
+        let permissions = |name: &str| {
+            let command = commands.iter().find(|c| c["name"] == name).unwrap();
+            command["default_member_permissions"].clone()
+        };
+        assert!(commands.iter().all(|c| c["dm_permission"] == false));
+        assert_eq!(
+            permissions("say"),
+            json!(Permissions::MANAGE_GUILD.bits().to_string())
+        );
+        assert_eq!(
+            permissions("standup-config"),
+            json!(Permissions::MANAGE_GUILD.bits().to_string())
+        );
+        assert_eq!(
+            permissions("standup"),
+            json!(Permissions::SEND_MESSAGES.bits().to_string())
+        );
+        assert_eq!(
+            permissions("rules"),
+            json!(Permissions::SEND_MESSAGES.bits().to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_options_of_a_subcommand_are_explained_to_the_member() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let mut interaction = fixtures::command(
+            3,
+            "say",
+            json!([{ "name": "log", "type": 1, "options": [
+                { "name": "page", "type": 4, "value": 0 },
+            ] }]),
+        );
+        interaction.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+
+        let res = command(&interaction, &app_state, &transport).await;
+        assert!(
+            matches!(&res, Err(CommandResponse::BasicFailure(reason)) if reason == "`page` must be at least 1."),
+            "{:?}",
+            res
+        );
+    }
+
+    #[tokio::test]
+    async fn refused_invocations_dont_use_up_the_cooldown() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let scheduled = |id: u64, permissions: Permissions| {
+            let mut interaction = fixtures::command(
+                id,
+                "say",
+                json!([{ "name": "scheduled", "type": 1, "options": [] }]),
+            );
+            interaction.member.as_mut().unwrap().permissions = Some(permissions);
+            interaction
+        };
+        let limited = |res: &Result<CommandResponse, CommandResponse>| matches!(res, Err(CommandResponse::ComplexFailure { log_message, .. }) if log_message.contains("limited using /say"));
+
+        // more refusals than the member has uses of /say
+        for id in 0..5 {
+            let res = command(&scheduled(id, Permissions::empty()), &app_state, &transport).await;
+            assert!(denied(&res, Capability::SayUse), "{:?}", res);
+        }
+        let res = command(
+            &scheduled(5, Permissions::MANAGE_GUILD),
+            &app_state,
+            &transport,
+        )
+        .await;
+        assert!(!limited(&res), "{:?}", res);
+
+        // uses which run are limited as before
+        for id in 6..8 {
+            let res = command(
+                &scheduled(id, Permissions::MANAGE_GUILD),
+                &app_state,
+                &transport,
+            )
+            .await;
+            assert!(!limited(&res), "{:?}", res);
+        }
+        let res = command(
+            &scheduled(8, Permissions::MANAGE_GUILD),
+            &app_state,
+            &transport,
+        )
+        .await;
+        assert!(limited(&res), "{:?}", res);
+    }
+
+    #[tokio::test]
+    async fn looking_up_another_member_needs_the_view_private_capability() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+
+        let own = fixtures::command(
+            3,
+            "history",
+            json!([{ "name": "member", "type": 6, "value": fixtures::USER_ID.to_string() }]),
+        );
+        let res = command(&own, &app_state, &transport).await;
+        assert!(res.is_ok(), "{:?}", res);
+
+        let mut other = fixtures::command(
+            4,
+            "history",
+            json!([{ "name": "member", "type": 6, "value": "99" }]),
+        );
+        let res = command(&other, &app_state, &transport).await;
+        assert!(denied(&res, Capability::StandupViewPrivate), "{:?}", res);
+
+        other.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+        let res = command(&other, &app_state, &transport).await;
+        assert!(res.is_ok(), "{:?}", res);
+    }
+
+    #[tokio::test]
+    async fn exporting_needs_the_export_capability() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+
+        let mut interaction = fixtures::command(3, "export", json!([]));
+        let res = command(&interaction, &app_state, &transport).await;
+        assert!(denied(&res, Capability::StandupExport), "{:?}", res);
+
+        interaction.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+        let res = command(&interaction, &app_state, &transport).await;
+        assert!(!denied(&res, Capability::StandupExport), "{:?}", res);
+    }
 }
@@ -0,0 +1,649 @@
+use std::time::Duration;
+
+use serenity::{
+    all::{CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::Permissions,
+};
+
+use crate::{
+    cooldown::{Cooldown, CooldownScope},
+    custom_commands::{custom_commands, valid_name, CustomCommand, MAX_CUSTOM_COMMANDS},
+    discord_bot::{
+        mentions::allowed_mentions,
+        registration::{request_sync, try_sync_guild},
+        transport::{Transport, TransportError},
+    },
+    locale::{self, Text},
+    mentions::neutralise,
+    modules::Module,
+    state::AppState,
+};
+
+use super::{
+    command::{guild_command, is_builtin, Command, DEFAULT_PERMISSIONS},
+    subcommand::{route, Invocation, SubCommand},
+    util::{has_permission, CommandResponse, FailureMessageKind},
+};
+
+/// the longest description discord accepts for a command
+const MAX_DESCRIPTION_LENGTH: u16 = 100;
+
+/// the longest text a custom command can reply with, the limit of a plain message
+const MAX_CONTENT_LENGTH: u16 = 2000;
+
+/// the cooldown bucket shared by the custom commands of a guild, which are counted together
+pub const CUSTOM_COMMAND_COOLDOWN: &str = "custom-command";
+
+/// a change to, or a request for, the custom commands of a guild
+enum CustomCmdAction {
+    /// define a new custom command
+    Create(CustomCommand),
+    /// change the fields provided of an existing custom command
+    Edit {
+        name: String,
+        description: Option<String>,
+        content: Option<String>,
+        embed: Option<bool>,
+        ephemeral: Option<bool>,
+    },
+    /// remove a custom command
+    Delete(String),
+    /// list the custom commands of the guild
+    List,
+}
+
+pub struct CustomCmdCommand {
+    action: CustomCmdAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for CustomCmdCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+/// get the name of the custom command a subcommand changes.
+/// names are matched case insensitively, as discord only allows lowercase names
+fn name(invocation: &Invocation) -> Result<String, String> {
+    invocation
+        .str("name")
+        .map(|n| n.trim().trim_start_matches('/').to_lowercase())
+        .ok_or_else(|| String::from("No name provided"))
+}
+
+/// the options setting the reply of a custom command, required when it is created and optional when it is edited
+fn reply_options(
+    subcommand: SubCommand<CustomCmdCommand>,
+    required: bool,
+) -> SubCommand<CustomCmdCommand> {
+    subcommand
+        .option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "description",
+                "The description shown next to the command",
+            )
+            .required(required)
+            .max_length(MAX_DESCRIPTION_LENGTH),
+        )
+        .option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "content",
+                "The text the command replies with",
+            )
+            .required(required)
+            .max_length(MAX_CONTENT_LENGTH),
+        )
+        .option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "embed",
+                "Whether to reply with an embed rather than a plain message",
+            )
+            .required(false),
+        )
+        .option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "ephemeral",
+                "Whether the reply is only shown to whoever used the command",
+            )
+            .required(false),
+        )
+}
+
+/// the name option taken by every subcommand changing a custom command
+fn name_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "name",
+        "The name of the command, without the slash",
+    )
+    .required(true)
+    .max_length(32)
+}
+
+/// put a custom command back as it was before a change, at the position it had, or remove it if it didn't exist
+fn restore(
+    commands: &mut Vec<CustomCommand>,
+    name: &str,
+    previous: Option<(usize, CustomCommand)>,
+) {
+    let current = commands.iter().position(|c| c.name == name);
+    match (current, previous) {
+        (Some(index), Some((_, command))) => commands[index] = command,
+        (Some(index), None) => {
+            commands.remove(index);
+        }
+        (None, Some((index, command))) => commands.insert(index.min(commands.len()), command),
+        (None, None) => {}
+    }
+}
+
+/// build the definitions of a guild's custom commands, to be registered next to the built in commands
+pub fn custom_application_commands(commands: &[CustomCommand]) -> Vec<CreateCommand> {
+    commands
+        .iter()
+        .map(|c| {
+            guild_command(
+                CreateCommand::new(&c.name).description(&c.description),
+                DEFAULT_PERMISSIONS,
+            )
+        })
+        .collect()
+}
+
+/// the limits on how often custom commands can be used, as they reply with a message for anyone to post
+pub fn custom_cooldowns() -> Vec<Cooldown> {
+    vec![
+        Cooldown::new(CooldownScope::User, 3, Duration::from_secs(30)),
+        Cooldown::new(CooldownScope::Channel, 5, Duration::from_secs(60)),
+        Cooldown::new(CooldownScope::Guild, 20, Duration::from_secs(60)),
+    ]
+}
+
+/// the reply to a custom command being used, only pinging the users it mentions if `users` is set
+pub fn custom_response(command: &CustomCommand, users: bool) -> CommandResponse {
+    let content = neutralise(&command.content);
+    let message = CreateInteractionResponseMessage::new()
+        .ephemeral(command.ephemeral)
+        .allowed_mentions(allowed_mentions(users));
+    let message = match command.embed {
+        true => message.embed(CreateEmbed::new().description(content)),
+        false => message.content(content),
+    };
+
+    CommandResponse::ComplexSuccess(CreateInteractionResponse::Message(message))
+}
+
+#[async_trait]
+impl<'a> Command<'a> for CustomCmdCommand {
+    fn name() -> &'static str {
+        "customcmd"
+    }
+
+    fn description() -> &'static str {
+        "Manage the custom commands of this server"
+    }
+
+    fn module() -> Module {
+        Module::Admin
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        vec![
+            reply_options(
+                SubCommand::new(
+                    "create",
+                    "Create a command replying with a text",
+                    |invocation| {
+                        let command = CustomCommand {
+                            name: name(invocation)?,
+                            description: invocation
+                                .str("description")
+                                .ok_or("No description provided")?,
+                            content: invocation.str("content").ok_or("No content provided")?,
+                            embed: invocation.bool("embed").unwrap_or(false),
+                            ephemeral: invocation.bool("ephemeral").unwrap_or(false),
+                        };
+                        Ok(Self {
+                            action: CustomCmdAction::Create(command),
+                        })
+                    },
+                )
+                .option(name_option()),
+                true,
+            ),
+            reply_options(
+                SubCommand::new("edit", "Change a custom command", |invocation| {
+                    Ok(Self {
+                        action: CustomCmdAction::Edit {
+                            name: name(invocation)?,
+                            description: invocation.str("description"),
+                            content: invocation.str("content"),
+                            embed: invocation.bool("embed"),
+                            ephemeral: invocation.bool("ephemeral"),
+                        },
+                    })
+                })
+                .option(name_option()),
+                false,
+            ),
+            SubCommand::new("delete", "Delete a custom command", |invocation| {
+                Ok(Self {
+                    action: CustomCmdAction::Delete(name(invocation)?),
+                })
+            })
+            .option(name_option()),
+            SubCommand::new("list", "List the custom commands of this server", |_| {
+                Ok(Self {
+                    action: CustomCmdAction::List,
+                })
+            }),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "customcmd command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        if !has_permission(interaction, Permissions::MANAGE_GUILD) {
+            return Err(CommandResponse::BasicFailure(
+                Text::ManageGuildRequired.get(locale).to_string(),
+            ));
+        }
+
+        let existing = custom_commands(&app_state.storage, guild_id).await;
+        let missing = |name: &str| {
+            CommandResponse::BasicFailure(
+                Text::CustomCommandMissing.format(locale, &[("name", name)]),
+            )
+        };
+
+        // the position and fields of a command before it is changed, to undo the change if discord rejects it
+        let before = |name: &str| {
+            existing
+                .iter()
+                .position(|c| c.name == name)
+                .map(|index| (index, existing[index].clone()))
+        };
+
+        let (name, previous, response) = match self.action {
+            CustomCmdAction::Create(command) => {
+                if !valid_name(&command.name) {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::CustomCommandInvalidName.get(locale).to_string(),
+                    ));
+                }
+                if is_builtin(&command.name) {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::CustomCommandReserved.format(locale, &[("name", &command.name)]),
+                    ));
+                }
+
+                // checked against the stored commands as the command is added, so two commands created at once
+                // can't both take the same name or the last free place
+                let name = command.name.clone();
+                app_state
+                    .storage
+                    .update(|data| {
+                        let commands =
+                            &mut data.guilds.entry(guild_id).or_default().custom_commands;
+                        if commands.iter().any(|c| c.name == command.name) {
+                            return Err(CommandResponse::BasicFailure(
+                                Text::CustomCommandExists
+                                    .format(locale, &[("name", &command.name)]),
+                            ));
+                        }
+                        if commands.len() >= MAX_CUSTOM_COMMANDS {
+                            return Err(CommandResponse::BasicFailure(
+                                Text::CustomCommandLimit
+                                    .format(locale, &[("count", &commands.len().to_string())]),
+                            ));
+                        }
+                        commands.push(command);
+                        Ok(())
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))??;
+
+                let response = Text::CustomCommandCreated.format(locale, &[("name", &name)]);
+                (name, None, response)
+            }
+            CustomCmdAction::Edit {
+                name,
+                description,
+                content,
+                embed,
+                ephemeral,
+            } => {
+                let Some(previous) = before(&name) else {
+                    return Err(missing(&name));
+                };
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        let commands =
+                            &mut data.guilds.entry(guild_id).or_default().custom_commands;
+                        if let Some(command) = commands.iter_mut().find(|c| c.name == name) {
+                            if let Some(description) = description {
+                                command.description = description;
+                            }
+                            if let Some(content) = content {
+                                command.content = content;
+                            }
+                            if let Some(embed) = embed {
+                                command.embed = embed;
+                            }
+                            if let Some(ephemeral) = ephemeral {
+                                command.ephemeral = ephemeral;
+                            }
+                        }
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                // only the description is registered, but the hash skips registering when it is unchanged
+                let response = Text::CustomCommandUpdated.format(locale, &[("name", &name)]);
+                (name, Some(previous), response)
+            }
+            CustomCmdAction::Delete(name) => {
+                let Some(previous) = before(&name) else {
+                    return Err(missing(&name));
+                };
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds
+                            .entry(guild_id)
+                            .or_default()
+                            .custom_commands
+                            .retain(|c| c.name != name)
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                let response = Text::CustomCommandDeleted.format(locale, &[("name", &name)]);
+                (name, Some(previous), response)
+            }
+            CustomCmdAction::List => {
+                let response = match existing.is_empty() {
+                    true => Text::CustomCommandsNone.get(locale).to_string(),
+                    false => existing
+                        .iter()
+                        .map(|c| format!("- `/{}`: {}", c.name, c.description))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                return Ok(reply(response));
+            }
+        };
+
+        // registered right away so a command discord rejects, such as one with a name it doesn't allow, is undone
+        match try_sync_guild(transport, app_state, guild_id).await {
+            Ok(()) => {}
+            Err(TransportError::Rejected(e)) => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        let commands =
+                            &mut data.guilds.entry(guild_id).or_default().custom_commands;
+                        restore(commands, &name, previous)
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                return Err(CommandResponse::ComplexFailure {
+                    response: Text::CustomCommandRejected.format(locale, &[("name", &name)]),
+                    kind: FailureMessageKind::Warn,
+                    log_message: format!(
+                        "custom command {} of guild {} rejected: {}",
+                        name, guild_id, e
+                    ),
+                });
+            }
+            // discord may only be unreachable for now, so registering is left to the guild handler to retry
+            Err(_) => request_sync(app_state, guild_id),
+        }
+
+        Ok(reply(response))
+    }
+}
+
+/// an ephemeral reply to a change to the custom commands
+fn reply(content: String) -> CommandResponse {
+    CommandResponse::ComplexSuccess(CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serenity::model::Permissions;
+
+    use crate::{
+        custom_commands::{custom_commands, CustomCommand, MAX_CUSTOM_COMMANDS},
+        discord_bot::{
+            commands::{
+                command::{command, Command},
+                fixtures,
+                util::CommandResponse,
+            },
+            transport::RecordingTransport,
+        },
+        modules::Module,
+    };
+
+    use super::CustomCmdCommand;
+
+    /// store a custom command in the fixture guild
+    async fn store(app_state: &crate::state::AppState, embed: bool) {
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .custom_commands
+                    .push(CustomCommand {
+                        name: String::from("oncall"),
+                        description: String::from("Who is on call"),
+                        content: String::from("Ask <@1>"),
+                        embed,
+                        ephemeral: true,
+                    })
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn custom_commands_reply_with_their_content() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, true).await;
+
+        let interaction = fixtures::command(3, "oncall", json!([]));
+        match command(&interaction, &app_state, &transport).await {
+            Ok(CommandResponse::ComplexSuccess(response)) => {
+                let body = serde_json::to_value(response).unwrap();
+                assert_eq!(body["data"]["embeds"][0]["description"], "Ask <@1>");
+                assert_eq!(body["data"]["flags"], 64);
+                assert_eq!(body["data"]["allowed_mentions"]["parse"], json!([]));
+            }
+            other => panic!("expected the custom reply, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_commands_are_refused_while_utility_is_disabled() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, false).await;
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .disabled_modules
+                    .push(Module::Utility)
+            })
+            .await
+            .unwrap();
+
+        let interaction = fixtures::command(3, "oncall", json!([]));
+        assert!(matches!(
+            command(&interaction, &app_state, &transport).await,
+            Err(CommandResponse::ComplexFailure { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn custom_commands_share_a_cooldown() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, false).await;
+
+        for id in 3..6 {
+            let interaction = fixtures::command(id, "oncall", json!([]));
+            assert!(command(&interaction, &app_state, &transport).await.is_ok());
+        }
+        let interaction = fixtures::command(6, "oncall", json!([]));
+        assert!(matches!(
+            command(&interaction, &app_state, &transport).await,
+            Err(CommandResponse::ComplexFailure { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejected_commands_are_undone() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, false).await;
+        transport.reject();
+
+        let mut interaction = fixtures::command(
+            3,
+            "customcmd",
+            json!([{ "name": "create", "type": 1, "options": [
+                { "name": "name", "type": 3, "value": "standup-notes" },
+                { "name": "description", "type": 3, "value": "Where the notes are" },
+                { "name": "content", "type": 3, "value": "In the wiki" },
+            ] }]),
+        );
+        interaction.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+
+        let command = CustomCmdCommand::try_from(&interaction).unwrap();
+        assert!(matches!(
+            command
+                .handle_application_command(&interaction, &app_state, &transport)
+                .await,
+            Err(CommandResponse::ComplexFailure { .. })
+        ));
+        let names = custom_commands(&app_state.storage, fixtures::GUILD_ID)
+            .await
+            .into_iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![String::from("oncall")]);
+    }
+
+    #[tokio::test]
+    async fn taken_names_and_full_guilds_are_refused() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, false).await;
+
+        let create = |name: &str| {
+            let mut interaction = fixtures::command(
+                3,
+                "customcmd",
+                json!([{ "name": "create", "type": 1, "options": [
+                    { "name": "name", "type": 3, "value": name },
+                    { "name": "description", "type": 3, "value": "A command" },
+                    { "name": "content", "type": 3, "value": "Some content" },
+                ] }]),
+            );
+            interaction.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+            interaction
+        };
+
+        let interaction = create("oncall");
+        let command = CustomCmdCommand::try_from(&interaction).unwrap();
+        match command
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await
+        {
+            Err(CommandResponse::BasicFailure(message)) => assert!(message.contains("oncall")),
+            other => panic!("expected the name to be refused, got {:?}", other),
+        }
+
+        app_state
+            .storage
+            .update(|data| {
+                let commands = &mut data
+                    .guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .custom_commands;
+                while commands.len() < MAX_CUSTOM_COMMANDS {
+                    let mut command = commands[0].clone();
+                    command.name = format!("command-{}", commands.len());
+                    commands.push(command);
+                }
+            })
+            .await
+            .unwrap();
+
+        let interaction = create("one-too-many");
+        let command = CustomCmdCommand::try_from(&interaction).unwrap();
+        assert!(matches!(
+            command
+                .handle_application_command(&interaction, &app_state, &transport)
+                .await,
+            Err(CommandResponse::BasicFailure(_))
+        ));
+        assert_eq!(
+            custom_commands(&app_state.storage, fixtures::GUILD_ID)
+                .await
+                .len(),
+            MAX_CUSTOM_COMMANDS
+        );
+        assert!(transport.sent().is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    builder::{
+        CreateAttachment, CreateCommand, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::Permissions,
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::transport::Transport,
+    locale::{self, Text},
+    modules::Module,
+    standups::Submission,
+    state::AppState,
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the name of the file the submissions are exported to
+const EXPORT_FILENAME: &str = "standups.csv";
+
+pub struct ExportCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ExportCommand {
+    type Error = String;
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+/// quote a field of a csv file if it holds a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// write submissions to a csv file, with a row for every answer given, oldest first
+fn export_csv(submissions: &mut [Submission]) -> String {
+    submissions.sort_by_key(|s| (s.submitted_at, s.id));
+
+    let mut csv = String::from("submitted_at,user_id,username,question,answer\n");
+    for submission in submissions.iter() {
+        for (question, answer) in submission.questions.iter().zip(&submission.answers) {
+            let row = [
+                submission.submitted_at.to_rfc3339(),
+                submission.user_id.to_string(),
+                submission.username.clone(),
+                question.clone(),
+                answer.clone(),
+            ];
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ExportCommand {
+    fn name() -> &'static str {
+        "export"
+    }
+
+    fn description() -> &'static str {
+        "Export every standup submitted in this server as a spreadsheet"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn capability() -> Option<Capability> {
+        Some(Capability::StandupExport)
+    }
+
+    /// the file can't be attached to a deferred response
+    fn deferrable() -> bool {
+        false
+    }
+
+    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
+        i
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "export command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        let mut submissions: Vec<Submission> = app_state
+            .storage
+            .read()
+            .await
+            .submissions
+            .values()
+            .filter(|s| s.guild_id == guild_id)
+            .cloned()
+            .collect();
+        if submissions.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                Text::ExportNone.get(locale).to_string(),
+            ));
+        }
+
+        let count = submissions.len().to_string();
+        let csv = export_csv(&mut submissions);
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(Text::ExportReady.format(locale, &[("count", &count)]))
+                    .add_file(CreateAttachment::bytes(csv.into_bytes(), EXPORT_FILENAME))
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::standups::Submission;
+
+    use super::{csv_field, export_csv};
+
+    /// a submission made by a user at an hour of the first of january
+    fn submission(id: u64, user_id: u64, hour: u32, answers: &[&str]) -> Submission {
+        Submission {
+            id,
+            guild_id: 10,
+            channel_id: 20,
+            message_id: None,
+            session_id: None,
+            user_id,
+            username: format!("user {}", user_id),
+            questions: (1..=answers.len()).map(|i| format!("q{}", i)).collect(),
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+            submitted_at: Utc.with_ymd_and_hms(2023, 1, 1, hour, 0, 0).unwrap(),
+            edited_at: None,
+            references: vec![],
+        }
+    }
+
+    #[test]
+    fn fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn every_answer_is_a_row_oldest_first() {
+        let mut submissions = vec![
+            submission(2, 31, 9, &["later"]),
+            submission(1, 30, 8, &["first, then", "second"]),
+        ];
+
+        assert_eq!(
+            export_csv(&mut submissions),
+            "submitted_at,user_id,username,question,answer\n\
+             2023-01-01T08:00:00+00:00,30,user 30,q1,\"first, then\"\n\
+             2023-01-01T08:00:00+00:00,30,user 30,q2,second\n\
+             2023-01-01T09:00:00+00:00,31,user 31,q1,later\n"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! State and interactions for testing commands without discord, the interactions are built from the json discord sends
+
+use std::{ops::Deref, path::PathBuf};
+
+use serde_json::{json, Value};
+use serenity::all::{CommandInteraction, Interaction, ModalInteraction};
+
+use crate::{state::AppState, storage::Storage};
+
+/// the guild every fixture interaction is in
+pub const GUILD_ID: u64 = 10;
+
+/// the channel every fixture interaction is in
+pub const CHANNEL_ID: u64 = 20;
+
+/// the user who triggers every fixture interaction
+pub const USER_ID: u64 = 30;
+
+/// an app state backed by a store in its own temporary directory, which is removed once the state is dropped
+pub struct TestState {
+    app_state: AppState,
+    dir: PathBuf,
+}
+
+impl Deref for TestState {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.app_state
+    }
+}
+
+impl Drop for TestState {
+    fn drop(&mut self) {
+        // a failure to clean up shouldn't fail the test, and panicking while a test unwinds would abort
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// an app state backed by a new, empty store in the temporary directory
+pub async fn app_state() -> TestState {
+    let dir = std::env::temp_dir().join(format!("standup-bot-test-{:016x}", rand::random::<u64>()));
+    let app_state = AppState::with_storage(Storage::load(dir.join("storage.json")).await.unwrap());
+    TestState { app_state, dir }
+}
+
+/// the json of an interaction of the provided type, triggered by [USER_ID] in [CHANNEL_ID]
+fn interaction(kind: u8, id: u64, data: Value) -> Interaction {
+    let raw = json!({
+        "id": id.to_string(),
+        "application_id": "1",
+        "type": kind,
+        "data": data,
+        "guild_id": GUILD_ID.to_string(),
+        "channel_id": CHANNEL_ID.to_string(),
+        "member": {
+            "user": {
+                "id": USER_ID.to_string(),
+                "username": "tester",
+                "discriminator": "0",
+                "global_name": null,
+                "avatar": null,
+                "bot": false,
+            },
+            "roles": [],
+            "joined_at": "2023-01-02T00:00:00.000000+00:00",
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+            "permissions": "0",
+        },
+        "token": format!("token-{}", id),
+        "version": 1,
+        "locale": "en-US",
+        "guild_locale": "en-US",
+        "app_permissions": "0",
+        "entitlements": [],
+    });
+    serde_json::from_value(raw).unwrap()
+}
+
+/// a slash command with the provided options
+pub fn command(id: u64, name: &str, options: Value) -> CommandInteraction {
+    let data = json!({
+        "id": "2",
+        "name": name,
+        "type": 1,
+        "options": options,
+    });
+    match interaction(2, id, data) {
+        Interaction::Command(command) => command,
+        other => panic!("expected a command, got {:?}", other),
+    }
+}
+
+/// a submitted modal with the provided custom id, answering one text input for each answer
+pub fn modal(id: u64, custom_id: &str, answers: &[&str]) -> ModalInteraction {
+    let inputs = answers
+        .iter()
+        .enumerate()
+        .map(|(i, answer)| (format!("input-{}", i), answer.to_string()))
+        .collect::<Vec<_>>();
+    modal_with_inputs(id, custom_id, &inputs)
+}
+
+/// submit a modal created by the bot, answering its text inputs in order
+pub fn submit_modal(id: u64, created: &Value, answers: &[String]) -> ModalInteraction {
+    let custom_id = created["custom_id"].as_str().unwrap_or_default();
+    let inputs = created["components"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .zip(answers)
+        .map(|(row, answer)| {
+            let input = row["components"][0]["custom_id"]
+                .as_str()
+                .unwrap_or_default();
+            (input.to_string(), answer.clone())
+        })
+        .collect::<Vec<_>>();
+    modal_with_inputs(id, custom_id, &inputs)
+}
+
+/// a submitted modal, with a row for each text input and its value
+fn modal_with_inputs(id: u64, custom_id: &str, inputs: &[(String, String)]) -> ModalInteraction {
+    let components = inputs
+        .iter()
+        .map(|(input, value)| {
+            json!({
+                "type": 1,
+                "components": [{ "type": 4, "custom_id": input, "value": value }],
+            })
+        })
+        .collect::<Vec<_>>();
+    let data = json!({ "custom_id": custom_id, "components": components });
+    match interaction(5, id, data) {
+        Interaction::Modal(modal) => modal,
+        other => panic!("expected a modal, got {:?}", other),
+    }
+}
@@ -1,11 +1,17 @@
+use std::time::Duration;
+
 use serenity::{
     all::CommandInteraction,
     async_trait,
     builder::{CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage},
-    prelude::Context,
 };
 
-use crate::state::AppState;
+use crate::{
+    cooldown::{Cooldown, CooldownScope},
+    discord_bot::transport::Transport,
+    modules::Module,
+    state::AppState,
+};
 
 use super::{command::Command, util::CommandResponse};
 
@@ -28,16 +34,27 @@ impl<'a> Command<'a> for HideCommand {
         "Creates a large message to hide previous messages in the chat"
     }
 
+    fn module() -> Module {
+        Module::Utility
+    }
+
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
         i
     }
 
+    fn cooldowns() -> Vec<Cooldown> {
+        vec![
+            Cooldown::new(CooldownScope::User, 2, Duration::from_secs(60)),
+            Cooldown::new(CooldownScope::Channel, 3, Duration::from_secs(60)),
+        ]
+    }
+
     #[allow(clippy::invisible_characters)]
     async fn handle_application_command<'b>(
         self,
         _: &'b CommandInteraction,
         _: &'b AppState,
-        _: &'b Context,
+        _: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
         Ok(CommandResponse::ComplexSuccess(
             CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content("​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​​\n​​\n​​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​​\n​​\n​​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n​\n"))
@@ -0,0 +1,221 @@
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, UserId},
+    async_trait,
+    builder::{
+        CreateAllowedMentions, CreateAutocompleteResponse, CreateCommand,
+        CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::board::shorten,
+    discord_bot::transport::Transport,
+    locale::{self, standup_questions, Locale, Text},
+    modules::Module,
+    standups::{Submission, THIS_WEEK_QUESTION},
+    state::AppState,
+    timezone::{discord_timestamp, TimestampStyle},
+};
+
+use super::{
+    command::{AutocompleteCommand, Command},
+    options::{add_options, CommandOptions},
+    util::{CommandResponse, FailureMessageKind},
+};
+
+/// the number of submissions shown if no count is provided
+pub const DEFAULT_COUNT: i64 = 5;
+
+/// the longest an answer may be before it is shortened
+const MAX_ANSWER_LENGTH: usize = 200;
+
+/// the maximum length of a message discord will accept
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// the longest name discord accepts for an autocomplete choice
+const MAX_CHOICE_LENGTH: usize = 100;
+
+#[derive(CommandOptions)]
+pub struct HistoryCommand {
+    // a user option rather than an autocompleted name, discord's own member picker searches the whole guild
+    // and hands over the id, where autocomplete could only offer the few names it has time to look up
+    /// The member to look up
+    member: UserId,
+    /// The question to show the answers to, by default what they were working on
+    #[option(min_value = 1, autocomplete)]
+    question: Option<i64>,
+    /// The number of standups to show
+    #[option(min_value = 1, max_value = 20)]
+    count: Option<i64>,
+}
+
+/// list the most recent answers a member gave to a question in a guild
+pub async fn history_response(
+    app_state: &AppState,
+    locale: Locale,
+    guild_id: u64,
+    user_id: u64,
+    question: usize,
+    count: i64,
+) -> String {
+    let questions = standup_questions(locale);
+    let mut submissions: Vec<Submission> = app_state
+        .storage
+        .read()
+        .await
+        .submissions
+        .values()
+        .filter(|s| s.guild_id == guild_id && s.user_id == user_id)
+        .cloned()
+        .collect();
+    submissions.sort_by_key(|s| std::cmp::Reverse(s.submitted_at));
+    submissions.truncate(count.max(1) as usize);
+
+    let mention = format!("<@{}>", user_id);
+    match submissions.is_empty() {
+        true => Text::HistoryNone.format(locale, &[("user", &mention)]),
+        false => {
+            let mut response = Text::HistoryHeader.format(
+                locale,
+                &[("user", &mention), ("question", questions[question])],
+            );
+            for submission in submissions {
+                let mut line = format!(
+                    "\n- {} · {}",
+                    discord_timestamp(submission.submitted_at, TimestampStyle::ShortDate),
+                    shorten(
+                        submission.answer(question).unwrap_or("-"),
+                        MAX_ANSWER_LENGTH
+                    ),
+                );
+                if let Some(message_id) = submission.message_id {
+                    line.push_str(&format!(
+                        " · [↗](<https://discord.com/channels/{}/{}/{}>)",
+                        submission.guild_id, submission.channel_id, message_id
+                    ));
+                }
+                if response.chars().count() + line.chars().count() > MAX_MESSAGE_LENGTH {
+                    break;
+                }
+                response.push_str(&line);
+            }
+            response
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for HistoryCommand {
+    fn name() -> &'static str {
+        "history"
+    }
+
+    fn description() -> &'static str {
+        "Look up the previous standups of a member"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
+        add_options::<Self>(i)
+    }
+
+    /// the history is only shown to the member asking, so anyone may look up their own
+    fn invocation_capability(&self, interaction: &CommandInteraction) -> Option<Capability> {
+        (self.member != interaction.user.id).then_some(Capability::StandupViewPrivate)
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "history command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        let user_id: u64 = self.member.into();
+
+        let questions = standup_questions(locale);
+        let question = match self.question {
+            Some(question) if question < 1 || question as usize > questions.len() => {
+                return Err(CommandResponse::ComplexFailure {
+                    response: Text::HistoryUnknownQuestion
+                        .format(locale, &[("count", &questions.len().to_string())]),
+                    kind: FailureMessageKind::Debug,
+                    log_message: format!("history requested for unknown question {}", question),
+                })
+            }
+            Some(question) => question as usize - 1,
+            None => THIS_WEEK_QUESTION,
+        };
+
+        let response = history_response(
+            app_state,
+            locale,
+            guild_id,
+            user_id,
+            question,
+            self.count.unwrap_or(DEFAULT_COUNT),
+        )
+        .await;
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .allowed_mentions(CreateAllowedMentions::new())
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for HistoryCommand {
+    async fn autocomplete<'c>(
+        command: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        _: &'c dyn Transport,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        let query = option.value.trim().to_lowercase();
+        let mut response = CreateAutocompleteResponse::new();
+
+        // members are picked with discord's own user picker, so only the question is completed here
+        if option.name == "question" {
+            let locale = locale::resolve(
+                &app_state.storage,
+                command.guild_id.map(u64::from),
+                command.user.id.into(),
+                &command.locale,
+            )
+            .await;
+            for (i, question) in standup_questions(locale).iter().enumerate() {
+                let name = format!("{}. {}", i + 1, question);
+                if name.to_lowercase().contains(&query) {
+                    response = response
+                        .add_int_choice(shorten(&name, MAX_CHOICE_LENGTH - 1), i as i64 + 1);
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,382 @@
+use serenity::{
+    all::{AttachmentId, CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage},
+    model::guild::Member,
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::transport::Transport,
+    holidays::{parse_date, parse_ics, Holiday},
+    locale::{self, Locale, Text},
+    modules::Module,
+    state::AppState,
+    storage::StorageData,
+};
+
+use super::{
+    command::Command,
+    subcommand::{route, Invocation, SubCommand},
+    util::{capability_denied, member_has_capability, CommandResponse, FailureMessageKind},
+};
+
+/// the action to take on the holidays of a guild or user
+enum HolidaysAction {
+    /// add a new holiday, spanning from the start to the (optional) end date
+    Add {
+        start: String,
+        end: Option<String>,
+        name: Option<String>,
+        personal: bool,
+    },
+    /// remove every holiday which falls on a date
+    Remove { date: String, personal: bool },
+    /// import holidays from an uploaded ICS file
+    Import(AttachmentId),
+    /// list the holidays relevant to the user
+    List,
+}
+
+pub struct HolidaysCommand {
+    action: HolidaysAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for HolidaysCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+/// build a failure for a date which could not be parsed
+fn invalid_date(locale: Locale, date: &str) -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        response: Text::HolidayInvalidDate.get(locale).to_string(),
+        kind: FailureMessageKind::Debug,
+        log_message: format!("invalid holiday date provided: {}", date),
+    }
+}
+
+/// get the holidays being modified, either those of the user or those of the whole guild
+fn holidays_mut(
+    data: &mut StorageData,
+    guild_id: u64,
+    user_id: u64,
+    personal: bool,
+) -> &mut Vec<Holiday> {
+    match personal {
+        true => &mut data.users.entry(user_id).or_default().holidays,
+        false => &mut data.guilds.entry(guild_id).or_default().holidays,
+    }
+}
+
+/// format a list of holidays, one per line
+fn format_holidays(holidays: &[Holiday]) -> String {
+    holidays
+        .iter()
+        .map(|h| match h.start == h.end {
+            true => format!("- {} {}", h.start, h.name),
+            false => format!("- {} – {} {}", h.start, h.end, h.name),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl<'a> Command<'a> for HolidaysCommand {
+    fn name() -> &'static str {
+        "holidays"
+    }
+
+    fn description() -> &'static str {
+        "Manage the holidays on which no standups are expected"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        let personal = || {
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "personal",
+                "Whether this is your own regional holiday, rather than one for the whole server",
+            )
+            .required(false)
+        };
+
+        vec![
+            SubCommand::new("add", "Add a holiday", |invocation| {
+                Ok(Self {
+                    action: HolidaysAction::Add {
+                        start: invocation.str("date").ok_or("No date provided")?,
+                        end: invocation.str("end"),
+                        name: invocation.str("name"),
+                        personal: invocation.bool("personal").unwrap_or(false),
+                    },
+                })
+            })
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "date",
+                    "The first day of the holiday, as YYYY-MM-DD",
+                )
+                .required(true),
+            )
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "end",
+                    "The last day of the holiday, as YYYY-MM-DD",
+                )
+                .required(false),
+            )
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The name of the holiday",
+                )
+                .required(false)
+                .max_length(100),
+            )
+            .option(personal()),
+            SubCommand::new("remove", "Remove the holidays on a date", |invocation| {
+                Ok(Self {
+                    action: HolidaysAction::Remove {
+                        date: invocation.str("date").ok_or("No date provided")?,
+                        personal: invocation.bool("personal").unwrap_or(false),
+                    },
+                })
+            })
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "date",
+                    "The date to clear, as YYYY-MM-DD",
+                )
+                .required(true),
+            )
+            .option(personal()),
+            SubCommand::new(
+                "import",
+                "Import the holidays of this server from an ICS calendar",
+                |invocation| {
+                    let file = invocation.attachment("file").ok_or("No file provided")?;
+                    Ok(Self {
+                        action: HolidaysAction::Import(file),
+                    })
+                },
+            )
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "file",
+                    "The ICS file to import",
+                )
+                .required(true),
+            ),
+            SubCommand::new(
+                "list",
+                "List the holidays of this server, and your own holidays",
+                |_| {
+                    Ok(Self {
+                        action: HolidaysAction::List,
+                    })
+                },
+            ),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user_id: u64 = interaction.user.id.into();
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "holidays command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            user_id,
+            &interaction.locale,
+        )
+        .await;
+
+        let is_guild_change = match &self.action {
+            HolidaysAction::Add { personal, .. } | HolidaysAction::Remove { personal, .. } => {
+                !personal
+            }
+            HolidaysAction::Import(_) => true,
+            HolidaysAction::List => false,
+        };
+        let member = interaction.member.as_ref().map(|m| -> &Member { m });
+        if is_guild_change
+            && !member_has_capability(
+                app_state,
+                Some(guild_id),
+                member,
+                Capability::StandupConfigure,
+            )
+            .await
+        {
+            return Err(capability_denied(
+                locale,
+                user_id,
+                Capability::StandupConfigure,
+            ));
+        }
+
+        let response = match self.action {
+            HolidaysAction::Add {
+                start,
+                end,
+                name,
+                personal,
+            } => {
+                let start_date = parse_date(&start).ok_or_else(|| invalid_date(locale, &start))?;
+                let end_date = match end {
+                    Some(end) => parse_date(&end).ok_or_else(|| invalid_date(locale, &end))?,
+                    None => start_date,
+                };
+                if end_date < start_date {
+                    return Err(invalid_date(locale, &start));
+                }
+
+                let holiday = Holiday {
+                    start: start_date,
+                    end: end_date,
+                    name: name.unwrap_or_else(|| start_date.to_string()),
+                };
+                let response = Text::HolidayAdded.format(locale, &[("name", &holiday.name)]);
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        holidays_mut(data, guild_id, user_id, personal).push(holiday);
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                response
+            }
+            HolidaysAction::Remove { date, personal } => {
+                let parsed = parse_date(&date).ok_or_else(|| invalid_date(locale, &date))?;
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        holidays_mut(data, guild_id, user_id, personal)
+                            .retain(|h| !h.contains(parsed));
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::HolidayRemoved.format(locale, &[("date", &parsed.to_string())])
+            }
+            HolidaysAction::Import(attachment_id) => {
+                let attachment = interaction
+                    .data
+                    .resolved
+                    .attachments
+                    .get(&attachment_id)
+                    .ok_or_else(|| {
+                        CommandResponse::InternalFailure(String::from(
+                            "holiday import attachment was not resolved",
+                        ))
+                    })?;
+
+                let raw = attachment
+                    .download()
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                let imported = String::from_utf8(raw)
+                    .map(|raw| parse_ics(&raw))
+                    .unwrap_or_default();
+                if imported.is_empty() {
+                    return Err(CommandResponse::ComplexFailure {
+                        response: Text::HolidayInvalidFile.get(locale).to_string(),
+                        kind: FailureMessageKind::Debug,
+                        log_message: format!(
+                            "no holidays found in uploaded file {}",
+                            attachment.filename
+                        ),
+                    });
+                }
+
+                let count = app_state
+                    .storage
+                    .update(|data| {
+                        let holidays = &mut data.guilds.entry(guild_id).or_default().holidays;
+                        let before = holidays.len();
+                        for holiday in imported {
+                            if !holidays.contains(&holiday) {
+                                holidays.push(holiday);
+                            }
+                        }
+                        holidays.len() - before
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::HolidaysImported.format(locale, &[("count", &count.to_string())])
+            }
+            HolidaysAction::List => {
+                let data = app_state.storage.read().await;
+                let mut guild_holidays = data
+                    .guilds
+                    .get(&guild_id)
+                    .map(|g| g.holidays.clone())
+                    .unwrap_or_default();
+                let mut user_holidays = data
+                    .users
+                    .get(&user_id)
+                    .map(|u| u.holidays.clone())
+                    .unwrap_or_default();
+                guild_holidays.sort_by_key(|h| h.start);
+                user_holidays.sort_by_key(|h| h.start);
+
+                let mut sections = vec![];
+                if !guild_holidays.is_empty() {
+                    sections.push(format!(
+                        "{}\n{}",
+                        Text::HolidaysGuildHeader.get(locale),
+                        format_holidays(&guild_holidays)
+                    ));
+                }
+                if !user_holidays.is_empty() {
+                    sections.push(format!(
+                        "{}\n{}",
+                        Text::HolidaysUserHeader.get(locale),
+                        format_holidays(&user_holidays)
+                    ));
+                }
+
+                match sections.is_empty() {
+                    true => Text::HolidaysNone.get(locale).to_string(),
+                    false => sections.join("\n\n"),
+                }
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
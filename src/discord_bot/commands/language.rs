@@ -0,0 +1,165 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::guild::Member,
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::transport::Transport,
+    locale::{self, Locale, Text},
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    util::{capability_denied, member_has_capability, CommandResponse},
+};
+
+/// who a language preference should apply to
+enum LanguageScope {
+    /// only the user running the command
+    User,
+    /// every member of the guild, unless they have chosen their own language
+    Guild,
+}
+
+pub struct LanguageCommand {
+    locale: Locale,
+    scope: LanguageScope,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for LanguageCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = &interaction.data.options;
+
+        let locale = options
+            .iter()
+            .find(|o| o.name == "language")
+            .and_then(|o| o.value.as_str())
+            .and_then(Locale::from_code)
+            .ok_or("No language provided")?;
+
+        let scope = match options
+            .iter()
+            .find(|o| o.name == "scope")
+            .and_then(|o| o.value.as_str())
+        {
+            Some("server") => LanguageScope::Guild,
+            _ => LanguageScope::User,
+        };
+
+        Ok(Self { locale, scope })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for LanguageCommand {
+    fn name() -> &'static str {
+        "language"
+    }
+
+    fn description() -> &'static str {
+        "Set the language the bot responds in"
+    }
+
+    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
+        let language = Locale::ALL.iter().fold(
+            CreateCommandOption::new(CommandOptionType::String, "language", "The language to use")
+                .required(true),
+            |option, locale| option.add_string_choice(locale.native_name(), locale.code()),
+        );
+
+        i.add_option(language).add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "scope",
+                "Whether to set your own language, or the default for this server",
+            )
+            .required(false)
+            .add_string_choice("me", "me")
+            .add_string_choice("server", "server"),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user_id: u64 = interaction.user.id.into();
+
+        let response = match self.scope {
+            LanguageScope::User => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.users.entry(user_id).or_default().locale = Some(self.locale);
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::LanguageUserSet
+                    .format(self.locale, &[("language", self.locale.native_name())])
+            }
+            LanguageScope::Guild => {
+                let guild_id: u64 = interaction
+                    .guild_id
+                    .ok_or_else(|| {
+                        CommandResponse::InternalFailure(String::from(
+                            "language command used outside of a guild",
+                        ))
+                    })?
+                    .into();
+
+                let member = interaction.member.as_ref().map(|m| -> &Member { m });
+                if !member_has_capability(
+                    app_state,
+                    Some(guild_id),
+                    member,
+                    Capability::StandupConfigure,
+                )
+                .await
+                {
+                    let locale = locale::resolve(
+                        &app_state.storage,
+                        Some(guild_id),
+                        user_id,
+                        &interaction.locale,
+                    )
+                    .await;
+                    return Err(capability_denied(
+                        locale,
+                        user_id,
+                        Capability::StandupConfigure,
+                    ));
+                }
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds.entry(guild_id).or_default().locale = Some(self.locale);
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::LanguageGuildSet
+                    .format(self.locale, &[("language", self.locale.native_name())])
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
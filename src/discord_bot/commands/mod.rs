@@ -1,9 +1,30 @@
 mod command;
+mod options;
+mod subcommand;
 mod util;
 
+#[cfg(test)]
+pub(super) mod fixtures;
+
+mod customcmd;
+mod export;
 mod hide;
+mod history;
+mod holidays;
+mod language;
+mod modules;
+mod permissions;
 mod ping;
+mod save_standup;
 mod say;
 mod standup;
+mod standup_config;
+mod timezone;
+mod user_history;
 
-pub use command::{application_command, autocomplete, command, handle_modal, interaction};
+pub use command::{
+    application_command, autocomplete, command, deferrable, deferrable_component, handle_modal,
+    interaction,
+};
+pub use customcmd::custom_application_commands;
+pub use util::{CommandResponse, FailureContext};
@@ -0,0 +1,265 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage},
+    model::Permissions,
+};
+
+use crate::{
+    discord_bot::{registration::request_sync, transport::Transport},
+    locale::{self, Text},
+    modules::{disabled_modules, Module},
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    subcommand::{route, Invocation, SubCommand},
+    util::{has_permission, CommandResponse},
+};
+
+/// a change to, or a request for, the modules enabled in a guild
+enum ModulesAction {
+    /// enable a module which was disabled
+    Enable(Module),
+    /// disable a module, hiding and refusing its commands
+    Disable(Module),
+    /// list whether each module is enabled
+    List,
+}
+
+pub struct ModulesCommand {
+    action: ModulesAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ModulesCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+/// get the module chosen for enable or disable
+fn module(invocation: &Invocation) -> Result<Module, String> {
+    invocation
+        .str("module")
+        .and_then(|m| Module::from_name(&m))
+        .ok_or_else(|| String::from("No valid module provided"))
+}
+
+/// the module option taken by both enable and disable, only offering modules which can be disabled
+fn module_option(subcommand: SubCommand<ModulesCommand>) -> SubCommand<ModulesCommand> {
+    let module = Module::ALL.into_iter().filter(|m| m.can_disable()).fold(
+        CreateCommandOption::new(CommandOptionType::String, "module", "The module to change")
+            .required(true),
+        |option, module| option.add_string_choice(module.name(), module.name()),
+    );
+
+    subcommand.option(module)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ModulesCommand {
+    fn name() -> &'static str {
+        "modules"
+    }
+
+    fn description() -> &'static str {
+        "Choose which groups of commands are available on this server"
+    }
+
+    fn module() -> Module {
+        Module::Admin
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        vec![
+            module_option(SubCommand::new(
+                "enable",
+                "Make the commands of a module available",
+                |invocation| {
+                    Ok(Self {
+                        action: ModulesAction::Enable(module(invocation)?),
+                    })
+                },
+            )),
+            module_option(SubCommand::new(
+                "disable",
+                "Hide the commands of a module on this server",
+                |invocation| {
+                    Ok(Self {
+                        action: ModulesAction::Disable(module(invocation)?),
+                    })
+                },
+            )),
+            SubCommand::new("list", "List which modules are enabled", |_| {
+                Ok(Self {
+                    action: ModulesAction::List,
+                })
+            }),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "modules command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        if !has_permission(interaction, Permissions::MANAGE_GUILD) {
+            return Err(CommandResponse::BasicFailure(
+                Text::ManageGuildRequired.get(locale).to_string(),
+            ));
+        }
+
+        let response = match self.action {
+            ModulesAction::Enable(module) => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds
+                            .entry(guild_id)
+                            .or_default()
+                            .disabled_modules
+                            .retain(|m| *m != module);
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                request_sync(app_state, guild_id);
+                Text::ModuleEnabled.format(locale, &[("module", module.name())])
+            }
+            ModulesAction::Disable(module) => {
+                if !module.can_disable() {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::ModuleRequired.format(locale, &[("module", module.name())]),
+                    ));
+                }
+
+                app_state
+                    .storage
+                    .update(|data| {
+                        let disabled =
+                            &mut data.guilds.entry(guild_id).or_default().disabled_modules;
+                        if !disabled.contains(&module) {
+                            disabled.push(module);
+                        }
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                request_sync(app_state, guild_id);
+                Text::ModuleDisabled.format(locale, &[("module", module.name())])
+            }
+            ModulesAction::List => {
+                let disabled = disabled_modules(&app_state.storage, Some(guild_id)).await;
+
+                Module::ALL
+                    .into_iter()
+                    .map(|module| {
+                        let state = match disabled.contains(&module) {
+                            true => Text::ModuleStateDisabled,
+                            false => Text::ModuleStateEnabled,
+                        };
+                        format!("- `{}`: {}", module.name(), state.get(locale))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        discord_bot::{
+            commands::{
+                command::{application_command, command},
+                fixtures,
+                util::CommandResponse,
+            },
+            transport::RecordingTransport,
+        },
+        locale::{Locale, Text},
+        modules::Module,
+    };
+
+    #[test]
+    fn disabled_modules_are_not_registered() {
+        let names = |disabled: &[Module]| {
+            application_command(disabled)
+                .iter()
+                .map(|c| serde_json::to_value(c).unwrap()["name"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names(&[]).contains(&json!("say")));
+        let names = names(&[Module::Utility]);
+        assert!(!names.contains(&json!("say")));
+        assert!(!names.contains(&json!("hide")));
+        assert!(names.contains(&json!("standup")));
+        assert!(names.contains(&json!("modules")));
+    }
+
+    #[tokio::test]
+    async fn commands_of_disabled_modules_are_refused() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .disabled_modules
+                    .push(Module::Utility)
+            })
+            .await
+            .unwrap();
+        let interaction = fixtures::command(
+            3,
+            "say",
+            json!([{ "name": "text", "type": 3, "value": "hello" }]),
+        );
+
+        match command(&interaction, &app_state, &transport).await {
+            Err(CommandResponse::ComplexFailure { response, .. }) => assert_eq!(
+                response,
+                Text::ModuleUnavailable.format(Locale::English, &[("module", "utility")])
+            ),
+            other => panic!("expected say to be refused, got {:?}", other),
+        }
+        assert!(transport.sent().is_empty());
+    }
+}
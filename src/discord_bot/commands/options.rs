@@ -0,0 +1,404 @@
+//! Typed options for commands, used by `#[derive(CommandOptions)]` to declare and parse a command from a struct
+
+use serenity::{
+    all::{AttachmentId, ChannelId, CommandDataOptionValue, CommandOptionType, RoleId, UserId},
+    builder::{CreateCommand, CreateCommandOption},
+};
+
+pub use standup_bot_macros::CommandOptions;
+
+use crate::locale::{Locale, Text};
+
+use super::subcommand::Invocation;
+
+/// the options of a command, declared to discord and parsed from an invocation
+pub trait CommandOptions: Sized {
+    /// the options sent to discord when registering the command
+    fn options() -> Vec<CreateCommandOption>;
+
+    /// parse the options provided to an invocation, checking each is valid
+    fn parse(invocation: &Invocation) -> Result<Self, OptionError>;
+}
+
+/// why the options of an invocation couldn't be parsed
+#[derive(Debug, PartialEq)]
+pub enum OptionError {
+    /// a required option was left out
+    Missing(String),
+    /// the value of an option is outside a bound it was declared with
+    OutOfBounds { option: String, bound: Bound },
+    /// an option doesn't hold the type of value it was declared with
+    Malformed(String),
+}
+
+/// a bound an option was declared with, which its value broke
+#[derive(Debug, PartialEq)]
+pub enum Bound {
+    MinLength(usize),
+    MaxLength(usize),
+    MinValue(f64),
+    MaxValue(f64),
+}
+
+impl OptionError {
+    /// the reason the options were refused, in the language of the member.
+    /// a malformed option can't be sent through discord, so its reason is only logged and isn't translated
+    pub fn reason(&self, locale: Locale) -> String {
+        let (option, text, bound) = match self {
+            OptionError::Missing(option) => {
+                return Text::OptionMissing.format(locale, &[("option", option)])
+            }
+            OptionError::OutOfBounds { option, bound } => match bound {
+                Bound::MinLength(min) => (option, Text::OptionTooShort, min.to_string()),
+                Bound::MaxLength(max) => (option, Text::OptionTooLong, max.to_string()),
+                Bound::MinValue(min) => (option, Text::OptionTooSmall, min.to_string()),
+                Bound::MaxValue(max) => (option, Text::OptionTooLarge, max.to_string()),
+            },
+            OptionError::Malformed(reason) => return reason.clone(),
+        };
+        text.format(locale, &[("option", option), ("bound", &bound)])
+    }
+}
+
+/// routing fails with a string, when a subcommand which was never declared is invoked
+impl From<String> for OptionError {
+    fn from(reason: String) -> Self {
+        OptionError::Malformed(reason)
+    }
+}
+
+/// a type which can be the value of an option
+pub trait OptionValue: Sized {
+    /// the type of option declared to discord
+    const KIND: CommandOptionType;
+
+    /// get the value of an option, if it is of this type
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self>;
+
+    /// the length of the value, if it has one
+    fn length(&self) -> Option<usize> {
+        None
+    }
+
+    /// the value as a number, if it is one
+    fn number(&self) -> Option<f64> {
+        None
+    }
+}
+
+impl OptionValue for String {
+    const KIND: CommandOptionType = CommandOptionType::String;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        value.as_str().map(String::from)
+    }
+
+    fn length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl OptionValue for bool {
+    const KIND: CommandOptionType = CommandOptionType::Boolean;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl OptionValue for i64 {
+    const KIND: CommandOptionType = CommandOptionType::Integer;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        value.as_i64()
+    }
+
+    fn number(&self) -> Option<f64> {
+        Some(*self as f64)
+    }
+}
+
+impl OptionValue for f64 {
+    const KIND: CommandOptionType = CommandOptionType::Number;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        value.as_f64()
+    }
+
+    fn number(&self) -> Option<f64> {
+        Some(*self)
+    }
+}
+
+impl OptionValue for ChannelId {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        match value {
+            CommandDataOptionValue::Channel(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for RoleId {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        match value {
+            CommandDataOptionValue::Role(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for UserId {
+    const KIND: CommandOptionType = CommandOptionType::User;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        match value {
+            CommandDataOptionValue::User(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for AttachmentId {
+    const KIND: CommandOptionType = CommandOptionType::Attachment;
+
+    fn from_value(value: &CommandDataOptionValue) -> Option<Self> {
+        value.as_attachment_id()
+    }
+}
+
+/// the limits an option was declared with, which discord should enforce but are checked again here
+pub struct Bounds {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+}
+
+/// parse an option by name, returning `None` if it wasn't provided and an error if it is invalid
+pub fn parse_option<T: OptionValue>(
+    invocation: &Invocation,
+    name: &str,
+    bounds: Bounds,
+) -> Result<Option<T>, OptionError> {
+    let value = match invocation.value(name) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let value = T::from_value(value).ok_or_else(|| {
+        OptionError::Malformed(format!("{} was not a {:?} option", name, T::KIND))
+    })?;
+
+    let broken = match (value.length(), value.number()) {
+        (Some(length), _) => match (bounds.min_length, bounds.max_length) {
+            (Some(min), _) if length < min => Some(Bound::MinLength(min)),
+            (_, Some(max)) if length > max => Some(Bound::MaxLength(max)),
+            _ => None,
+        },
+        (_, Some(number)) => match (bounds.min_value, bounds.max_value) {
+            (Some(min), _) if number < min => Some(Bound::MinValue(min)),
+            (_, Some(max)) if number > max => Some(Bound::MaxValue(max)),
+            _ => None,
+        },
+        (None, None) => None,
+    };
+
+    match broken {
+        Some(bound) => Err(OptionError::OutOfBounds {
+            option: name.to_string(),
+            bound,
+        }),
+        None => Ok(Some(value)),
+    }
+}
+
+/// add the options of a struct to a command
+pub fn add_options<T: CommandOptions>(command: CreateCommand) -> CreateCommand {
+    T::options()
+        .into_iter()
+        .fold(command, |command, option| command.add_option(option))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use serenity::all::{ChannelId, CommandInteraction};
+
+    use crate::{
+        discord_bot::commands::{fixtures, subcommand::Invocation},
+        locale::Locale,
+    };
+
+    use super::{parse_option, Bound, Bounds, CommandOptions, OptionError};
+
+    /// options with each kind of bound, parsed the same way as the options of a command
+    #[derive(CommandOptions, Debug, PartialEq)]
+    struct Example {
+        /// a bounded string
+        #[option(min_length = 2, max_length = 4)]
+        name: String,
+        /// a bounded number
+        #[option(min_value = 1, max_value = 10)]
+        count: Option<i64>,
+        /// a channel
+        channel: Option<ChannelId>,
+    }
+
+    /// an invocation of a command with the provided options
+    fn invoke(options: Value) -> CommandInteraction {
+        fixtures::command(1, "example", options)
+    }
+
+    /// bounds which only limit the length of a value
+    fn length(min: usize, max: usize) -> Bounds {
+        Bounds {
+            min_length: Some(min),
+            max_length: Some(max),
+            min_value: None,
+            max_value: None,
+        }
+    }
+
+    #[test]
+    fn options_are_parsed_in_any_order() {
+        let interaction = invoke(json!([
+            { "name": "channel", "type": 7, "value": "5" },
+            { "name": "count", "type": 4, "value": 3 },
+            { "name": "name", "type": 3, "value": "abc" },
+        ]));
+
+        assert_eq!(
+            Example::try_from(&interaction),
+            Ok(Example {
+                name: String::from("abc"),
+                count: Some(3),
+                channel: Some(ChannelId::new(5)),
+            })
+        );
+    }
+
+    #[test]
+    fn optional_options_may_be_left_out_but_required_ones_may_not() {
+        let interaction = invoke(json!([{ "name": "name", "type": 3, "value": "abc" }]));
+        assert_eq!(
+            Example::try_from(&interaction),
+            Ok(Example {
+                name: String::from("abc"),
+                count: None,
+                channel: None,
+            })
+        );
+
+        let interaction = invoke(json!([{ "name": "count", "type": 4, "value": 3 }]));
+        assert_eq!(
+            Example::try_from(&interaction),
+            Err(OptionError::Missing(String::from("name")))
+        );
+    }
+
+    #[test]
+    fn options_of_the_wrong_type_are_malformed() {
+        let interaction = invoke(json!([{ "name": "name", "type": 4, "value": 3 }]));
+        assert!(matches!(
+            Example::try_from(&interaction),
+            Err(OptionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn values_outside_their_bounds_are_refused() {
+        let cases = [
+            (json!("a"), json!(3), Bound::MinLength(2)),
+            (json!("abcde"), json!(3), Bound::MaxLength(4)),
+            (json!("abc"), json!(0), Bound::MinValue(1.0)),
+            (json!("abc"), json!(11), Bound::MaxValue(10.0)),
+        ];
+        for (name, count, bound) in cases {
+            let interaction = invoke(json!([
+                { "name": "name", "type": 3, "value": name },
+                { "name": "count", "type": 4, "value": count },
+            ]));
+            let option = match bound {
+                Bound::MinLength(_) | Bound::MaxLength(_) => "name",
+                Bound::MinValue(_) | Bound::MaxValue(_) => "count",
+            };
+            assert_eq!(
+                Example::try_from(&interaction),
+                Err(OptionError::OutOfBounds {
+                    option: String::from(option),
+                    bound,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_are_inclusive_and_count_characters() {
+        let interaction = invoke(json!([
+            { "name": "name", "type": 3, "value": "äöü" },
+            { "name": "count", "type": 4, "value": 10 },
+        ]));
+        let invocation = Invocation::new(&interaction);
+
+        assert_eq!(
+            parse_option::<String>(&invocation, "name", length(3, 3)),
+            Ok(Some(String::from("äöü")))
+        );
+        assert_eq!(
+            parse_option::<i64>(
+                &invocation,
+                "count",
+                Bounds {
+                    min_length: None,
+                    max_length: None,
+                    min_value: Some(10.0),
+                    max_value: Some(10.0),
+                }
+            ),
+            Ok(Some(10))
+        );
+        // a length bound doesn't apply to a number, nor a value bound to a string
+        assert_eq!(
+            parse_option::<i64>(&invocation, "count", length(5, 5)),
+            Ok(Some(10))
+        );
+        assert_eq!(
+            parse_option::<String>(&invocation, "missing", length(1, 1)),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn reasons_are_given_in_the_language_of_the_member() {
+        let missing = OptionError::Missing(String::from("name"));
+        assert_eq!(
+            missing.reason(Locale::English),
+            "You need to provide `name`."
+        );
+        assert_eq!(missing.reason(Locale::German), "Du musst `name` angeben.");
+
+        let too_large = OptionError::OutOfBounds {
+            option: String::from("count"),
+            bound: Bound::MaxValue(10.0),
+        };
+        assert_eq!(
+            too_large.reason(Locale::English),
+            "`count` can be at most 10."
+        );
+        assert_eq!(
+            too_large.reason(Locale::Maori),
+            "Kia 10 te nui rawa o `count`."
+        );
+
+        // malformed options are only logged
+        let malformed = OptionError::Malformed(String::from("name was not a String option"));
+        assert_eq!(
+            malformed.reason(Locale::German),
+            "name was not a String option"
+        );
+    }
+}
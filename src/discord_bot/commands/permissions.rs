@@ -0,0 +1,301 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{
+        CreateAllowedMentions, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::Permissions,
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::transport::Transport,
+    locale::{self, Text},
+    modules::Module,
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    subcommand::{route, Invocation, SubCommand},
+    util::{has_permission, CommandResponse},
+};
+
+/// a change to, or a request for, the capabilities granted in a guild
+enum PermissionsAction {
+    /// grant a capability to a role
+    Grant {
+        role_id: u64,
+        capability: Capability,
+    },
+    /// take a capability away from a role
+    Revoke {
+        role_id: u64,
+        capability: Capability,
+    },
+    /// list the roles each capability has been granted to
+    List,
+    /// choose whether text written by members may ping the users it mentions when the bot posts it
+    Mentions { users: bool },
+}
+
+pub struct PermissionsCommand {
+    action: PermissionsAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for PermissionsCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+/// get the role and capability chosen for grant or revoke
+fn role_and_capability_of(invocation: &Invocation) -> Result<(u64, Capability), String> {
+    let role_id = invocation.role("role").ok_or("No role provided")?;
+    let capability = invocation
+        .str("capability")
+        .and_then(|c| Capability::from_name(&c))
+        .ok_or("No valid capability provided")?;
+    Ok((role_id, capability))
+}
+
+/// the options taken by both grant and revoke
+fn role_and_capability(
+    subcommand: SubCommand<PermissionsCommand>,
+) -> SubCommand<PermissionsCommand> {
+    let capability = Capability::ALL.into_iter().fold(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "capability",
+            "The capability to change",
+        )
+        .required(true),
+        |option, capability| option.add_string_choice(capability.name(), capability.name()),
+    );
+
+    subcommand
+        .option(
+            CreateCommandOption::new(CommandOptionType::Role, "role", "The role to change")
+                .required(true),
+        )
+        .option(capability)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for PermissionsCommand {
+    fn name() -> &'static str {
+        "permissions"
+    }
+
+    fn description() -> &'static str {
+        "Choose which roles can use each part of the bot"
+    }
+
+    fn module() -> Module {
+        Module::Admin
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        vec![
+            role_and_capability(SubCommand::new(
+                "grant",
+                "Let a role use a capability",
+                |invocation| {
+                    let (role_id, capability) = role_and_capability_of(invocation)?;
+                    Ok(Self {
+                        action: PermissionsAction::Grant {
+                            role_id,
+                            capability,
+                        },
+                    })
+                },
+            )),
+            role_and_capability(SubCommand::new(
+                "revoke",
+                "Stop a role from using a capability",
+                |invocation| {
+                    let (role_id, capability) = role_and_capability_of(invocation)?;
+                    Ok(Self {
+                        action: PermissionsAction::Revoke {
+                            role_id,
+                            capability,
+                        },
+                    })
+                },
+            )),
+            SubCommand::new(
+                "list",
+                "List the roles each capability is granted to",
+                |_| {
+                    Ok(Self {
+                        action: PermissionsAction::List,
+                    })
+                },
+            ),
+            SubCommand::new(
+                "mentions",
+                "Choose whether messages members make the bot post can ping users",
+                |invocation| {
+                    let users = invocation.bool("users").ok_or("No users choice provided")?;
+                    Ok(Self {
+                        action: PermissionsAction::Mentions { users },
+                    })
+                },
+            )
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "users",
+                    "Whether users mentioned in them are pinged, roles and @everyone never are",
+                )
+                .required(true),
+            ),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "permissions command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        // capabilities can't be used to hand out more capabilities, only those managing the guild can
+        if !has_permission(interaction, Permissions::MANAGE_GUILD) {
+            return Err(CommandResponse::BasicFailure(
+                Text::ManageGuildRequired.get(locale).to_string(),
+            ));
+        }
+
+        let response = match self.action {
+            PermissionsAction::Grant {
+                role_id,
+                capability,
+            } => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        let roles = data
+                            .guilds
+                            .entry(guild_id)
+                            .or_default()
+                            .role_capabilities
+                            .entry(capability)
+                            .or_default();
+                        if !roles.contains(&role_id) {
+                            roles.push(role_id);
+                        }
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::PermissionGranted.format(
+                    locale,
+                    &[
+                        ("role", &format!("<@&{}>", role_id)),
+                        ("capability", capability.name()),
+                    ],
+                )
+            }
+            PermissionsAction::Revoke {
+                role_id,
+                capability,
+            } => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        let capabilities =
+                            &mut data.guilds.entry(guild_id).or_default().role_capabilities;
+                        if let Some(roles) = capabilities.get_mut(&capability) {
+                            roles.retain(|r| *r != role_id);
+                            if roles.is_empty() {
+                                capabilities.remove(&capability);
+                            }
+                        }
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::PermissionRevoked.format(
+                    locale,
+                    &[
+                        ("role", &format!("<@&{}>", role_id)),
+                        ("capability", capability.name()),
+                    ],
+                )
+            }
+            PermissionsAction::List => {
+                let capabilities = app_state
+                    .storage
+                    .read()
+                    .await
+                    .guilds
+                    .get(&guild_id)
+                    .map(|g| g.role_capabilities.clone())
+                    .unwrap_or_default();
+
+                let lines = Capability::ALL
+                    .into_iter()
+                    .filter_map(|capability| {
+                        let roles = capabilities.get(&capability)?;
+                        let roles = roles
+                            .iter()
+                            .map(|r| format!("<@&{}>", r))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        Some(format!("- `{}`: {}", capability.name(), roles))
+                    })
+                    .collect::<Vec<_>>();
+
+                match lines.is_empty() {
+                    true => Text::PermissionsNone.get(locale).to_string(),
+                    false => lines.join("\n"),
+                }
+            }
+            PermissionsAction::Mentions { users } => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds.entry(guild_id).or_default().allow_user_mentions = users
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                match users {
+                    true => Text::UserMentionsAllowed.get(locale).to_string(),
+                    false => Text::UserMentionsBlocked.get(locale).to_string(),
+                }
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .allowed_mentions(CreateAllowedMentions::new())
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
@@ -2,10 +2,13 @@ use serenity::{
     all::CommandInteraction,
     async_trait,
     builder::{CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage},
-    prelude::Context,
 };
 
-use crate::state::AppState;
+use crate::{
+    discord_bot::transport::Transport,
+    locale::{self, Text},
+    state::AppState,
+};
 
 use super::{command::Command, util::CommandResponse};
 
@@ -34,14 +37,22 @@ impl<'a> Command<'a> for PingCommand {
 
     async fn handle_application_command<'b>(
         self,
-        _: &'b CommandInteraction,
-        _: &'b AppState,
-        _: &'b Context,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
+        let locale = locale::resolve(
+            &app_state.storage,
+            interaction.guild_id.map(u64::from),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
         Ok(CommandResponse::ComplexSuccess(
             CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content("Pong!")
+                    .content(Text::Pong.get(locale))
                     .ephemeral(true),
             ),
         ))
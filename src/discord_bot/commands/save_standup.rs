@@ -0,0 +1,133 @@
+use serenity::{
+    all::{CommandInteraction, CommandType, ResolvedTarget},
+    async_trait,
+    builder::CreateInteractionResponse,
+};
+
+use crate::{
+    discord_bot::{board::shorten, custom_id::ComponentId, transport::Transport},
+    locale::{self, standup_questions, Text},
+    modules::Module,
+    standups::THIS_WEEK_QUESTION,
+    state::AppState,
+};
+
+use super::{command::Command, standup::standup_modal, util::CommandResponse};
+
+/// the longest answer pre-filled from a message, the submission is checked for length once it is submitted
+const MAX_PREFILLED_LENGTH: usize = 1900;
+
+/// turn an existing message into a standup, opened from the context menu of a message
+pub struct SaveStandupCommand {
+    /// the user who wrote the message
+    author_id: u64,
+    /// the text of the message
+    content: String,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SaveStandupCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        match interaction.data.target() {
+            Some(ResolvedTarget::Message(message)) => Ok(Self {
+                author_id: message.author.id.into(),
+                content: message.content.clone(),
+            }),
+            _ => Err(String::from("No message provided")),
+        }
+    }
+}
+
+/// split a message into an answer for each question, one line each if it has a line for every question,
+/// otherwise the whole message is what the member is working on
+fn prefill(content: &str, questions: usize) -> Vec<String> {
+    let lines = content
+        .lines()
+        .map(|l| l.trim().trim_start_matches(['-', '*', '•']).trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| shorten(l, MAX_PREFILLED_LENGTH))
+        .collect::<Vec<_>>();
+
+    if lines.len() == questions {
+        return lines;
+    }
+
+    let mut answers = vec![String::new(); questions];
+    if let Some(answer) = answers.get_mut(THIS_WEEK_QUESTION) {
+        *answer = shorten(&lines.join(" "), MAX_PREFILLED_LENGTH);
+    }
+    answers
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SaveStandupCommand {
+    fn name() -> &'static str {
+        "Save as standup update"
+    }
+
+    fn description() -> &'static str {
+        "Submit a message as your standup"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn kind() -> CommandType {
+        CommandType::Message
+    }
+
+    fn deferrable() -> bool {
+        false
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let locale = locale::resolve(
+            &app_state.storage,
+            interaction.guild_id.map(u64::from),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        if self.author_id != u64::from(interaction.user.id) {
+            return Err(CommandResponse::BasicFailure(
+                Text::StandupSaveNotYours.get(locale).to_string(),
+            ));
+        }
+        if self.content.trim().is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                Text::StandupSaveEmpty.get(locale).to_string(),
+            ));
+        }
+
+        // the submission joins the latest session still open in the channel, if there is one
+        let channel_id: u64 = interaction.channel_id.into();
+        let session_id = app_state
+            .storage
+            .read()
+            .await
+            .sessions
+            .values()
+            .filter(|s| s.channel_id == channel_id && s.is_open())
+            .max_by_key(|s| s.started_at)
+            .map(|s| s.id);
+
+        // the member reviews the answers in the usual modal, which is submitted as any other standup
+        let questions = standup_questions(locale);
+        let answers = prefill(&self.content, questions.len());
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Modal(standup_modal(
+                ComponentId::StandupSubmit { session_id },
+                locale,
+                questions,
+                Some(&answers),
+            )),
+        ))
+    }
+}
@@ -1,41 +1,301 @@
+use std::time::Duration;
+
+use chrono::Utc;
 use serenity::{
-    all::{CommandInteraction, CommandOptionType},
+    all::{AttachmentId, ChannelId, CommandInteraction, UserId},
     async_trait,
-    builder::{
-        CreateCommand, CreateCommandOption, CreateInteractionResponse,
-        CreateInteractionResponseMessage, CreateMessage,
-    },
-    prelude::Context,
+    builder::{CreateAllowedMentions, CreateInteractionResponse, CreateInteractionResponseMessage},
+    model::Permissions,
 };
 
-use crate::state::AppState;
+use crate::{
+    access::Capability,
+    announcements::{
+        parse_colour, parse_message_link, parse_send_time, remove_attachments, said_message,
+        store_attachment, SayEmbed, SayLogAction, SayLogEntry, SayMessage, ScheduledMessage,
+        MAX_SCHEDULED_ATTACHMENT_BYTES, MAX_SCHEDULED_MESSAGES,
+    },
+    cooldown::{Cooldown, CooldownScope},
+    discord_bot::{
+        announcements::{audit, create_message, edit_message, render_log_entry},
+        transport::{Transport, TransportError},
+    },
+    locale::{self, Locale, Text},
+    mentions::user_mentions_allowed,
+    modules::Module,
+    state::AppState,
+    timezone::{discord_timestamp, user_timezone, TimestampStyle},
+};
 
 use super::{
     command::Command,
-    util::{CommandResponse, FailureMessageKind},
+    options::{CommandOptions, OptionError},
+    subcommand::{route, Invocation, SubCommand},
+    util::{has_permission, CommandResponse, FailureMessageKind},
 };
 
-pub struct SayCommand<'a> {
-    message: &'a str,
+/// the most entries of the log shown at once, so they fit within a single message
+const LOG_PAGE_SIZE: usize = 5;
+
+/// the text and embed options shared by sending and editing a message, as they were provided
+#[derive(CommandOptions)]
+struct SayContent {
+    /// The text of the message
+    #[option(max_length = 1900)]
+    text: Option<String>,
+    /// The title of an embed
+    #[option(max_length = 256)]
+    title: Option<String>,
+    /// The body of an embed
+    #[option(max_length = 4000)]
+    body: Option<String>,
+    /// The colour of the embed, as hex such as #ff8800
+    #[option(max_length = 7)]
+    colour: Option<String>,
+}
+
+impl SayContent {
+    /// build the message described by the options, an embed is only added if it has a title or a body
+    fn into_message(self, locale: Locale) -> Result<SayMessage, CommandResponse> {
+        let colour = match self.colour {
+            Some(colour) => Some(parse_colour(&colour).ok_or_else(|| {
+                CommandResponse::BasicFailure(Text::SayInvalidColour.get(locale).to_string())
+            })?),
+            None => None,
+        };
+        let embed = (self.title.is_some() || self.body.is_some()).then(|| SayEmbed {
+            title: self.title,
+            description: self.body,
+            colour,
+        });
+
+        Ok(SayMessage {
+            content: self.text,
+            embed,
+            attachments: vec![],
+        })
+    }
+}
+
+/// what `/say` was asked to do
+enum SayAction {
+    /// send a message now, or schedule it if a time is provided
+    Send {
+        content: SayContent,
+        channel_id: Option<u64>,
+        attachment: Option<AttachmentId>,
+        at: Option<String>,
+    },
+    /// edit a message the bot sent
+    Edit { link: String, content: SayContent },
+    /// delete a message the bot sent
+    Delete { link: String },
+    /// list the messages waiting to be sent
+    Scheduled,
+    /// stop a scheduled message from being sent
+    Cancel { id: String },
+    /// show a page of the log, newest first, optionally only the entries of one member
+    Log { user_id: Option<u64>, page: usize },
+    /// set or clear the channel the log is mirrored to
+    Audit { channel_id: Option<u64> },
+}
+
+pub struct SayCommand {
+    action: SayAction,
 }
 
-impl<'a> TryFrom<&'a CommandInteraction> for SayCommand<'a> {
-    type Error = String;
+impl<'a> TryFrom<&'a CommandInteraction> for SayCommand {
+    type Error = OptionError;
     fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
-        let message = interaction
-            .data
-            .options
-            .get(0)
-            .ok_or("No message provided")?
-            .value
-            .as_str()
-            .ok_or("No message provided")?;
-        Ok(Self { message })
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+/// the options of `/say send`
+#[derive(CommandOptions)]
+struct SendOptions {
+    #[option(flatten)]
+    content: SayContent,
+    /// The channel to send to, this channel if not provided
+    #[option(channel_types(Text, News))]
+    channel: Option<ChannelId>,
+    /// A file to attach
+    attachment: Option<AttachmentId>,
+    /// When to send the message, as YYYY-MM-DD HH:MM in your timezone
+    at: Option<String>,
+}
+
+impl From<SendOptions> for SayCommand {
+    fn from(options: SendOptions) -> Self {
+        Self {
+            action: SayAction::Send {
+                content: options.content,
+                channel_id: options.channel.map(u64::from),
+                attachment: options.attachment,
+                at: options.at,
+            },
+        }
+    }
+}
+
+/// the options of `/say edit`
+#[derive(CommandOptions)]
+struct EditOptions {
+    /// A link to the message, from Copy Message Link
+    link: String,
+    #[option(flatten)]
+    content: SayContent,
+}
+
+impl From<EditOptions> for SayCommand {
+    fn from(options: EditOptions) -> Self {
+        Self {
+            action: SayAction::Edit {
+                link: options.link,
+                content: options.content,
+            },
+        }
+    }
+}
+
+/// the options of `/say delete`
+#[derive(CommandOptions)]
+struct DeleteOptions {
+    /// A link to the message, from Copy Message Link
+    link: String,
+}
+
+impl From<DeleteOptions> for SayCommand {
+    fn from(options: DeleteOptions) -> Self {
+        Self {
+            action: SayAction::Delete { link: options.link },
+        }
+    }
+}
+
+/// the options of `/say cancel`
+#[derive(CommandOptions)]
+struct CancelOptions {
+    /// The id of the scheduled message
+    id: String,
+}
+
+impl From<CancelOptions> for SayCommand {
+    fn from(options: CancelOptions) -> Self {
+        Self {
+            action: SayAction::Cancel { id: options.id },
+        }
+    }
+}
+
+/// the options of `/say log`
+#[derive(CommandOptions)]
+struct LogOptions {
+    /// Only show what this member did
+    user: Option<UserId>,
+    /// The page of the log to show, the newest entries are on the first
+    #[option(min_value = 1)]
+    page: Option<i64>,
+}
+
+impl From<LogOptions> for SayCommand {
+    fn from(options: LogOptions) -> Self {
+        Self {
+            action: SayAction::Log {
+                user_id: options.user.map(u64::from),
+                page: options.page.map_or(1, |p| p as usize),
+            },
+        }
+    }
+}
+
+/// the options of `/say audit`
+#[derive(CommandOptions)]
+struct AuditOptions {
+    /// The channel to mirror the log to, stops mirroring if not provided
+    #[option(channel_types(Text))]
+    channel: Option<ChannelId>,
+}
+
+impl From<AuditOptions> for SayCommand {
+    fn from(options: AuditOptions) -> Self {
+        Self {
+            action: SayAction::Audit {
+                channel_id: options.channel.map(u64::from),
+            },
+        }
+    }
+}
+
+/// find the channel and message a link points to, if it is a message in this guild
+fn message_link(link: &str, guild_id: u64, locale: Locale) -> Result<(u64, u64), CommandResponse> {
+    match parse_message_link(link) {
+        Some((linked_guild, channel_id, message_id)) if linked_guild == guild_id => {
+            Ok((channel_id, message_id))
+        }
+        _ => Err(CommandResponse::BasicFailure(
+            Text::SayInvalidLink.get(locale).to_string(),
+        )),
+    }
+}
+
+/// an entry for the log, of something done now
+fn log_entry(
+    action: SayLogAction,
+    user_id: u64,
+    channel_id: u64,
+    message_id: Option<u64>,
+    content: String,
+) -> SayLogEntry {
+    SayLogEntry {
+        action,
+        user_id,
+        at: Utc::now(),
+        channel_id,
+        message_id,
+        content,
+    }
+}
+
+/// the failure returned when discord refuses a message, or the message it refers to no longer exists
+fn transport_failure(locale: Locale, e: TransportError) -> CommandResponse {
+    match e {
+        TransportError::NotFound => {
+            CommandResponse::BasicFailure(Text::SayMessageMissing.get(locale).to_string())
+        }
+        e => CommandResponse::ComplexFailure {
+            response: Text::SayFailed.get(locale).to_string(),
+            kind: FailureMessageKind::Error,
+            log_message: e.to_string(),
+        },
+    }
+}
+
+/// refuse to change a message unless it was sent with `/say` in this guild and still exists.
+/// the bot sends messages of its own, such as standup submissions, which `/say` must not change
+async fn require_said(
+    app_state: &AppState,
+    transport: &dyn Transport,
+    guild_id: u64,
+    channel_id: u64,
+    message_id: u64,
+    locale: Locale,
+) -> Result<(), CommandResponse> {
+    let said = said_message(&app_state.storage, guild_id, channel_id, message_id).await
+        && transport
+            .is_own_message(channel_id, message_id)
+            .await
+            .map_err(|e| transport_failure(locale, e))?;
+    match said {
+        true => Ok(()),
+        false => Err(CommandResponse::BasicFailure(
+            Text::SayNotOwnMessage.get(locale).to_string(),
+        )),
     }
 }
 
 #[async_trait]
-impl<'a> Command<'a> for SayCommand<'a> {
+impl<'a> Command<'a> for SayCommand {
     fn name() -> &'static str {
         "say"
     }
@@ -44,43 +304,805 @@ impl<'a> Command<'a> for SayCommand<'a> {
         "Says whatever you want!"
     }
 
-    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
-        i.add_option(
-            CreateCommandOption::new(
-                CommandOptionType::String,
-                "text",
-                "What you want the bot to say",
-            )
-            .required(true)
-            .max_length(1900)
-            .to_owned(),
-        )
+    fn module() -> Module {
+        Module::Utility
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn capability() -> Option<Capability> {
+        Some(Capability::SayUse)
+    }
+
+    fn cooldowns() -> Vec<Cooldown> {
+        vec![
+            Cooldown::new(CooldownScope::User, 3, Duration::from_secs(30)),
+            Cooldown::new(CooldownScope::Channel, 5, Duration::from_secs(60)),
+            Cooldown::new(CooldownScope::Guild, 20, Duration::from_secs(60)),
+        ]
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self, OptionError>> {
+        vec![
+            SubCommand::with_options::<SendOptions>("send", "Send a message as the bot"),
+            SubCommand::with_options::<EditOptions>("edit", "Edit a message sent by the bot"),
+            SubCommand::with_options::<DeleteOptions>("delete", "Delete a message sent by the bot"),
+            SubCommand::new("scheduled", "List the messages waiting to be sent", |_| {
+                Ok(Self {
+                    action: SayAction::Scheduled,
+                })
+            }),
+            SubCommand::with_options::<CancelOptions>(
+                "cancel",
+                "Stop a scheduled message from being sent",
+            ),
+            SubCommand::with_options::<LogOptions>(
+                "log",
+                "Show who made the bot send which messages",
+            ),
+            SubCommand::with_options::<AuditOptions>("audit", "Mirror the log to a channel"),
+        ]
     }
 
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
-        _: &'b AppState,
-        ctx: &'b Context,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
-        if let Err(e) = interaction
-            .channel_id
-            .send_message(ctx, CreateMessage::new().content(self.message))
-            .await
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "say command used outside of a guild",
+                ))
+            })?
+            .into();
+        let user_id: u64 = interaction.user.id.into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            user_id,
+            &interaction.locale,
+        )
+        .await;
+        let users = user_mentions_allowed(&app_state.storage, Some(guild_id)).await;
+
+        // the log says who used the bot to speak, so only admins may read it or choose where it goes
+        if matches!(self.action, SayAction::Log { .. } | SayAction::Audit { .. })
+            && !has_permission(interaction, Permissions::MANAGE_GUILD)
         {
-            return Err(CommandResponse::ComplexFailure {
-                response: String::from("Failed to use /say due to error"),
-                kind: FailureMessageKind::Error,
-                log_message: e.to_string(),
-            });
+            return Err(CommandResponse::BasicFailure(
+                Text::ManageGuildRequired.get(locale).to_string(),
+            ));
         }
 
+        let response = match self.action {
+            SayAction::Send {
+                content,
+                channel_id,
+                attachment,
+                at,
+            } => {
+                let channel_id = channel_id.unwrap_or_else(|| interaction.channel_id.into());
+                // discord resolves the permissions the member has in a channel chosen as an option
+                let allowed = interaction
+                    .data
+                    .resolved
+                    .channels
+                    .get(&ChannelId::new(channel_id))
+                    .and_then(|c| c.permissions)
+                    .map_or(true, |p| p.contains(Permissions::SEND_MESSAGES));
+                if !allowed {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::SayChannelDenied
+                            .format(locale, &[("channel", &channel_id.to_string())]),
+                    ));
+                }
+
+                let send_at = match at {
+                    Some(at) => {
+                        let tz = user_timezone(&app_state.storage, user_id).await;
+                        let send_at = parse_send_time(&at, tz).ok_or_else(|| {
+                            CommandResponse::BasicFailure(
+                                Text::SayInvalidTime.get(locale).to_string(),
+                            )
+                        })?;
+                        if send_at <= Utc::now() {
+                            return Err(CommandResponse::BasicFailure(
+                                Text::SayTimeInPast.get(locale).to_string(),
+                            ));
+                        }
+                        Some(send_at)
+                    }
+                    None => None,
+                };
+
+                let mut message = content.into_message(locale)?;
+                if let Some(attachment_id) = attachment {
+                    let attachment = interaction
+                        .data
+                        .resolved
+                        .attachments
+                        .get(&attachment_id)
+                        .ok_or_else(|| {
+                            CommandResponse::InternalFailure(String::from(
+                                "say attachment was not resolved",
+                            ))
+                        })?;
+                    // scheduled attachments are kept on disk until they are sent, so their size is limited
+                    if send_at.is_some()
+                        && attachment.size as usize > MAX_SCHEDULED_ATTACHMENT_BYTES
+                    {
+                        return Err(CommandResponse::BasicFailure(
+                            Text::SayAttachmentTooLarge.format(
+                                locale,
+                                &[(
+                                    "size",
+                                    &(MAX_SCHEDULED_ATTACHMENT_BYTES / 1024 / 1024).to_string(),
+                                )],
+                            ),
+                        ));
+                    }
+                    let data = attachment
+                        .download()
+                        .await
+                        .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+                    let stored = store_attachment(
+                        &app_state.storage,
+                        interaction.id.into(),
+                        message.attachments.len(),
+                        &attachment.filename,
+                        &data,
+                    )
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+                    message.attachments.push(stored);
+                }
+                if message.is_empty() {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::SayEmpty.get(locale).to_string(),
+                    ));
+                }
+
+                match send_at {
+                    Some(send_at) => {
+                        let summary = message.summary();
+                        let attachments = message.attachments.clone();
+                        let scheduled = ScheduledMessage {
+                            id: interaction.id.into(),
+                            channel_id,
+                            author_id: user_id,
+                            send_at,
+                            message,
+                        };
+                        let id = scheduled.id;
+                        let stored = app_state
+                            .storage
+                            .update(|data| {
+                                let waiting = &mut data
+                                    .guilds
+                                    .entry(guild_id)
+                                    .or_default()
+                                    .scheduled_messages;
+                                if waiting.len() >= MAX_SCHEDULED_MESSAGES {
+                                    return false;
+                                }
+                                waiting.push(scheduled);
+                                true
+                            })
+                            .await;
+                        // the attachments are only kept for a message which is waiting to be sent
+                        if !matches!(stored, Ok(true)) {
+                            remove_attachments(&attachments).await;
+                        }
+                        if !stored.map_err(|e| CommandResponse::InternalFailure(e.to_string()))? {
+                            return Err(CommandResponse::BasicFailure(
+                                Text::SayScheduleLimit.format(
+                                    locale,
+                                    &[("count", &MAX_SCHEDULED_MESSAGES.to_string())],
+                                ),
+                            ));
+                        }
+                        let entry =
+                            log_entry(SayLogAction::Scheduled, user_id, channel_id, None, summary);
+                        audit(transport, app_state, guild_id, entry).await;
+
+                        Text::SayScheduled.format(
+                            locale,
+                            &[
+                                ("id", &id.to_string()),
+                                (
+                                    "time",
+                                    &discord_timestamp(send_at, TimestampStyle::ShortDateTime),
+                                ),
+                            ],
+                        )
+                    }
+                    None => {
+                        let sent = match create_message(&message, users).await {
+                            Ok(create) => transport
+                                .send_message(channel_id, create)
+                                .await
+                                .map_err(|e| transport_failure(locale, e)),
+                            Err(e) => Err(CommandResponse::InternalFailure(e.to_string())),
+                        };
+                        remove_attachments(&message.attachments).await;
+                        let message_id = sent?;
+                        let entry = log_entry(
+                            SayLogAction::Sent,
+                            user_id,
+                            channel_id,
+                            Some(message_id),
+                            message.summary(),
+                        );
+                        audit(transport, app_state, guild_id, entry).await;
+                        Text::SaySent.format(locale, &[("channel", &channel_id.to_string())])
+                    }
+                }
+            }
+            SayAction::Edit { link, content } => {
+                let (channel_id, message_id) = message_link(&link, guild_id, locale)?;
+                let message = content.into_message(locale)?;
+                if message.is_empty() {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::SayEmpty.get(locale).to_string(),
+                    ));
+                }
+
+                require_said(
+                    app_state, transport, guild_id, channel_id, message_id, locale,
+                )
+                .await?;
+
+                transport
+                    .edit_message(channel_id, message_id, edit_message(&message, users))
+                    .await
+                    .map_err(|e| transport_failure(locale, e))?;
+                let entry = log_entry(
+                    SayLogAction::Edited,
+                    user_id,
+                    channel_id,
+                    Some(message_id),
+                    message.summary(),
+                );
+                audit(transport, app_state, guild_id, entry).await;
+                Text::SayEdited.get(locale).to_string()
+            }
+            SayAction::Delete { link } => {
+                let (channel_id, message_id) = message_link(&link, guild_id, locale)?;
+                require_said(
+                    app_state, transport, guild_id, channel_id, message_id, locale,
+                )
+                .await?;
+
+                transport
+                    .delete_message(channel_id, message_id)
+                    .await
+                    .map_err(|e| transport_failure(locale, e))?;
+                // the message is gone, so what it last said is taken from the log
+                let content = app_state
+                    .storage
+                    .read()
+                    .await
+                    .guilds
+                    .get(&guild_id)
+                    .and_then(|g| {
+                        g.say_log
+                            .iter()
+                            .rev()
+                            .find(|e| e.message_id == Some(message_id))
+                    })
+                    .map(|e| e.content.clone())
+                    .unwrap_or_default();
+                let entry = log_entry(
+                    SayLogAction::Deleted,
+                    user_id,
+                    channel_id,
+                    Some(message_id),
+                    content,
+                );
+                audit(transport, app_state, guild_id, entry).await;
+                Text::SayDeleted.get(locale).to_string()
+            }
+            SayAction::Scheduled => {
+                let data = app_state.storage.read().await;
+                let waiting = data
+                    .guilds
+                    .get(&guild_id)
+                    .map(|g| g.scheduled_messages.as_slice())
+                    .unwrap_or_default();
+                match waiting.is_empty() {
+                    true => Text::SayScheduledNone.get(locale).to_string(),
+                    false => waiting
+                        .iter()
+                        .map(|m| {
+                            format!(
+                                "- `{}` <#{}> {} <@{}>",
+                                m.id,
+                                m.channel_id,
+                                discord_timestamp(m.send_at, TimestampStyle::ShortDateTime),
+                                m.author_id
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }
+            SayAction::Cancel { id } => {
+                let missing = || {
+                    CommandResponse::BasicFailure(
+                        Text::SayScheduledMissing.format(locale, &[("id", &id)]),
+                    )
+                };
+                let scheduled_id: u64 = id.trim().parse().map_err(|_| missing())?;
+
+                let removed = app_state
+                    .storage
+                    .update(|data| {
+                        let waiting =
+                            &mut data.guilds.entry(guild_id).or_default().scheduled_messages;
+                        let index = waiting.iter().position(|m| m.id == scheduled_id)?;
+                        Some(waiting.remove(index))
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?
+                    .ok_or_else(missing)?;
+                remove_attachments(&removed.message.attachments).await;
+                let entry = log_entry(
+                    SayLogAction::Cancelled,
+                    user_id,
+                    removed.channel_id,
+                    None,
+                    removed.message.summary(),
+                );
+                audit(transport, app_state, guild_id, entry).await;
+
+                Text::SayScheduledCancelled.format(locale, &[("id", &id)])
+            }
+            SayAction::Log { user_id, page } => {
+                let data = app_state.storage.read().await;
+                let entries = data
+                    .guilds
+                    .get(&guild_id)
+                    .map(|g| g.say_log.as_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .rev()
+                    .filter(|e| user_id.map_or(true, |u| e.user_id == u))
+                    .collect::<Vec<_>>();
+                let pages = entries.len().div_ceil(LOG_PAGE_SIZE);
+                if entries.is_empty() {
+                    Text::SayLogNone.get(locale).to_string()
+                } else if page > pages {
+                    Text::SayLogPageMissing.format(locale, &[("pages", &pages.to_string())])
+                } else {
+                    let mut lines = entries
+                        .iter()
+                        .skip((page - 1) * LOG_PAGE_SIZE)
+                        .take(LOG_PAGE_SIZE)
+                        .map(|e| render_log_entry(locale, guild_id, e))
+                        .collect::<Vec<_>>();
+                    lines.push(Text::SayLogPage.format(
+                        locale,
+                        &[("page", &page.to_string()), ("pages", &pages.to_string())],
+                    ));
+                    lines.join("\n")
+                }
+            }
+            SayAction::Audit { channel_id } => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds.entry(guild_id).or_default().audit_channel = channel_id
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                match channel_id {
+                    Some(channel_id) => Text::SayAuditChannelSet
+                        .format(locale, &[("channel", &channel_id.to_string())]),
+                    None => Text::SayAuditChannelCleared.get(locale).to_string(),
+                }
+            }
+        };
+
         Ok(CommandResponse::ComplexSuccess(
             CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(format!("I will send: {}", self.message))
+                    .content(response)
+                    .allowed_mentions(CreateAllowedMentions::new())
                     .ephemeral(true),
             ),
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value};
+    use serenity::{builder::CreateMessage, model::Permissions};
+
+    use crate::{
+        announcements::{
+            record_say, store_attachment, take_due_messages, SayLogAction, SayLogEntry, SayMessage,
+            ScheduledMessage, SEND_TIME_FORMAT,
+        },
+        discord_bot::{
+            commands::{command::Command, fixtures, util::CommandResponse},
+            transport::{RecordingTransport, Sent, Transport},
+        },
+    };
+
+    use super::SayCommand;
+
+    /// the options of `/say` invoked through a subcommand
+    fn subcommand(name: &str, options: Value) -> Value {
+        json!([{ "name": name, "type": 1, "options": options }])
+    }
+
+    #[tokio::test]
+    async fn say_sends_the_message_and_confirms() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let interaction = fixtures::command(
+            3,
+            "say",
+            subcommand(
+                "send",
+                json!([
+                    { "name": "text", "type": 3, "value": "hello" },
+                    { "name": "title", "type": 3, "value": "News" },
+                    { "name": "colour", "type": 3, "value": "#ff8800" },
+                ]),
+            ),
+        );
+
+        let res = SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Ok(CommandResponse::ComplexSuccess(_))));
+
+        match transport.sent().as_slice() {
+            [Sent::Message {
+                channel_id, body, ..
+            }] => {
+                assert_eq!(*channel_id, fixtures::CHANNEL_ID);
+                assert_eq!(body["content"], "hello");
+                assert_eq!(body["embeds"][0]["title"], "News");
+                assert_eq!(body["embeds"][0]["color"], 0xff8800);
+            }
+            other => panic!("expected a single message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn say_reports_a_failure_to_send() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        transport.fail();
+        let interaction = fixtures::command(
+            3,
+            "say",
+            subcommand(
+                "send",
+                json!([{ "name": "text", "type": 3, "value": "hello" }]),
+            ),
+        );
+
+        let res = SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Err(CommandResponse::ComplexFailure { .. })));
+    }
+
+    #[tokio::test]
+    async fn sent_messages_are_logged_and_mirrored() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let audit_channel = 99;
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .audit_channel = Some(audit_channel)
+            })
+            .await
+            .unwrap();
+        let interaction = fixtures::command(
+            3,
+            "say",
+            subcommand(
+                "send",
+                json!([{ "name": "text", "type": 3, "value": "hello" }]),
+            ),
+        );
+
+        SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await
+            .unwrap();
+
+        let data = app_state.storage.read().await;
+        let log = &data.guilds[&fixtures::GUILD_ID].say_log;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].action, SayLogAction::Sent);
+        assert_eq!(log[0].user_id, fixtures::USER_ID);
+        assert_eq!(log[0].channel_id, fixtures::CHANNEL_ID);
+        assert_eq!(log[0].content, "hello");
+        match transport.sent().as_slice() {
+            [Sent::Message { message_id, .. }, Sent::Message {
+                channel_id, body, ..
+            }] => {
+                assert_eq!(log[0].message_id, Some(*message_id));
+                assert_eq!(*channel_id, audit_channel);
+                let mirrored = body["embeds"][0]["description"].as_str().unwrap();
+                assert!(mirrored.contains("hello"));
+                assert_eq!(body["allowed_mentions"]["parse"], json!([]));
+            }
+            other => panic!("expected the message and its mirror, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduled_messages_are_stored_until_due() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let at = (Utc::now() + Duration::days(1))
+            .format(SEND_TIME_FORMAT)
+            .to_string();
+        let interaction = fixtures::command(
+            3,
+            "say",
+            subcommand(
+                "send",
+                json!([
+                    { "name": "text", "type": 3, "value": "later" },
+                    { "name": "at", "type": 3, "value": at },
+                ]),
+            ),
+        );
+
+        let res = SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Ok(CommandResponse::ComplexSuccess(_))));
+        assert!(transport.sent().is_empty());
+
+        let storage = &app_state.storage;
+        let now = Utc::now();
+        assert!(take_due_messages(storage, fixtures::GUILD_ID, now)
+            .await
+            .unwrap()
+            .is_empty());
+        let due = take_due_messages(storage, fixtures::GUILD_ID, now + Duration::days(2))
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].message.content.as_deref(), Some("later"));
+        assert!(storage.read().await.guilds[&fixtures::GUILD_ID]
+            .scheduled_messages
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn say_only_edits_its_own_messages() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let link = |message_id: u64| {
+            format!(
+                "https://discord.com/channels/{}/{}/{}",
+                fixtures::GUILD_ID,
+                fixtures::CHANNEL_ID,
+                message_id
+            )
+        };
+        let edit = |id: u64, message_id: u64| {
+            fixtures::command(
+                id,
+                "say",
+                subcommand(
+                    "edit",
+                    json!([
+                        { "name": "link", "type": 3, "value": link(message_id) },
+                        { "name": "text", "type": 3, "value": "edited" },
+                    ]),
+                ),
+            )
+        };
+
+        let missing = edit(3, 1);
+        let res = SayCommand::try_from(&missing)
+            .unwrap()
+            .handle_application_command(&missing, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Err(CommandResponse::BasicFailure(_))));
+
+        let send = fixtures::command(
+            4,
+            "say",
+            subcommand(
+                "send",
+                json!([{ "name": "text", "type": 3, "value": "hello" }]),
+            ),
+        );
+        SayCommand::try_from(&send)
+            .unwrap()
+            .handle_application_command(&send, &app_state, &transport)
+            .await
+            .unwrap();
+        let message_id = match transport.sent().as_slice() {
+            [Sent::Message { message_id, .. }] => *message_id,
+            other => panic!("expected a single message, got {:?}", other),
+        };
+        let interaction = edit(5, message_id);
+        let res = SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Ok(CommandResponse::ComplexSuccess(_))));
+        assert!(transport.sent().iter().any(|s| matches!(
+            s,
+            Sent::Edit { message_id: m, body, .. } if *m == message_id && body["content"] == "edited"
+        )));
+    }
+
+    #[tokio::test]
+    async fn say_refuses_messages_it_did_not_send() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        // the bot posts standup submissions itself, so they are its own messages without being sent by `/say`
+        let message_id = transport
+            .send_message(
+                fixtures::CHANNEL_ID,
+                CreateMessage::new().content("submission"),
+            )
+            .await
+            .unwrap();
+        let link = format!(
+            "https://discord.com/channels/{}/{}/{}",
+            fixtures::GUILD_ID,
+            fixtures::CHANNEL_ID,
+            message_id
+        );
+
+        for (id, options) in [
+            (
+                3,
+                subcommand(
+                    "edit",
+                    json!([
+                        { "name": "link", "type": 3, "value": link },
+                        { "name": "text", "type": 3, "value": "edited" },
+                    ]),
+                ),
+            ),
+            (
+                4,
+                subcommand(
+                    "delete",
+                    json!([{ "name": "link", "type": 3, "value": link }]),
+                ),
+            ),
+        ] {
+            let interaction = fixtures::command(id, "say", options);
+            let res = SayCommand::try_from(&interaction)
+                .unwrap()
+                .handle_application_command(&interaction, &app_state, &transport)
+                .await;
+            assert!(matches!(res, Err(CommandResponse::BasicFailure(_))));
+        }
+        assert_eq!(transport.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_message_removes_its_attachments() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let attachment = store_attachment(&app_state.storage, 1, 0, "notes.txt", b"notes")
+            .await
+            .unwrap();
+        let path = attachment.path.clone();
+        let scheduled = ScheduledMessage {
+            id: 1,
+            channel_id: fixtures::CHANNEL_ID,
+            author_id: fixtures::USER_ID,
+            send_at: Utc::now() + Duration::days(1),
+            message: SayMessage {
+                attachments: vec![attachment],
+                ..SayMessage::default()
+            },
+        };
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds
+                    .entry(fixtures::GUILD_ID)
+                    .or_default()
+                    .scheduled_messages
+                    .push(scheduled)
+            })
+            .await
+            .unwrap();
+        assert!(path.exists());
+
+        let interaction = fixtures::command(
+            3,
+            "say",
+            subcommand("cancel", json!([{ "name": "id", "type": 3, "value": "1" }])),
+        );
+        let res = SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await;
+        assert!(matches!(res, Ok(CommandResponse::ComplexSuccess(_))));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn the_log_is_shown_a_page_at_a_time() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        for i in 0..7 {
+            let entry = SayLogEntry {
+                action: SayLogAction::Sent,
+                user_id: fixtures::USER_ID,
+                at: Utc::now(),
+                channel_id: fixtures::CHANNEL_ID,
+                message_id: Some(i),
+                content: format!("message {}", i),
+            };
+            record_say(&app_state.storage, fixtures::GUILD_ID, entry)
+                .await
+                .unwrap();
+        }
+        let log = |id: u64, page: i64| {
+            let mut interaction = fixtures::command(
+                id,
+                "say",
+                subcommand("log", json!([{ "name": "page", "type": 4, "value": page }])),
+            );
+            interaction.member.as_mut().unwrap().permissions = Some(Permissions::MANAGE_GUILD);
+            interaction
+        };
+
+        let interaction = log(3, 2);
+        match SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await
+        {
+            Ok(CommandResponse::ComplexSuccess(response)) => {
+                let body = serde_json::to_value(response).unwrap();
+                let content = body["data"]["content"].as_str().unwrap();
+                assert!(content.contains("message 1"));
+                assert!(content.contains("message 0"));
+                assert!(!content.contains("message 2"));
+                assert!(content.ends_with("Page 2 of 2"));
+            }
+            other => panic!("expected the second page, got {:?}", other),
+        }
+
+        let interaction = log(4, 3);
+        match SayCommand::try_from(&interaction)
+            .unwrap()
+            .handle_application_command(&interaction, &app_state, &transport)
+            .await
+        {
+            Ok(CommandResponse::ComplexSuccess(response)) => {
+                let body = serde_json::to_value(response).unwrap();
+                assert_eq!(body["data"]["content"], "The log only has 2 pages.");
+            }
+            other => panic!("expected the number of pages, got {:?}", other),
+        }
+    }
+}
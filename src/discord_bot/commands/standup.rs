@@ -1,28 +1,155 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
 use log::error;
 use serenity::{
     all::{ActionRowComponent, CommandInteraction, ComponentInteraction, ModalInteraction},
     async_trait,
     builder::{
-        CreateActionRow, CreateButton, CreateCommand, CreateInputText, CreateInteractionResponse,
-        CreateInteractionResponseMessage, CreateModal,
+        CreateActionRow, CreateAllowedMentions, CreateButton, CreateCommand, CreateInputText,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal,
     },
-    prelude::Context,
+    model::Permissions,
 };
 
-use crate::state::AppState;
+use crate::{
+    cooldown::{Cooldown, CooldownScope},
+    discord_bot::{
+        board::{refresh_board, MemberCache},
+        custom_id::ComponentId,
+        mentions::allowed_mentions,
+        panel::{self, close_session, refresh_panel},
+        transport::{InteractionRef, Transport},
+    },
+    holidays::holiday_on,
+    locale::{self, standup_questions, Locale, Text},
+    mentions::{neutralise, user_mentions_allowed},
+    modules::Module,
+    references::{find_references, link_references, CompiledPatterns, Reference},
+    standups::{StandupSession, Submission},
+    state::AppState,
+    timezone::{discord_timestamp, monday_of_week, user_timezone, TimestampStyle},
+    webhook::{self, WebhookEvent},
+};
 
 use super::{
     command::{Command, InteractionCommand, ModalSubmit},
-    util::CommandResponse,
+    util::{member_has_permission, CommandResponse},
 };
 
-static QUESTION_LIST: &[&str] = &[
-    "What did you work on last week?",
-    "What are you working on this week?",
-    "When are you aiming to finish?",
-    "Is there anything blocking you?",
-    "is there anything you need help with?",
-];
+/// the maximum length of a message discord will accept
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// the format the week a submission reports on is written in
+const WEEK_OF_FORMAT: &str = "%Y-%m-%d";
+
+/// get the compiled patterns a guild uses to link issue tracker references
+async fn link_patterns(app_state: &AppState, guild_id: u64) -> Arc<CompiledPatterns> {
+    let data = app_state.storage.read().await;
+    let patterns = data
+        .guilds
+        .get(&guild_id)
+        .map(|g| g.link_patterns.as_slice())
+        .unwrap_or_default();
+    app_state.link_patterns.get(guild_id, patterns)
+}
+
+/// find the unique references across every answer of a submission
+fn collect_references(patterns: &CompiledPatterns, answers: &[String]) -> Vec<Reference> {
+    let mut references: Vec<Reference> = vec![];
+    for (_, _, reference) in answers.iter().flat_map(|a| find_references(patterns, a)) {
+        if !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    references
+}
+
+/// render a submission as the message posted to the channel
+async fn render_submission(
+    app_state: &AppState,
+    locale: Locale,
+    submission: &Submission,
+) -> String {
+    // the week being reported on is the submitter's own week, not the week of the server. it is written as a plain
+    // date, as a discord timestamp would show the submitter's monday as sunday to anyone further west
+    let timezone = user_timezone(&app_state.storage, submission.user_id).await;
+    let monday = monday_of_week(timezone, submission.submitted_at);
+    let week_of = Text::StandupWeekOf.format(
+        locale,
+        &[("date", &monday.format(WEEK_OF_FORMAT).to_string())],
+    );
+
+    let patterns = link_patterns(app_state, submission.guild_id).await;
+    let render = |link: bool| {
+        let answers = submission
+            .questions
+            .iter()
+            .zip(submission.answers.iter())
+            .map(|(question, answer)| match link {
+                true => format!(
+                    "**{}**\r{}",
+                    question,
+                    link_references(&patterns, &neutralise(answer))
+                ),
+                false => format!("**{}**\r{}", question, neutralise(answer)),
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{}\r_{} · {}_\r{}",
+            Text::StandupSubmission.format(locale, &[("user", &submission.username)]),
+            week_of,
+            discord_timestamp(submission.submitted_at, TimestampStyle::ShortDateTime),
+            answers.join("\r")
+        )
+    };
+
+    // links make a message longer, so leave them out if they would push it over the limit
+    let linked = render(true);
+    match linked.chars().count() > MAX_MESSAGE_LENGTH {
+        true => render(false),
+        false => linked,
+    }
+}
+
+/// the buttons attached to a posted submission, allowing the author to change it
+fn submission_buttons(locale: Locale, submission_id: u64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(ComponentId::StandupEdit { submission_id }.encode())
+            .style(serenity::all::ButtonStyle::Secondary)
+            .label(Text::StandupEditButton.get(locale)),
+        CreateButton::new(ComponentId::StandupDelete { submission_id }.encode())
+            .style(serenity::all::ButtonStyle::Danger)
+            .label(Text::StandupDeleteButton.get(locale)),
+    ])]
+}
+
+/// build the modal containing each question, optionally pre-filled with existing answers
+pub fn standup_modal(
+    id: ComponentId,
+    locale: Locale,
+    questions: &[&str],
+    answers: Option<&[String]>,
+) -> CreateModal {
+    CreateModal::new(id.encode(), Text::StandupModalTitle.get(locale)).components(
+        questions
+            .iter()
+            .enumerate()
+            .map(|(i, question)| {
+                let mut input = CreateInputText::new(
+                    serenity::all::InputTextStyle::Short,
+                    *question,
+                    format!("standup-question-{}", i),
+                );
+                if let Some(answer) = answers.and_then(|a| a.get(i)) {
+                    input = input.value(answer);
+                }
+                CreateActionRow::InputText(input)
+            })
+            .collect(),
+    )
+}
 
 pub struct StandupCommand;
 
@@ -43,134 +170,743 @@ impl<'a> Command<'a> for StandupCommand {
         "Run the weekly standups"
     }
 
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn deferrable() -> bool {
+        false
+    }
+
+    fn cooldowns() -> Vec<Cooldown> {
+        vec![Cooldown::new(
+            CooldownScope::Channel,
+            2,
+            Duration::from_secs(300),
+        )]
+    }
+
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
         i
     }
 
     async fn handle_application_command<'b>(
         self,
-        _: &'b CommandInteraction,
-        _: &'b AppState,
-        _: &'b Context,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
-        Ok(CommandResponse::ComplexSuccess(
-            CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content("Click the button to start the standup")
-                    .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
-                        "standup-start",
-                    )
-                    .style(serenity::all::ButtonStyle::Primary)
-                    .label("Start Standup")])]),
-            ),
-        ))
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "standup command used outside of a guild",
+                ))
+            })?
+            .into();
+
+        // standups aren't expected on a holiday of the guild, so let everyone know it's optional
+        let timezone = user_timezone(&app_state.storage, interaction.user.id.into()).await;
+        let today = Utc::now().with_timezone(&timezone).date_naive();
+        let holiday = holiday_on(&app_state.storage, guild_id, None, today)
+            .await
+            .map(|h| h.name);
+
+        let mut session = StandupSession {
+            id: interaction.id.into(),
+            guild_id,
+            channel_id: interaction.channel_id.into(),
+            started_by: interaction.user.id.into(),
+            started_at: Utc::now(),
+            message_id: None,
+            holiday,
+            closed_at: None,
+            digest_message_id: None,
+        };
+
+        // respond before fetching the members of the guild, which can take longer than discord waits for a
+        // response, the pending members are filled in by refreshing the panel once it has been posted
+        let locale = locale::guild_locale(&app_state.storage, guild_id).await;
+        let (content, components) = panel::opening_panel(locale, &session);
+        if let Err(e) = transport
+            .respond(
+                InteractionRef::from(interaction),
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(content)
+                        .components(components)
+                        .allowed_mentions(CreateAllowedMentions::new()),
+                ),
+            )
+            .await
+        {
+            error!("Error sending standup panel: {}", e);
+            return Ok(CommandResponse::NoResponse);
+        }
+
+        match transport
+            .response_message(InteractionRef::from(interaction))
+            .await
+        {
+            Ok(message_id) => session.message_id = Some(message_id),
+            Err(e) => error!("Unable to fetch posted standup panel: {}", e),
+        }
+
+        let session_id = session.id;
+        app_state
+            .storage
+            .update(|data| data.sessions.insert(session.id, session))
+            .await
+            .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+        refresh_panel(transport, app_state, &MemberCache::default(), session_id).await;
+
+        Ok(CommandResponse::NoResponse)
     }
 }
 
 #[async_trait]
 impl InteractionCommand<'_> for StandupCommand {
-    async fn answerable<'b>(
-        interaction: &'b ComponentInteraction,
-        _: &'b AppState,
-        _: &'b Context,
-    ) -> bool {
-        interaction.data.custom_id == "standup-start"
+    fn namespace() -> &'static str {
+        "standup"
+    }
+
+    fn deferrable(id: &ComponentId) -> bool {
+        // both refresh the board and the panel, and are answered by the response the handler returns
+        matches!(
+            id,
+            ComponentId::StandupClose { .. } | ComponentId::StandupDelete { .. }
+        )
     }
 
     async fn interaction<'b>(
+        id: ComponentId,
         interaction: &'b ComponentInteraction,
-        _: &'b AppState,
-        context: &'b Context,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
-        interaction
-            .create_response(
-                context,
-                CreateInteractionResponse::Modal(
-                    CreateModal::new("standups", "Standups").components({
-                        QUESTION_LIST
-                            .iter()
-                            .enumerate()
-                            .map(|(i, question)| {
-                                CreateActionRow::InputText(CreateInputText::new(
-                                    serenity::all::InputTextStyle::Short,
-                                    *question,
-                                    format!("standup-question-{}", i),
-                                ))
-                            })
-                            .collect()
-                    }),
-                ),
-            )
+        let locale = locale::resolve(
+            &app_state.storage,
+            interaction.guild_id.map(u64::from),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+        let user_id: u64 = interaction.user.id.into();
+
+        if let ComponentId::StandupClose { session_id } = id {
+            let started_by = app_state
+                .storage
+                .read()
+                .await
+                .sessions
+                .get(&session_id)
+                .map(|s| s.started_by);
+            let allowed = started_by == Some(user_id)
+                || member_has_permission(interaction.member.as_ref(), Permissions::MANAGE_GUILD);
+            if !allowed {
+                return Err(CommandResponse::BasicFailure(
+                    Text::StandupCloseDenied.get(locale).to_string(),
+                ));
+            }
+
+            // closing fetches the members of the guild, so it is answered by the dispatcher, which defers it if slow
+            let reply = match close_session(transport, app_state, session_id)
+                .await
+                .map_err(CommandResponse::InternalFailure)?
+            {
+                true => Text::StandupClosedReply,
+                false => Text::StandupAlreadyClosed,
+            };
+            return Ok(CommandResponse::BasicSuccess(reply.get(locale).to_string()));
+        }
+
+        if let ComponentId::StandupStart { session_id } = id {
+            let open = app_state
+                .storage
+                .read()
+                .await
+                .sessions
+                .get(&session_id)
+                .map_or(true, |s| s.is_open());
+            if !open {
+                return Err(CommandResponse::BasicFailure(
+                    Text::StandupAlreadyClosed.get(locale).to_string(),
+                ));
+            }
+
+            transport
+                .respond(
+                    InteractionRef::from(interaction),
+                    CreateInteractionResponse::Modal(standup_modal(
+                        ComponentId::StandupSubmit {
+                            session_id: Some(session_id),
+                        },
+                        locale,
+                        standup_questions(locale),
+                        None,
+                    )),
+                )
+                .await
+                .map_err(|e| {
+                    CommandResponse::InternalFailure(format!("failed to open standup modal: {}", e))
+                })?;
+
+            return Ok(CommandResponse::NoResponse);
+        }
+
+        let (submission_id, deleting) = match id {
+            ComponentId::StandupEdit { submission_id } => (submission_id, false),
+            ComponentId::StandupDelete { submission_id } => (submission_id, true),
+            id => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "unexpected standup component {:?}",
+                    id
+                )))
+            }
+        };
+
+        let submission = app_state
+            .storage
+            .read()
             .await
-            .unwrap();
+            .submissions
+            .get(&submission_id)
+            .cloned();
+        let submission = match submission {
+            Some(s) if s.user_id == user_id => s,
+            Some(_) => {
+                return Err(CommandResponse::BasicFailure(
+                    Text::StandupNotYours.get(locale).to_string(),
+                ));
+            }
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    Text::StandupMissing.get(locale).to_string(),
+                ));
+            }
+        };
 
-        Ok(CommandResponse::NoResponse)
+        if !deleting {
+            let questions = submission
+                .questions
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+
+            if let Err(e) = transport
+                .respond(
+                    InteractionRef::from(interaction),
+                    CreateInteractionResponse::Modal(standup_modal(
+                        ComponentId::StandupEdit { submission_id },
+                        locale,
+                        &questions,
+                        Some(&submission.answers),
+                    )),
+                )
+                .await
+            {
+                error!("Error opening standup edit modal: {}", e);
+            }
+
+            return Ok(CommandResponse::NoResponse);
+        }
+
+        if let Err(e) = transport
+            .delete_message(interaction.channel_id.into(), interaction.message.id.into())
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "failed to delete standup message: {}",
+                e
+            )));
+        }
+
+        let session = app_state
+            .storage
+            .update(|data| {
+                data.submissions.remove(&submission_id);
+                submission
+                    .session_id
+                    .and_then(|id| data.sessions.get(&id).cloned())
+            })
+            .await
+            .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+        if let Err(e) = webhook::enqueue(
+            &app_state.storage,
+            WebhookEvent::SubmissionDeleted,
+            session.as_ref(),
+            &submission,
+        )
+        .await
+        {
+            error!("failed to queue standup webhook: {}", e);
+        }
+
+        let members = MemberCache::default();
+        refresh_board(transport, app_state, &members, submission.guild_id).await;
+        if let Some(session_id) = submission.session_id {
+            refresh_panel(transport, app_state, &members, session_id).await;
+        }
+
+        Ok(CommandResponse::BasicSuccess(
+            Text::StandupDeleted.get(locale).to_string(),
+        ))
     }
 }
 
 #[async_trait]
 impl<'a> ModalSubmit<'a> for StandupCommand {
-    async fn modal_submit<'b>(
-        modal: &'b ModalInteraction,
-        _: &'b AppState,
-        _: &'b Context,
-    ) -> bool {
-        modal.data.custom_id == "standups"
-    }
-
     async fn handle_modal_submit<'b>(
+        id: ComponentId,
         modal: &'b ModalInteraction,
-        _: &'b AppState,
-        context: &'b Context,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
     ) -> Result<CommandResponse, CommandResponse> {
-        // send a simple message in the channel of the interaction WITH the data provided by the user
-        let username = &modal.user.name;
+        let locale = locale::resolve(
+            &app_state.storage,
+            modal.guild_id.map(u64::from),
+            modal.user.id.into(),
+            &modal.locale,
+        )
+        .await;
+        let user_id: u64 = modal.user.id.into();
+        // answers are written by the member, so they only ping the users they mention if the guild allows it
+        let mentions = allowed_mentions(
+            user_mentions_allowed(&app_state.storage, modal.guild_id.map(u64::from)).await,
+        );
 
-        let mut result_strings = vec![];
+        // an edit is answering the questions of the original submission, which may have been asked in another locale
+        let (existing, session_id) = match id {
+            ComponentId::StandupEdit { submission_id } => {
+                let submission = app_state
+                    .storage
+                    .read()
+                    .await
+                    .submissions
+                    .get(&submission_id)
+                    .cloned();
+                if submission.is_none() {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::StandupMissing.get(locale).to_string(),
+                    ));
+                }
+                (submission, None)
+            }
+            ComponentId::StandupSubmit { session_id } => {
+                // the modal may have been opened before the session was closed
+                let closed = match session_id {
+                    Some(session_id) => app_state
+                        .storage
+                        .read()
+                        .await
+                        .sessions
+                        .get(&session_id)
+                        .map_or(false, |s| !s.is_open()),
+                    None => false,
+                };
+                if closed {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::StandupAlreadyClosed.get(locale).to_string(),
+                    ));
+                }
+                (None, session_id)
+            }
+            id => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "unexpected standup modal {:?}",
+                    id
+                )))
+            }
+        };
+        let questions: Vec<String> = match &existing {
+            Some(s) => s.questions.clone(),
+            None => standup_questions(locale)
+                .iter()
+                .map(|q| q.to_string())
+                .collect(),
+        };
 
-        for (i, question) in QUESTION_LIST.iter().enumerate() {
-            let answer = {
-                match &modal.data.components[i].components[0] {
-                    ActionRowComponent::InputText(input) => input,
-                    _ => panic!("Unexpected component type"),
+        let mut answers = vec![];
+
+        for i in 0..questions.len() {
+            let answer = match modal
+                .data
+                .components
+                .get(i)
+                .and_then(|row| row.components.first())
+            {
+                Some(ActionRowComponent::InputText(input)) => input,
+                component => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "unexpected component in standup modal for question {}: {:?}",
+                        i, component
+                    )))
                 }
             };
 
-            result_strings.push(format!("**{}**\r{}", question, answer.value));
+            answers.push(answer.value.clone());
         }
 
-        // if the total length of all result strings is >=1900 chars, throw an error
-        let char_count = result_strings.iter().map(|s| s.len()).sum::<usize>();
+        // if the total length of all questions and answers is >=1900 chars, throw an error
+        let char_count = questions
+            .iter()
+            .chain(answers.iter())
+            .map(|s| s.len())
+            .sum::<usize>();
 
         if char_count >= 1900 {
-            if let Err(e) = modal.create_response(&context,
-                CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new().content("Your standup is too long, please shorten it")
-                    .ephemeral(true)
-                )
+            return Err(CommandResponse::BasicFailure(
+                Text::StandupTooLong.get(locale).to_string(),
+            ));
+        }
+
+        let (submission, event) = match existing {
+            Some(mut submission) => {
+                if submission.user_id != user_id {
+                    return Err(CommandResponse::BasicFailure(
+                        Text::StandupNotYours.get(locale).to_string(),
+                    ));
+                }
 
-            ).await {
-                error!("Error sending standup response: {}", e);
-                return Ok(CommandResponse::NoResponse)
+                let patterns = link_patterns(app_state, submission.guild_id).await;
+                submission.references = collect_references(&patterns, &answers);
+                submission.answers = answers;
+                submission.edited_at = Some(Utc::now());
+
+                if let Err(e) = transport
+                    .respond(
+                        InteractionRef::from(modal),
+                        CreateInteractionResponse::UpdateMessage(
+                            CreateInteractionResponseMessage::new()
+                                .content(render_submission(app_state, locale, &submission).await)
+                                .allowed_mentions(mentions),
+                        ),
+                    )
+                    .await
+                {
+                    error!("Error sending standup response: {}", e);
+                    return Ok(CommandResponse::NoResponse);
+                }
+
+                (submission, WebhookEvent::SubmissionEdited)
             }
-        }
+            None => {
+                let guild_id: u64 = modal
+                    .guild_id
+                    .ok_or_else(|| {
+                        CommandResponse::InternalFailure(String::from(
+                            "standup submitted outside of a guild",
+                        ))
+                    })?
+                    .into();
 
-        if let Err(e) = modal
-            .create_response(
-                &context,
-                CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new().content(format!(
-                        "**Standup Submission by {}:**\r{}",
-                        username,
-                        result_strings.join("\r")
-                    )),
-                ),
-            )
+                let patterns = link_patterns(app_state, guild_id).await;
+                let mut submission = Submission {
+                    id: modal.id.into(),
+                    guild_id,
+                    channel_id: modal.channel_id.into(),
+                    message_id: None,
+                    session_id,
+                    user_id,
+                    username: modal.user.name.clone(),
+                    questions,
+                    references: collect_references(&patterns, &answers),
+                    answers,
+                    submitted_at: Utc::now(),
+                    edited_at: None,
+                };
+
+                // send a simple message in the channel of the interaction WITH the data provided by the user
+                if let Err(e) = transport
+                    .respond(
+                        InteractionRef::from(modal),
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(render_submission(app_state, locale, &submission).await)
+                                .allowed_mentions(mentions)
+                                .components(submission_buttons(locale, submission.id)),
+                        ),
+                    )
+                    .await
+                {
+                    error!("Error sending standup response: {}", e);
+                    return Ok(CommandResponse::NoResponse);
+                }
+
+                match transport
+                    .response_message(InteractionRef::from(modal))
+                    .await
+                {
+                    Ok(message_id) => submission.message_id = Some(message_id),
+                    Err(e) => error!("Unable to fetch posted standup: {}", e),
+                }
+
+                (submission, WebhookEvent::SubmissionCreated)
+            }
+        };
+
+        let session = app_state
+            .storage
+            .update(|data| {
+                data.submissions.insert(submission.id, submission.clone());
+                submission
+                    .session_id
+                    .and_then(|id| data.sessions.get(&id).cloned())
+            })
             .await
+            .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+        if let Err(e) =
+            webhook::enqueue(&app_state.storage, event, session.as_ref(), &submission).await
         {
-            error!("Error sending standup response: {}", e);
+            error!("failed to queue standup webhook: {}", e);
+        }
+
+        let members = MemberCache::default();
+        refresh_board(transport, app_state, &members, submission.guild_id).await;
+        if let Some(session_id) = submission.session_id {
+            refresh_panel(transport, app_state, &members, session_id).await;
         }
 
         Ok(CommandResponse::NoResponse)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::Value;
+
+    use crate::{
+        discord_bot::{
+            commands::{
+                command::{deferrable_component, handle_modal},
+                fixtures,
+                util::CommandResponse,
+            },
+            custom_id::ComponentId,
+            transport::{RecordingTransport, Sent},
+        },
+        locale::{standup_questions, Locale, Text},
+        standups::StandupSession,
+    };
+
+    use super::standup_modal;
+
+    /// the session the fixture submissions are made to, its panel is message 500
+    const SESSION_ID: u64 = 1;
+
+    /// the content of a response to an interaction
+    fn content(response: &Value) -> &str {
+        response["data"]["content"].as_str().unwrap_or_default()
+    }
+
+    /// check if a response to an interaction is only shown to the user
+    fn ephemeral(response: &Value) -> bool {
+        response["data"]["flags"]
+            .as_u64()
+            .map_or(false, |f| f & (1 << 6) != 0)
+    }
+
+    /// check if a handler refused with the provided text, leaving the dispatcher to reply
+    fn refused(res: Result<CommandResponse, CommandResponse>, text: Text) -> bool {
+        matches!(res, Err(CommandResponse::BasicFailure(m)) if m == text.get(Locale::English))
+    }
+
+    /// an answer for each question, long enough to fill a standup when `length` is large
+    fn answers(length: usize) -> Vec<String> {
+        (0..standup_questions(Locale::English).len())
+            .map(|i| format!("answer {} {}", i, "x".repeat(length)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn modal_round_trip_posts_and_stores_submission() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let session = StandupSession {
+            id: SESSION_ID,
+            guild_id: fixtures::GUILD_ID,
+            channel_id: fixtures::CHANNEL_ID,
+            started_by: fixtures::USER_ID,
+            started_at: Utc::now(),
+            message_id: Some(500),
+            holiday: None,
+            closed_at: None,
+            digest_message_id: None,
+        };
+        app_state
+            .storage
+            .update(|data| data.sessions.insert(session.id, session))
+            .await
+            .unwrap();
+
+        let created = serde_json::to_value(standup_modal(
+            ComponentId::StandupSubmit {
+                session_id: Some(SESSION_ID),
+            },
+            Locale::English,
+            standup_questions(Locale::English),
+            None,
+        ))
+        .unwrap();
+        let answers = answers(0);
+        let modal = fixtures::submit_modal(2, &created, &answers);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(matches!(res, Ok(CommandResponse::NoResponse)));
+
+        let responses = transport.responses();
+        assert_eq!(responses.len(), 1);
+        assert!(!ephemeral(&responses[0]));
+        for answer in &answers {
+            assert!(content(&responses[0]).contains(answer.as_str()));
+        }
+
+        {
+            let data = app_state.storage.read().await;
+            let submission = data.submissions.get(&2).expect("submission is stored");
+            assert_eq!(submission.answers, answers);
+            assert_eq!(submission.session_id, Some(SESSION_ID));
+            assert_eq!(submission.user_id, fixtures::USER_ID);
+            assert!(submission.message_id.is_some());
+        }
+
+        // the panel of the session is updated to show the new submission
+        assert!(transport.sent().iter().any(|s| matches!(
+            s,
+            Sent::Edit {
+                message_id: 500,
+                ..
+            }
+        )));
+    }
+
+    #[tokio::test]
+    async fn submitting_to_a_closed_session_is_refused() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let session = StandupSession {
+            id: SESSION_ID,
+            guild_id: fixtures::GUILD_ID,
+            channel_id: fixtures::CHANNEL_ID,
+            started_by: fixtures::USER_ID,
+            started_at: Utc::now(),
+            message_id: Some(500),
+            holiday: None,
+            closed_at: Some(Utc::now()),
+            digest_message_id: None,
+        };
+        app_state
+            .storage
+            .update(|data| data.sessions.insert(session.id, session))
+            .await
+            .unwrap();
+        let custom_id = ComponentId::StandupSubmit {
+            session_id: Some(SESSION_ID),
+        }
+        .encode();
+        let answers = answers(0);
+        let answers = answers.iter().map(String::as_str).collect::<Vec<_>>();
+        let modal = fixtures::modal(2, &custom_id, &answers);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(refused(res, Text::StandupAlreadyClosed));
+        assert!(transport.sent().is_empty());
+        assert!(app_state.storage.read().await.submissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn modal_missing_answers_is_an_internal_failure() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let custom_id = ComponentId::StandupSubmit { session_id: None }.encode();
+        let modal = fixtures::modal(2, &custom_id, &["only one answer"]);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(matches!(res, Err(CommandResponse::InternalFailure(_))));
+        assert!(transport.sent().is_empty());
+        assert!(app_state.storage.read().await.submissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn overly_long_standup_is_refused() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let custom_id = ComponentId::StandupSubmit { session_id: None }.encode();
+        let answers = answers(500);
+        let answers = answers.iter().map(String::as_str).collect::<Vec<_>>();
+        let modal = fixtures::modal(2, &custom_id, &answers);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(refused(res, Text::StandupTooLong));
+        assert!(transport.sent().is_empty());
+        assert!(app_state.storage.read().await.submissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn editing_a_missing_submission_is_refused() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let custom_id = ComponentId::StandupEdit { submission_id: 99 }.encode();
+        let modal = fixtures::modal(2, &custom_id, &["edited"]);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(refused(res, Text::StandupMissing));
+        assert!(transport.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_modal_is_answered_with_an_error() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let modal = fixtures::modal(2, "standup-modal", &["answer"]);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        let response = match res {
+            Err(response @ CommandResponse::ComplexFailure { .. }) => response,
+            other => panic!("expected the modal to have expired, got {:?}", other),
+        };
+        assert!(transport.sent().is_empty());
+
+        let response =
+            serde_json::to_value(response.generate_response(Locale::English, None)).unwrap();
+        assert!(ephemeral(&response));
+        assert_eq!(
+            content(&response),
+            Text::ComponentExpired.get(Locale::English)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_response_stores_nothing() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        transport.fail();
+        let custom_id = ComponentId::StandupSubmit { session_id: None }.encode();
+        let answers = answers(0);
+        let answers = answers.iter().map(String::as_str).collect::<Vec<_>>();
+        let modal = fixtures::modal(2, &custom_id, &answers);
+
+        let res = handle_modal(&modal, &app_state, &transport).await;
+        assert!(matches!(res, Ok(CommandResponse::NoResponse)));
+        assert!(app_state.storage.read().await.submissions.is_empty());
+    }
+
+    #[test]
+    fn only_closing_a_session_or_deleting_a_submission_may_be_deferred() {
+        let close = ComponentId::StandupClose {
+            session_id: SESSION_ID,
+        };
+        let delete = ComponentId::StandupDelete { submission_id: 2 };
+        let start = ComponentId::StandupStart {
+            session_id: SESSION_ID,
+        };
+        assert!(deferrable_component(&close.encode()));
+        assert!(deferrable_component(&delete.encode()));
+        assert!(!deferrable_component(&start.encode()));
+        assert!(!deferrable_component("not a custom id"));
+    }
+}
@@ -0,0 +1,436 @@
+use serenity::{
+    all::{ChannelType, CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage},
+    model::Permissions,
+};
+
+use crate::{
+    access::Capability,
+    discord_bot::{
+        board::{refresh_board, remove_board, MemberCache},
+        transport::Transport,
+    },
+    locale::{self, Text},
+    modules::Module,
+    references::{self, LinkPattern},
+    standups::BoardConfig,
+    state::AppState,
+    webhook::{self, WebhookConfig},
+};
+
+use super::{
+    command::Command,
+    subcommand::{route, Invocation, SubCommand},
+    util::{CommandResponse, FailureMessageKind},
+};
+
+/// a change to the configuration of standups in a guild
+enum ConfigAction {
+    /// set the webhook url, and optionally the signing secret
+    WebhookSet { url: String, secret: Option<String> },
+    /// remove the webhook
+    WebhookClear,
+    /// send a test payload to the webhook
+    WebhookTest,
+    /// add a pattern which links issue tracker references
+    LinksAdd { pattern: String, url: String },
+    /// remove a link pattern
+    LinksRemove { pattern: String },
+    /// list the link patterns of the guild
+    LinksList,
+    /// post the board in a channel, optionally only showing the members of a role
+    BoardSet {
+        channel_id: u64,
+        roster_role: Option<u64>,
+    },
+    /// stop keeping the board up to date
+    BoardClear,
+}
+
+pub struct StandupConfigCommand {
+    action: ConfigAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for StandupConfigCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for StandupConfigCommand {
+    fn name() -> &'static str {
+        "standup-config"
+    }
+
+    fn description() -> &'static str {
+        "Configure how standups work in this server"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn default_member_permissions() -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn capability() -> Option<Capability> {
+        Some(Capability::StandupConfigure)
+    }
+
+    /// the webhook test waits on the receiver, which can take longer than discord waits for a response
+    fn deferrable() -> bool {
+        true
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        vec![
+            SubCommand::group(
+                "webhook",
+                "Configure the webhook notified of every standup submission",
+            )
+            .subcommand(
+                SubCommand::new("set", "Set the url submissions are sent to", |invocation| {
+                    Ok(Self {
+                        action: ConfigAction::WebhookSet {
+                            url: invocation.str("url").ok_or("No url provided")?,
+                            secret: invocation.str("secret"),
+                        },
+                    })
+                })
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "url",
+                            "The url to POST each submission to",
+                        )
+                        .required(true),
+                    )
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "secret",
+                            "The secret used to sign payloads, one is generated if not provided",
+                        )
+                        .required(false)
+                        .min_length(16),
+                    ),
+            )
+            .subcommand(SubCommand::new(
+                "clear",
+                "Stop sending submissions to the webhook",
+                |_| {
+                    Ok(Self {
+                        action: ConfigAction::WebhookClear,
+                    })
+                },
+            ))
+            .subcommand(SubCommand::new(
+                "test",
+                "Send a test payload to the webhook",
+                |_| {
+                    Ok(Self {
+                        action: ConfigAction::WebhookTest,
+                    })
+                },
+            )),
+            SubCommand::group(
+                "links",
+                "Configure how issue tracker references in answers are linked",
+            )
+            .subcommand(
+                SubCommand::new("add", "Link every match of a pattern to a url", |invocation| {
+                    Ok(Self {
+                        action: ConfigAction::LinksAdd {
+                            pattern: invocation.str("pattern").ok_or("No pattern provided")?,
+                            url: invocation.str("url").ok_or("No url provided")?,
+                        },
+                    })
+                })
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "pattern",
+                            "A regex matching a reference, e.g. #(\\d+) or PAY-\\d+",
+                        )
+                        .required(true)
+                        .max_length(200),
+                    )
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "url",
+                            "The url to link to, $0 is the whole match and $1 the first group",
+                        )
+                        .required(true)
+                        .max_length(300),
+                    ),
+            )
+            .subcommand(
+                SubCommand::new("remove", "Stop linking a pattern", |invocation| {
+                    Ok(Self {
+                        action: ConfigAction::LinksRemove {
+                            pattern: invocation.str("pattern").ok_or("No pattern provided")?,
+                        },
+                    })
+                })
+                .option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "pattern",
+                        "The pattern to remove",
+                    )
+                    .required(true),
+                ),
+            )
+            .subcommand(SubCommand::new(
+                "list",
+                "List the patterns which are linked",
+                |_| {
+                    Ok(Self {
+                        action: ConfigAction::LinksList,
+                    })
+                },
+            )),
+            SubCommand::group(
+                "board",
+                "Configure the board showing what each member is working on",
+            )
+            .subcommand(
+                SubCommand::new(
+                    "set",
+                    "Post the board in a channel and keep it up to date",
+                    |invocation| {
+                        Ok(Self {
+                            action: ConfigAction::BoardSet {
+                                channel_id: invocation
+                                    .channel("channel")
+                                    .ok_or("No channel provided")?,
+                                roster_role: invocation.role("role"),
+                            },
+                        })
+                    },
+                )
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "channel",
+                            "The channel to post the board in",
+                        )
+                        .required(true)
+                        .channel_types(vec![ChannelType::Text]),
+                    )
+                    .option(
+                        CreateCommandOption::new(
+                            CommandOptionType::Role,
+                            "role",
+                            "Only show the members of this role, by default everyone who has submitted is shown",
+                        )
+                        .required(false),
+                    ),
+            )
+            .subcommand(SubCommand::new(
+                "clear",
+                "Stop keeping the board up to date",
+                |_| {
+                    Ok(Self {
+                        action: ConfigAction::BoardClear,
+                    })
+                },
+            )),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        transport: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "standup-config command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        let response = match self.action {
+            ConfigAction::WebhookSet { url, secret } => {
+                if !webhook::valid_url(&url) {
+                    return Err(CommandResponse::ComplexFailure {
+                        response: Text::WebhookInvalidUrl.get(locale).to_string(),
+                        kind: FailureMessageKind::Debug,
+                        log_message: format!("invalid webhook url provided: {}", url),
+                    });
+                }
+
+                let config = WebhookConfig {
+                    url,
+                    secret: secret.unwrap_or_else(webhook::generate_secret),
+                };
+                let response = Text::WebhookSet
+                    .format(locale, &[("url", &config.url), ("secret", &config.secret)]);
+
+                app_state
+                    .storage
+                    .update(|data| data.guilds.entry(guild_id).or_default().webhook = Some(config))
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                response
+            }
+            ConfigAction::WebhookClear => {
+                app_state
+                    .storage
+                    .update(|data| data.guilds.entry(guild_id).or_default().webhook = None)
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::WebhookCleared.get(locale).to_string()
+            }
+            ConfigAction::WebhookTest => {
+                let config = app_state
+                    .storage
+                    .read()
+                    .await
+                    .guilds
+                    .get(&guild_id)
+                    .and_then(|g| g.webhook.clone());
+
+                match config {
+                    None => Text::WebhookNotConfigured.get(locale).to_string(),
+                    Some(config) => match webhook::send_test(&config, guild_id).await {
+                        Ok(status) => Text::WebhookTestSucceeded
+                            .format(locale, &[("status", &status.to_string())]),
+                        Err(e) => Text::WebhookTestFailed.format(locale, &[("error", &e)]),
+                    },
+                }
+            }
+            ConfigAction::LinksAdd { pattern, url } => {
+                if let Err(e) = references::compile(&pattern) {
+                    return Err(CommandResponse::ComplexFailure {
+                        response: Text::LinkInvalidPattern.format(locale, &[("error", &e)]),
+                        kind: FailureMessageKind::Debug,
+                        log_message: format!("invalid link pattern provided: {}", pattern),
+                    });
+                }
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(CommandResponse::ComplexFailure {
+                        response: Text::LinkInvalidUrl.get(locale).to_string(),
+                        kind: FailureMessageKind::Debug,
+                        log_message: format!("invalid link url provided: {}", url),
+                    });
+                }
+
+                let response = Text::LinkAdded.format(locale, &[("pattern", &pattern)]);
+                app_state
+                    .storage
+                    .update(|data| {
+                        let patterns = &mut data.guilds.entry(guild_id).or_default().link_patterns;
+                        patterns.retain(|p| p.pattern != pattern);
+                        patterns.push(LinkPattern { pattern, url });
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                response
+            }
+            ConfigAction::LinksRemove { pattern } => {
+                app_state
+                    .storage
+                    .update(|data| {
+                        data.guilds
+                            .entry(guild_id)
+                            .or_default()
+                            .link_patterns
+                            .retain(|p| p.pattern != pattern)
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                Text::LinkRemoved.format(locale, &[("pattern", &pattern)])
+            }
+            ConfigAction::LinksList => {
+                let patterns = app_state
+                    .storage
+                    .read()
+                    .await
+                    .guilds
+                    .get(&guild_id)
+                    .map(|g| g.link_patterns.clone())
+                    .unwrap_or_default();
+
+                match patterns.is_empty() {
+                    true => Text::LinksNone.get(locale).to_string(),
+                    false => patterns
+                        .iter()
+                        .map(|p| format!("- `{}` → <{}>", p.pattern, p.url))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }
+            ConfigAction::BoardSet {
+                channel_id,
+                roster_role,
+            } => {
+                let previous = app_state
+                    .storage
+                    .update(|data| {
+                        let board = &mut data.guilds.entry(guild_id).or_default().board;
+                        // keep the existing message if the board stays in the same channel
+                        let message_id = board
+                            .as_ref()
+                            .filter(|b| b.channel_id == channel_id)
+                            .and_then(|b| b.message_id);
+                        board.replace(BoardConfig {
+                            channel_id,
+                            message_id,
+                            roster_role,
+                        })
+                    })
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                if let Some(previous) = previous.filter(|b| b.channel_id != channel_id) {
+                    remove_board(transport, guild_id, &previous).await;
+                }
+                refresh_board(transport, app_state, &MemberCache::default(), guild_id).await;
+
+                Text::BoardSet.format(locale, &[("channel", &format!("<#{}>", channel_id))])
+            }
+            ConfigAction::BoardClear => {
+                let previous = app_state
+                    .storage
+                    .update(|data| data.guilds.entry(guild_id).or_default().board.take())
+                    .await
+                    .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+                if let Some(previous) = previous {
+                    remove_board(transport, guild_id, &previous).await;
+                }
+
+                Text::BoardCleared.get(locale).to_string()
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
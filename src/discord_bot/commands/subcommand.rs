@@ -0,0 +1,298 @@
+//! Subcommands and subcommand groups, declared by a command and resolved from the options of an interaction
+
+use serenity::{
+    all::{
+        AttachmentId, CommandDataOption, CommandDataOptionValue, CommandInteraction,
+        CommandOptionType,
+    },
+    builder::CreateCommandOption,
+};
+
+use super::options::{CommandOptions, OptionError};
+
+/// parse the options provided to an invoked subcommand, into the command which handles it
+pub type Parse<C, E = String> = fn(&Invocation) -> Result<C, E>;
+
+/// a subcommand, or a group of subcommands, which a command declares.
+/// the subcommands of a command are its routing table, each one parsing the options it declares
+pub enum SubCommand<C, E = String> {
+    /// a subcommand which can be invoked, taking the provided options
+    Command {
+        name: &'static str,
+        description: &'static str,
+        options: Vec<CreateCommandOption>,
+        parse: Parse<C, E>,
+    },
+    /// a group of subcommands, which can't be invoked itself
+    Group {
+        name: &'static str,
+        description: &'static str,
+        subcommands: Vec<SubCommand<C, E>>,
+    },
+}
+
+impl<C, E> SubCommand<C, E> {
+    /// declare a subcommand, parsed by the provided function when it is invoked
+    pub fn new(name: &'static str, description: &'static str, parse: Parse<C, E>) -> Self {
+        Self::Command {
+            name,
+            description,
+            options: vec![],
+            parse,
+        }
+    }
+
+    /// declare a subcommand taking the options of a `#[derive(CommandOptions)]` struct, which is turned into the command
+    pub fn with_options<T>(name: &'static str, description: &'static str) -> Self
+    where
+        T: CommandOptions + Into<C>,
+        E: From<OptionError>,
+    {
+        Self::Command {
+            name,
+            description,
+            options: T::options(),
+            parse: parse_options::<T, C, E>,
+        }
+    }
+
+    /// declare an empty group of subcommands
+    pub fn group(name: &'static str, description: &'static str) -> Self {
+        Self::Group {
+            name,
+            description,
+            subcommands: vec![],
+        }
+    }
+
+    /// add an option to a subcommand, groups can't take options
+    pub fn option(mut self, option: CreateCommandOption) -> Self {
+        match &mut self {
+            Self::Command { options, .. } => options.push(option),
+            Self::Group { name, .. } => panic!("subcommand group {} can't take options", name),
+        }
+        self
+    }
+
+    /// add a subcommand to a group, discord only allows subcommands to be nested one level deep
+    pub fn subcommand(mut self, subcommand: SubCommand<C, E>) -> Self {
+        match (&mut self, &subcommand) {
+            (Self::Group { subcommands, .. }, Self::Command { .. }) => subcommands.push(subcommand),
+            _ => panic!("subcommands may only be added to a group"),
+        }
+        self
+    }
+
+    /// the name the subcommand or group is invoked by
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Command { name, .. } | Self::Group { name, .. } => name,
+        }
+    }
+
+    /// build the option sent to discord when registering the command
+    pub fn build(self) -> CreateCommandOption {
+        match self {
+            Self::Command {
+                name,
+                description,
+                options,
+                ..
+            } => options.into_iter().fold(
+                CreateCommandOption::new(CommandOptionType::SubCommand, name, description),
+                |subcommand, option| subcommand.add_sub_option(option),
+            ),
+            Self::Group {
+                name,
+                description,
+                subcommands,
+            } => subcommands.into_iter().fold(
+                CreateCommandOption::new(CommandOptionType::SubCommandGroup, name, description),
+                |group, subcommand| group.add_sub_option(subcommand.build()),
+            ),
+        }
+    }
+}
+
+/// parse the options of a subcommand declared with [SubCommand::with_options]
+fn parse_options<T, C, E>(invocation: &Invocation) -> Result<C, E>
+where
+    T: CommandOptions + Into<C>,
+    E: From<OptionError>,
+{
+    T::parse(invocation).map(Into::into).map_err(E::from)
+}
+
+/// follow the path of an invocation through the subcommands of a command, and parse the options of the one it leads to
+pub fn route<C, E: From<String>>(
+    subcommands: &[SubCommand<C, E>],
+    invocation: &Invocation,
+) -> Result<C, E> {
+    let path = invocation.path();
+    let mut level = subcommands;
+    for (depth, invoked) in path.iter().enumerate() {
+        let last = depth + 1 == path.len();
+        match level.iter().find(|s| s.name() == *invoked) {
+            Some(SubCommand::Command { parse, .. }) if last => return parse(invocation),
+            Some(SubCommand::Group { subcommands, .. }) if !last => level = subcommands,
+            _ => break,
+        }
+    }
+    Err(E::from(format!("Unknown subcommand {}", path.join(" "))))
+}
+
+/// the subcommand an interaction invoked, and the options provided to it
+pub struct Invocation<'a> {
+    /// the names of the group and subcommand invoked, e.g. `["webhook", "set"]`, empty if there are none
+    path: Vec<&'a str>,
+    /// the options provided to the invoked subcommand
+    options: &'a [CommandDataOption],
+}
+
+impl<'a> Invocation<'a> {
+    /// follow the subcommands of an interaction down to the options of the one invoked
+    pub fn new(interaction: &'a CommandInteraction) -> Self {
+        let mut path = vec![];
+        let mut options = interaction.data.options.as_slice();
+
+        while let Some(option) = options.first() {
+            match &option.value {
+                CommandDataOptionValue::SubCommandGroup(inner)
+                | CommandDataOptionValue::SubCommand(inner) => {
+                    path.push(option.name.as_str());
+                    options = inner.as_slice();
+                }
+                _ => break,
+            }
+        }
+
+        Self { path, options }
+    }
+
+    /// the names of the group and subcommand invoked
+    pub fn path(&self) -> &[&'a str] {
+        &self.path
+    }
+
+    /// get the value of an option, if it was provided
+    pub fn value(&self, name: &str) -> Option<&'a CommandDataOptionValue> {
+        self.options
+            .iter()
+            .find(|o| o.name == name)
+            .map(|o| &o.value)
+    }
+
+    /// get the value of a string option
+    pub fn str(&self, name: &str) -> Option<String> {
+        self.value(name).and_then(|v| v.as_str()).map(String::from)
+    }
+
+    /// get the value of a boolean option
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        self.value(name).and_then(|v| v.as_bool())
+    }
+
+    /// get the value of an integer option
+    pub fn i64(&self, name: &str) -> Option<i64> {
+        self.value(name).and_then(|v| v.as_i64())
+    }
+
+    /// get the id of a channel option
+    pub fn channel(&self, name: &str) -> Option<u64> {
+        match self.value(name)? {
+            CommandDataOptionValue::Channel(id) => Some((*id).into()),
+            _ => None,
+        }
+    }
+
+    /// get the id of a user option
+    pub fn user(&self, name: &str) -> Option<u64> {
+        match self.value(name)? {
+            CommandDataOptionValue::User(id) => Some((*id).into()),
+            _ => None,
+        }
+    }
+
+    /// get the id of a role option
+    pub fn role(&self, name: &str) -> Option<u64> {
+        match self.value(name)? {
+            CommandDataOptionValue::Role(id) => Some((*id).into()),
+            _ => None,
+        }
+    }
+
+    /// get the id of an attachment option
+    pub fn attachment(&self, name: &str) -> Option<AttachmentId> {
+        self.value(name).and_then(|v| v.as_attachment_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::discord_bot::commands::fixtures;
+
+    /// a routing table with a plain subcommand and one inside a group, parsing to the path invoked
+    fn table() -> Vec<SubCommand<String>> {
+        vec![
+            SubCommand::new("list", "List", |_| Ok(String::from("list"))),
+            SubCommand::group("webhook", "Webhook").subcommand(SubCommand::new(
+                "set",
+                "Set",
+                |invocation| {
+                    invocation
+                        .str("url")
+                        .ok_or_else(|| String::from("No url provided"))
+                },
+            )),
+        ]
+    }
+
+    #[test]
+    fn routes_to_the_subcommand_invoked() {
+        let interaction = fixtures::command(
+            1,
+            "test",
+            json!([{ "name": "list", "type": 1, "options": [] }]),
+        );
+        assert_eq!(
+            route(&table(), &Invocation::new(&interaction)),
+            Ok(String::from("list"))
+        );
+
+        let interaction = fixtures::command(
+            2,
+            "test",
+            json!([{ "name": "webhook", "type": 2, "options": [
+                { "name": "set", "type": 1, "options": [
+                    { "name": "url", "type": 3, "value": "https://example.com" },
+                ] },
+            ] }]),
+        );
+        assert_eq!(
+            route(&table(), &Invocation::new(&interaction)),
+            Ok(String::from("https://example.com"))
+        );
+    }
+
+    #[test]
+    fn refuses_unknown_subcommands_and_groups() {
+        let interaction = fixtures::command(
+            1,
+            "test",
+            json!([{ "name": "webhook", "type": 2, "options": [
+            { "name": "clear", "type": 1, "options": [] },
+        ] }]),
+        );
+        assert!(route(&table(), &Invocation::new(&interaction)).is_err());
+
+        let interaction = fixtures::command(
+            2,
+            "test",
+            json!([{ "name": "webhook", "type": 1, "options": [] }]),
+        );
+        assert!(route(&table(), &Invocation::new(&interaction)).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, CommandOptionType},
+    async_trait,
+    builder::{
+        CreateAutocompleteResponse, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+};
+
+use crate::{
+    discord_bot::transport::Transport,
+    locale::{self, Text},
+    state::AppState,
+    timezone::{default_timezone, parse_timezone, search_timezones},
+};
+
+use super::{
+    command::{AutocompleteCommand, Command},
+    subcommand::{route, Invocation, SubCommand},
+    util::{CommandResponse, FailureMessageKind, MAX_AUTOCOMPLETE_CHOICES},
+};
+
+/// the action to take on a user's timezone
+enum TimezoneAction {
+    /// set the timezone to the provided IANA zone name
+    Set(String),
+    /// remove the timezone, falling back to the default
+    Clear,
+}
+
+pub struct TimezoneCommand {
+    action: TimezoneAction,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for TimezoneCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        route(&Self::subcommands(), &Invocation::new(interaction))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for TimezoneCommand {
+    fn name() -> &'static str {
+        "timezone"
+    }
+
+    fn description() -> &'static str {
+        "Set the timezone that your standups are scheduled in"
+    }
+
+    fn subcommands() -> Vec<SubCommand<Self>> {
+        vec![
+            SubCommand::new("set", "Set your timezone", |invocation| {
+                let zone = invocation.str("zone").ok_or("No timezone provided")?;
+                Ok(Self {
+                    action: TimezoneAction::Set(zone),
+                })
+            })
+            .option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "zone",
+                    "An IANA timezone name, such as Pacific/Auckland",
+                )
+                .required(true)
+                .set_autocomplete(true),
+            ),
+            SubCommand::new(
+                "clear",
+                "Stop using a personal timezone, and use the default instead",
+                |_| {
+                    Ok(Self {
+                        action: TimezoneAction::Clear,
+                    })
+                },
+            ),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user_id: u64 = interaction.user.id.into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            interaction.guild_id.map(u64::from),
+            user_id,
+            &interaction.locale,
+        )
+        .await;
+
+        let timezone: Option<Tz> = match self.action {
+            TimezoneAction::Set(zone) => match parse_timezone(&zone) {
+                Some(tz) => Some(tz),
+                None => {
+                    return Err(CommandResponse::ComplexFailure {
+                        response: Text::TimezoneInvalid.format(locale, &[("timezone", &zone)]),
+                        kind: FailureMessageKind::Debug,
+                        log_message: format!("user {} provided unknown timezone {}", user_id, zone),
+                    });
+                }
+            },
+            TimezoneAction::Clear => None,
+        };
+
+        app_state
+            .storage
+            .update(|data| {
+                data.users.entry(user_id).or_default().timezone = timezone;
+            })
+            .await
+            .map_err(|e| CommandResponse::InternalFailure(e.to_string()))?;
+
+        let response = match timezone {
+            Some(tz) => Text::TimezoneSet.format(
+                locale,
+                &[
+                    ("timezone", tz.name()),
+                    (
+                        "time",
+                        &Utc::now().with_timezone(&tz).format("%H:%M").to_string(),
+                    ),
+                ],
+            ),
+            None => {
+                Text::TimezoneCleared.format(locale, &[("timezone", default_timezone().name())])
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for TimezoneCommand {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c dyn Transport,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        Ok(search_timezones(option.value, MAX_AUTOCOMPLETE_CHOICES)
+            .into_iter()
+            .fold(CreateAutocompleteResponse::new(), |response, tz| {
+                response.add_string_choice(tz.name(), tz.name())
+            }))
+    }
+}
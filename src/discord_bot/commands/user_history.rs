@@ -0,0 +1,99 @@
+use serenity::{
+    all::{CommandInteraction, CommandType, ResolvedTarget},
+    async_trait,
+    builder::{CreateAllowedMentions, CreateInteractionResponse, CreateInteractionResponseMessage},
+};
+
+use crate::{
+    access::Capability, discord_bot::transport::Transport, locale, modules::Module,
+    standups::THIS_WEEK_QUESTION, state::AppState,
+};
+
+use super::{
+    command::Command,
+    history::{history_response, DEFAULT_COUNT},
+    util::CommandResponse,
+};
+
+/// the history of a member, opened from the context menu of a user
+pub struct UserHistoryCommand {
+    /// the user whose history is shown
+    user_id: u64,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for UserHistoryCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        match interaction.data.target() {
+            Some(ResolvedTarget::User(user, _)) => Ok(Self {
+                user_id: user.id.into(),
+            }),
+            _ => Err(String::from("No user provided")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for UserHistoryCommand {
+    fn name() -> &'static str {
+        "View standup history"
+    }
+
+    fn description() -> &'static str {
+        "Look up the previous standups of a member"
+    }
+
+    fn module() -> Module {
+        Module::Standup
+    }
+
+    fn kind() -> CommandType {
+        CommandType::User
+    }
+
+    fn invocation_capability(&self, interaction: &CommandInteraction) -> Option<Capability> {
+        (self.user_id != u64::from(interaction.user.id)).then_some(Capability::StandupViewPrivate)
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b dyn Transport,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = interaction
+            .guild_id
+            .ok_or_else(|| {
+                CommandResponse::InternalFailure(String::from(
+                    "user history command used outside of a guild",
+                ))
+            })?
+            .into();
+        let locale = locale::resolve(
+            &app_state.storage,
+            Some(guild_id),
+            interaction.user.id.into(),
+            &interaction.locale,
+        )
+        .await;
+
+        let response = history_response(
+            app_state,
+            locale,
+            guild_id,
+            self.user_id,
+            THIS_WEEK_QUESTION,
+            DEFAULT_COUNT,
+        )
+        .await;
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .allowed_mentions(CreateAllowedMentions::new())
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
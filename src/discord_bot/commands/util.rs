@@ -1,7 +1,156 @@
 //! Various utilities to assist with writing application commands for the DIANA bot
 
+use std::{fmt::Display, time::Duration};
+
 use log::{debug, error, info, warn};
-use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use rand::Rng;
+use serenity::{
+    all::CommandInteraction,
+    builder::{
+        CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage,
+    },
+    model::{guild::Member, Permissions},
+};
+
+use crate::{
+    access::{roles_have_capability, Capability},
+    discord_bot::custom_id::DecodeError,
+    locale::{Locale, Text},
+    modules::Module,
+    state::AppState,
+};
+
+/// check if a member may use a capability in a guild, either through one of their roles or by managing the guild
+pub async fn member_has_capability(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    member: Option<&Member>,
+    capability: Capability,
+) -> bool {
+    if member_has_permission(member, Permissions::MANAGE_GUILD) {
+        return true;
+    }
+
+    let (guild_id, member) = match (guild_id, member) {
+        (Some(guild_id), Some(member)) => (guild_id, member),
+        _ => return false,
+    };
+    let roles: Vec<u64> = member.roles.iter().map(|r| u64::from(*r)).collect();
+    roles_have_capability(&app_state.storage, guild_id, &roles, capability).await
+}
+
+/// the failure returned when a member tries to use a capability they haven't been granted
+pub fn capability_denied(locale: Locale, user_id: u64, capability: Capability) -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        response: Text::CapabilityMissing.format(locale, &[("capability", capability.name())]),
+        kind: FailureMessageKind::Debug,
+        log_message: format!("user {} denied without {}", user_id, capability.name()),
+    }
+}
+
+/// the failure returned when a command, or one of its components, is used while its module is disabled in the guild
+pub fn module_disabled(locale: Locale, command: &str, module: Module) -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        response: Text::ModuleUnavailable.format(locale, &[("module", module.name())]),
+        kind: FailureMessageKind::Debug,
+        log_message: format!("{} refused, module {} is disabled", command, module.name()),
+    }
+}
+
+/// the failure returned when a command is used again before its cooldown has passed, `hits` counting every time it has been limited
+pub fn cooldown_denied(
+    locale: Locale,
+    command: &str,
+    user_id: u64,
+    wait: Duration,
+    hits: u64,
+) -> CommandResponse {
+    // rounded up, so nobody is told to try again in 0s
+    let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    CommandResponse::ComplexFailure {
+        response: Text::CooldownActive.format(locale, &[("seconds", &seconds.to_string())]),
+        kind: FailureMessageKind::Info,
+        log_message: format!(
+            "user {} limited using /{} for {}s, limited {} times since starting",
+            user_id, command, seconds, hits
+        ),
+    }
+}
+
+/// the failure returned when a component or modal has a custom id which is no longer understood, such as one on an old message
+pub fn component_expired(locale: Locale, custom_id: &str, error: DecodeError) -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        response: Text::ComponentExpired.get(locale).to_string(),
+        kind: FailureMessageKind::Debug,
+        log_message: format!("{} {}", error, custom_id),
+    }
+}
+
+/// the most choices discord will show in response to an autocomplete
+pub const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// check if the member who ran a command has the provided permissions in the channel it was run in
+pub fn has_permission(interaction: &CommandInteraction, permission: Permissions) -> bool {
+    member_has_permission(
+        interaction.member.as_ref().map(|m| -> &Member { m }),
+        permission,
+    )
+}
+
+/// check if the member who triggered an interaction has the provided permissions in its channel
+pub fn member_has_permission(member: Option<&Member>, permission: Permissions) -> bool {
+    member
+        .and_then(|m| m.permissions)
+        .map_or(false, |p| p.contains(permission))
+}
+
+/// the interaction a failure happened in, logged with the failure so a report from a user can be traced to its cause
+#[derive(Debug, Clone)]
+pub struct FailureContext {
+    /// a short random id, shown to the user and written to the log
+    pub correlation_id: String,
+    /// the guild the interaction was in, if it wasn't in a DM
+    pub guild_id: Option<u64>,
+    /// the user who triggered the interaction
+    pub user_id: u64,
+    /// the command, component or modal which failed
+    pub source: String,
+    /// the id of the interaction, generated by discord
+    pub interaction_id: u64,
+}
+
+impl FailureContext {
+    /// describe a failed interaction, giving it a new correlation id
+    pub fn new(
+        guild_id: Option<u64>,
+        user_id: u64,
+        source: impl Into<String>,
+        interaction_id: u64,
+    ) -> Self {
+        Self {
+            correlation_id: format!("{:06x}", rand::thread_rng().gen_range(0..0x1000000)),
+            guild_id,
+            user_id,
+            source: source.into(),
+            interaction_id,
+        }
+    }
+}
+
+impl Display for FailureContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let guild = match self.guild_id {
+            Some(guild_id) => guild_id.to_string(),
+            None => String::from("none"),
+        };
+        write!(
+            f,
+            "error {} (guild {}, user {}, {}, interaction {})",
+            self.correlation_id, guild, self.user_id, self.source, self.interaction_id
+        )
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code, clippy::missing_docs_in_private_items)]
@@ -38,6 +187,14 @@ pub enum CommandResponse {
     /// but will instead log it to the console, and return a generic "internal error" resposne
     /// to the user
     InternalFailure(String),
+    /// acknowledge the interaction without a message yet, showing that the bot is thinking.
+    /// the handler must carry on in a task of its own, and edit or follow up the response once it is done
+    Defer {
+        /// whether the eventual response is only shown to the user
+        ephemeral: bool,
+    },
+    /// a message sent after the interaction has already been responded to, such as by the handler itself
+    Followup(CreateInteractionResponseFollowup),
     NoResponse,
 }
 
@@ -56,25 +213,43 @@ impl CommandResponse {
     pub fn get_log_type(&self) -> FailureMessageKind {
         match self {
             Self::BasicFailure(_) => FailureMessageKind::Error,
+            Self::InternalFailure(_) => FailureMessageKind::Error,
             Self::ComplexFailure { kind, .. } => *kind,
             _ => FailureMessageKind::Info,
         }
     }
 
-    /// writ ethe message to the log, if there is a loggable message
-    pub fn write_to_log(&self) {
+    /// writ ethe message to the log, if there is a loggable message, along with where it happened
+    pub fn write_to_log(&self, failure: &FailureContext) {
         if let Some(message) = self.get_log_message() {
             match self.get_log_type() {
-                FailureMessageKind::Error => error!("{}", message),
-                FailureMessageKind::Warn => warn!("{}", message),
-                FailureMessageKind::Info => info!("{}", message),
-                FailureMessageKind::Debug => debug!("{}", message),
+                FailureMessageKind::Error => error!("{}: {}", failure, message),
+                FailureMessageKind::Warn => warn!("{}: {}", failure, message),
+                FailureMessageKind::Info => info!("{}: {}", failure, message),
+                FailureMessageKind::Debug => debug!("{}: {}", failure, message),
             }
         }
     }
 
-    /// generate a response to be sent to the user from the CommandResponse type
-    pub fn generate_response(self) -> Option<CreateInteractionResponse> {
+    /// generate a response to be sent to the user from the CommandResponse type, generic messages
+    /// are written in the provided locale. Failures which are logged as errors or warnings reference
+    /// the correlation id of the failure, so the user can report it
+    pub fn generate_response(
+        self,
+        locale: Locale,
+        failure: Option<&FailureContext>,
+    ) -> Option<CreateInteractionResponse> {
+        let reference = match (failure, self.get_log_type()) {
+            (Some(failure), FailureMessageKind::Error | FailureMessageKind::Warn) => {
+                Some(Text::ErrorReference.format(locale, &[("id", &failure.correlation_id)]))
+            }
+            _ => None,
+        };
+        let referenced = |message: String| match &reference {
+            Some(reference) => format!("{}\n{}", message, reference),
+            None => message,
+        };
+
         match self {
             CommandResponse::BasicSuccess(message) => Some(CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::default()
@@ -85,21 +260,73 @@ impl CommandResponse {
             CommandResponse::BasicFailure(message) => Some(CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::default()
                     .ephemeral(true)
-                    .content(message),
+                    .content(referenced(message)),
             )),
             CommandResponse::ComplexFailure { response, .. } => {
                 Some(CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::default()
                         .ephemeral(true)
-                        .content(response),
+                        .content(referenced(response)),
                 ))
             }
             CommandResponse::InternalFailure(_) => Some(CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::default()
                     .ephemeral(true)
-                    .content("An internal error occurred."),
+                    .content(referenced(Text::InternalError.get(locale).to_string())),
+            )),
+            CommandResponse::Defer { ephemeral } => Some(CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::default().ephemeral(ephemeral),
             )),
-            CommandResponse::NoResponse => None,
+            // followups aren't a response to the interaction, so are sent separately
+            CommandResponse::Followup(_) | CommandResponse::NoResponse => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::{Locale, Text};
+
+    use super::{CommandResponse, FailureContext, FailureMessageKind};
+
+    /// the content of the response generated for a failure
+    fn content(response: CommandResponse, failure: &FailureContext) -> String {
+        let response = response
+            .generate_response(Locale::English, Some(failure))
+            .unwrap();
+        serde_json::to_value(response).unwrap()["data"]["content"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn only_errors_show_a_correlation_id() {
+        let failure = FailureContext::new(Some(1), 2, "command test", 3);
+        let reference =
+            Text::ErrorReference.format(Locale::English, &[("id", &failure.correlation_id)]);
+
+        let basic = content(
+            CommandResponse::BasicFailure(String::from("Invalid date")),
+            &failure,
+        );
+        assert_eq!(basic, format!("Invalid date\n{}", reference));
+
+        let internal = content(
+            CommandResponse::InternalFailure(String::from("storage failed")),
+            &failure,
+        );
+        assert!(internal.ends_with(&reference));
+        assert!(!internal.contains("storage failed"));
+
+        let info = content(
+            CommandResponse::ComplexFailure {
+                response: String::from("Nothing to cancel"),
+                kind: FailureMessageKind::Info,
+                log_message: String::from("nothing scheduled"),
+            },
+            &failure,
+        );
+        assert_eq!(info, "Nothing to cancel");
+    }
+}
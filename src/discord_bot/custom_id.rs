@@ -0,0 +1,285 @@
+//! The custom ids attached to components and modals, encoded as `namespace:action:version:payload`.
+//! Every component and modal the bot creates is listed here, and interactions are routed to a command by namespace.
+//! When the payload of a component changes its version is bumped, so components on old messages are recognised as expired.
+
+use std::fmt::Display;
+
+/// the separator between the parts of a custom id
+const SEPARATOR: char = ':';
+
+/// the separator between the ids of a payload
+const PAYLOAD_SEPARATOR: char = '.';
+
+/// ids in a payload are written in base 36 to keep custom ids short
+const PAYLOAD_RADIX: u32 = 36;
+
+/// a component or modal created by the bot, and the ids it carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentId {
+    /// the button on a standup panel which opens the submission modal
+    StandupStart { session_id: u64 },
+    /// the button on a standup panel which closes the session
+    StandupClose { session_id: u64 },
+    /// the modal submitting a standup, to a session if it was opened from one
+    StandupSubmit { session_id: Option<u64> },
+    /// the button on a submission which opens the modal to edit it, and that modal
+    StandupEdit { submission_id: u64 },
+    /// the button on a submission which deletes it
+    StandupDelete { submission_id: u64 },
+}
+
+/// why a custom id couldn't be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the id isn't in the format used by the bot, such as one created before custom ids were versioned
+    Malformed,
+    /// the id is well formed, but for a component which is no longer created or an older version of one
+    Outdated,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Malformed => write!(f, "malformed custom id"),
+            DecodeError::Outdated => write!(f, "outdated custom id"),
+        }
+    }
+}
+
+/// write an id in base 36
+fn encode_id(mut id: u64) -> String {
+    let mut digits = vec![];
+    loop {
+        digits.push(
+            std::char::from_digit((id % PAYLOAD_RADIX as u64) as u32, PAYLOAD_RADIX).unwrap(),
+        );
+        id /= PAYLOAD_RADIX as u64;
+        if id == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+/// a kind of component, the namespace and action it is encoded with, and its current version
+struct Kind {
+    namespace: &'static str,
+    action: &'static str,
+    version: u8,
+    /// rebuild the component from its payload, if the payload is the shape of the current version
+    build: fn(&[u64]) -> Option<ComponentId>,
+}
+
+/// every kind of component the bot creates, both encoding and decoding are derived from this table
+const KINDS: &[Kind] = &[
+    Kind {
+        namespace: "standup",
+        action: "start",
+        version: 1,
+        build: |payload| match payload {
+            [session_id] => Some(ComponentId::StandupStart {
+                session_id: *session_id,
+            }),
+            _ => None,
+        },
+    },
+    Kind {
+        namespace: "standup",
+        action: "close",
+        version: 1,
+        build: |payload| match payload {
+            [session_id] => Some(ComponentId::StandupClose {
+                session_id: *session_id,
+            }),
+            _ => None,
+        },
+    },
+    Kind {
+        namespace: "standup",
+        action: "submit",
+        version: 1,
+        build: |payload| match payload {
+            [] => Some(ComponentId::StandupSubmit { session_id: None }),
+            [session_id] => Some(ComponentId::StandupSubmit {
+                session_id: Some(*session_id),
+            }),
+            _ => None,
+        },
+    },
+    Kind {
+        namespace: "standup",
+        action: "edit",
+        version: 1,
+        build: |payload| match payload {
+            [submission_id] => Some(ComponentId::StandupEdit {
+                submission_id: *submission_id,
+            }),
+            _ => None,
+        },
+    },
+    Kind {
+        namespace: "standup",
+        action: "delete",
+        version: 1,
+        build: |payload| match payload {
+            [submission_id] => Some(ComponentId::StandupDelete {
+                submission_id: *submission_id,
+            }),
+            _ => None,
+        },
+    },
+];
+
+impl ComponentId {
+    /// the kind of this component, the one which rebuilds it from its payload
+    fn kind(&self) -> &'static Kind {
+        let payload = self.payload();
+        KINDS
+            .iter()
+            .find(|kind| (kind.build)(&payload) == Some(*self))
+            .expect("every component is listed in KINDS")
+    }
+
+    /// the ids carried by this component
+    fn payload(&self) -> Vec<u64> {
+        match *self {
+            ComponentId::StandupStart { session_id } | ComponentId::StandupClose { session_id } => {
+                vec![session_id]
+            }
+            ComponentId::StandupSubmit { session_id } => session_id.into_iter().collect(),
+            ComponentId::StandupEdit { submission_id }
+            | ComponentId::StandupDelete { submission_id } => vec![submission_id],
+        }
+    }
+
+    /// the namespace of this component, used to route it to the command which handles it
+    pub fn namespace(&self) -> &'static str {
+        self.kind().namespace
+    }
+
+    /// encode this component as a custom id
+    pub fn encode(&self) -> String {
+        let Kind {
+            namespace,
+            action,
+            version,
+            ..
+        } = self.kind();
+        let payload = self
+            .payload()
+            .into_iter()
+            .map(encode_id)
+            .collect::<Vec<_>>()
+            .join(&PAYLOAD_SEPARATOR.to_string());
+        format!(
+            "{}{sep}{}{sep}{}{sep}{}",
+            namespace,
+            action,
+            version,
+            payload,
+            sep = SEPARATOR
+        )
+    }
+
+    /// decode a custom id created by [ComponentId::encode]
+    pub fn decode(custom_id: &str) -> Result<Self, DecodeError> {
+        let mut parts = custom_id.splitn(4, SEPARATOR);
+        let (namespace, action, version, payload) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(namespace), Some(action), Some(version), Some(payload)) => {
+                    (namespace, action, version, payload)
+                }
+                _ => return Err(DecodeError::Malformed),
+            };
+        let version: u8 = version.parse().map_err(|_| DecodeError::Malformed)?;
+        let payload = match payload.is_empty() {
+            true => vec![],
+            false => payload
+                .split(PAYLOAD_SEPARATOR)
+                .map(|id| u64::from_str_radix(id, PAYLOAD_RADIX))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| DecodeError::Malformed)?,
+        };
+
+        KINDS
+            .iter()
+            .find(|kind| {
+                kind.namespace == namespace && kind.action == action && kind.version == version
+            })
+            .and_then(|kind| (kind.build)(&payload))
+            .ok_or(DecodeError::Outdated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_round_trip() {
+        let components = [
+            ComponentId::StandupStart { session_id: 1 },
+            ComponentId::StandupClose {
+                session_id: u64::MAX,
+            },
+            ComponentId::StandupSubmit { session_id: None },
+            ComponentId::StandupSubmit {
+                session_id: Some(36),
+            },
+            ComponentId::StandupEdit { submission_id: 0 },
+            ComponentId::StandupDelete {
+                submission_id: 1234567890123,
+            },
+        ];
+        for component in components {
+            let custom_id = component.encode();
+            assert!(custom_id.len() <= 100, "{} is too long", custom_id);
+            assert_eq!(ComponentId::decode(&custom_id), Ok(component));
+        }
+        assert_eq!(
+            ComponentId::StandupSubmit {
+                session_id: Some(36)
+            }
+            .encode(),
+            "standup:submit:1:10"
+        );
+    }
+
+    #[test]
+    fn malformed_ids_are_refused() {
+        for custom_id in [
+            "",
+            "standup_start",
+            "standup:start:1",
+            "standup:start:one:1",
+            "standup:start:1:not-base-36",
+            "standup:start:1:1..2",
+        ] {
+            assert_eq!(
+                ComponentId::decode(custom_id),
+                Err(DecodeError::Malformed),
+                "{}",
+                custom_id
+            );
+        }
+    }
+
+    #[test]
+    fn outdated_ids_are_refused() {
+        for custom_id in [
+            "standup:start:0:1",
+            "standup:start:2:1",
+            "standup:start:1:",
+            "standup:start:1:1.2",
+            "standup:pause:1:1",
+            "polls:start:1:1",
+        ] {
+            assert_eq!(
+                ComponentId::decode(custom_id),
+                Err(DecodeError::Outdated),
+                "{}",
+                custom_id
+            );
+        }
+    }
+}
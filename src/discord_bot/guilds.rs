@@ -1,74 +1,298 @@
 //! A handler for a guild, each guild will have one handler instance to manage it
 
 use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
     sync::{atomic::Ordering, Arc},
     time::Duration,
 };
 
 use log::{error, info, trace, warn};
+use serde_json::json;
 use serenity::{
     all::Interaction,
-    builder::{CreateAutocompleteResponse, CreateInteractionResponse},
+    builder::{
+        CreateAutocompleteResponse, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
     client::Context,
-    futures::{stream::FuturesUnordered, StreamExt},
+    futures::{stream::FuturesUnordered, FutureExt, StreamExt},
     model::id::GuildId,
 };
 use tokio::{
     select,
+    sync::broadcast::error::RecvError,
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
     sync::RwLock,
     task::JoinHandle,
 };
 
-use super::manager::{DiscordEvent, InternalSender};
+use super::{
+    announcements::send_due_messages,
+    board::handle_deleted_message,
+    manager::{DiscordEvent, InternalSender},
+    registration::sync_guild,
+    transport::{InteractionRef, Transport, TransportError},
+};
 use crate::{
     discord_bot::commands::{
-        application_command, autocomplete, command, handle_modal, interaction as handle_interaction,
+        autocomplete, command, deferrable, deferrable_component, handle_modal,
+        interaction as handle_interaction, CommandResponse, FailureContext,
     },
+    locale::{self, Locale, Text},
     state::AppState,
 };
 
+/// the time a command may take before it is deferred, discord only waits 3 seconds for a response
+const DEFER_AFTER: Duration = Duration::from_secs(2);
+
+/// the flag marking an interaction response as only shown to the user
+const EPHEMERAL_FLAG: u64 = 1 << 6;
+
+/// how often each guild checks for scheduled messages which have become due
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// run a handler, turning a panic into an internal failure so the user is still told that something went wrong
+async fn catch_panic<T, F>(handler: F) -> Result<T, CommandResponse>
+where
+    F: Future<Output = Result<T, CommandResponse>>,
+{
+    match AssertUnwindSafe(handler).catch_unwind().await {
+        Ok(res) => res,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("unknown panic"));
+            Err(CommandResponse::InternalFailure(format!(
+                "handler panicked: {}",
+                message
+            )))
+        }
+    }
+}
+
+/// run a handler, turning a panic into an internal failure. discord only waits a few seconds for a response,
+/// so if the interaction may be deferred and the handler is still running after [DEFER_AFTER], it is deferred while it finishes.
+/// returns the result of the handler, and whether the interaction was deferred
+async fn within_budget<F>(
+    handler: F,
+    deferrable: bool,
+    interaction: InteractionRef<'_>,
+    label: &str,
+    context: &Context,
+) -> (Result<CommandResponse, CommandResponse>, bool)
+where
+    F: Future<Output = Result<CommandResponse, CommandResponse>>,
+{
+    let handler = catch_panic(handler);
+    tokio::pin!(handler);
+    if !deferrable {
+        return (handler.await, false);
+    }
+
+    match tokio::time::timeout(DEFER_AFTER, &mut handler).await {
+        Ok(res) => (res, false),
+        Err(_) => {
+            trace!("Deferring slow {}", label);
+            let defer = CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(true),
+            );
+            let deferred = match context.respond(interaction, defer).await {
+                Ok(_) => true,
+                Err(e) => {
+                    error!("Unable to defer response: {:?}", e);
+                    false
+                }
+            };
+            (handler.await, deferred)
+        }
+    }
+}
+
+/// send the response to an interaction, or if it was deferred, deliver it by editing the deferred response.
+/// a followup is sent in addition to whatever the interaction was already responded with
+async fn respond_to_interaction(
+    interaction: InteractionRef<'_>,
+    label: &str,
+    transport: &dyn Transport,
+    response: CommandResponse,
+    locale: Locale,
+    failure: Option<&FailureContext>,
+    deferred: bool,
+) {
+    if let CommandResponse::Followup(followup) = response {
+        let sent = match serde_json::to_value(&followup) {
+            Ok(body) => transport.followup(interaction, &body).await.map(|_| ()),
+            Err(e) => Err(TransportError::Other(e.to_string())),
+        };
+        if let Err(e) = sent {
+            error!("Unable to send followup: {:?}", e);
+        }
+        return;
+    }
+
+    let resp = match response.generate_response(locale, failure) {
+        Some(resp) => resp,
+        None => return,
+    };
+    if !deferred {
+        if let Err(e) = transport.respond(interaction, resp).await {
+            error!("Unable to send response: {:?}", e);
+        }
+        return;
+    }
+
+    let message = match resp {
+        CreateInteractionResponse::Message(message) => message,
+        // the dispatcher has already deferred the interaction
+        CreateInteractionResponse::Defer(_) => return,
+        resp => {
+            error!(
+                "Unable to send {:?} after deferring {}, only messages can be sent",
+                resp, label
+            );
+            let edit = json!({ "content": Text::InternalError.get(locale) });
+            if let Err(e) = transport.edit_response(interaction, &edit).await {
+                error!("Unable to edit deferred response: {:?}", e);
+            }
+            return;
+        }
+    };
+
+    // the builders can't be converted between each other, but share the same body
+    let body = match serde_json::to_value(&message) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Unable to serialise deferred response: {:?}", e);
+            return;
+        }
+    };
+    let ephemeral = body
+        .get("flags")
+        .and_then(|f| f.as_u64())
+        .map_or(false, |f| f & EPHEMERAL_FLAG != 0);
+
+    // the deferred response is private, so a public message replaces it with a followup instead
+    let sent = match ephemeral {
+        true => transport.edit_response(interaction, &body).await,
+        false => match transport.delete_response(interaction).await {
+            Ok(()) => transport.followup(interaction, &body).await.map(|_| ()),
+            Err(e) => Err(e),
+        },
+    };
+    if let Err(e) = sent {
+        error!("Unable to deliver deferred response: {:?}", e);
+    }
+}
+
 /// handle an interaction generated by slash command.
 /// matches over the type of interaction and then handles it appropriately, generating a response that can be sent to the user
 async fn handle_slash_command(interaction: Interaction, context: Context, app_state: AppState) {
     match interaction {
         Interaction::Command(raw_command) => {
             trace!("Received application command: {:?}", raw_command);
-            let res = command(&raw_command, &app_state, &context).await;
+            let label = format!("command {}", raw_command.data.name);
+            let (res, deferred) = within_budget(
+                command(&raw_command, &app_state, &context),
+                deferrable(&raw_command),
+                InteractionRef::from(&raw_command),
+                &label,
+                &context,
+            )
+            .await;
+
+            let locale = locale::resolve(
+                &app_state.storage,
+                raw_command.guild_id.map(u64::from),
+                raw_command.user.id.into(),
+                &raw_command.locale,
+            )
+            .await;
 
-            match res {
+            let (response, failure) = match res {
                 Ok(response) => {
                     trace!("Sending response: {:?}", response);
-
-                    if let Some(resp) = response.generate_response() {
-                        if let Err(e) = raw_command.create_response(&context, resp).await {
-                            error!("Unable to send response: {:?}", e);
-                        }
-                    }
+                    (response, None)
                 }
                 Err(response) => {
-                    response.write_to_log();
-
-                    if let Some(resp) = response.generate_response() {
-                        if let Err(e) = raw_command.create_response(&context, resp).await {
-                            error!("Unable to send response: {:?}", e);
-                        }
-                    }
+                    let failure = FailureContext::new(
+                        raw_command.guild_id.map(u64::from),
+                        raw_command.user.id.into(),
+                        label.clone(),
+                        raw_command.id.into(),
+                    );
+                    response.write_to_log(&failure);
+                    (response, Some(failure))
                 }
-            }
+            };
+            respond_to_interaction(
+                InteractionRef::from(&raw_command),
+                &label,
+                &context,
+                response,
+                locale,
+                failure.as_ref(),
+                deferred,
+            )
+            .await;
         }
         Interaction::Component(component) => {
             trace!("Received component interaction: {:?}", component);
-            if let Err(e) = handle_interaction(&component, &app_state, &context).await {
-                error!("Unable to handle component interaction: {:?}", e);
-            }
+            let label = format!("component {}", component.data.custom_id);
+            let (res, deferred) = within_budget(
+                handle_interaction(&component, &app_state, &context),
+                deferrable_component(&component.data.custom_id),
+                InteractionRef::from(&component),
+                &label,
+                &context,
+            )
+            .await;
+            let (response, failure) = match res {
+                Ok(response) => (response, None),
+                Err(response) => {
+                    let failure = FailureContext::new(
+                        component.guild_id.map(u64::from),
+                        component.user.id.into(),
+                        label.clone(),
+                        component.id.into(),
+                    );
+                    response.write_to_log(&failure);
+                    (response, Some(failure))
+                }
+            };
+
+            let locale = locale::resolve(
+                &app_state.storage,
+                component.guild_id.map(u64::from),
+                component.user.id.into(),
+                &component.locale,
+            )
+            .await;
+            respond_to_interaction(
+                InteractionRef::from(&component),
+                &label,
+                &context,
+                response,
+                locale,
+                failure.as_ref(),
+                deferred,
+            )
+            .await;
         }
         Interaction::Autocomplete(interaction) => {
-            let res = autocomplete(&interaction, &app_state, &context).await;
+            trace!("Received autocomplete: {:?}", interaction);
+            // an empty list of choices is still sent on failure, so the user isn't left waiting
+            let res = catch_panic(autocomplete(&interaction, &app_state, &context)).await;
             let resp = match res {
                 Ok(r) => r,
                 Err(r) => {
-                    r.write_to_log();
+                    r.write_to_log(&FailureContext::new(
+                        interaction.guild_id.map(u64::from),
+                        interaction.user.id.into(),
+                        format!("autocomplete {}", interaction.data.name),
+                        interaction.id.into(),
+                    ));
                     CreateAutocompleteResponse::default()
                 }
             };
@@ -81,15 +305,62 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
         }
         Interaction::Modal(submit) => {
             trace!("Received modal submit: {:?}", submit);
-            if let Err(e) = handle_modal(&submit, &app_state, &context).await {
-                error!("Unable to handle modal submit: {:?}", e);
-            }
+            let label = format!("modal {}", submit.data.custom_id);
+            let (res, deferred) = within_budget(
+                handle_modal(&submit, &app_state, &context),
+                deferrable_component(&submit.data.custom_id),
+                InteractionRef::from(&submit),
+                &label,
+                &context,
+            )
+            .await;
+            let (response, failure) = match res {
+                Ok(response) => (response, None),
+                Err(response) => {
+                    let failure = FailureContext::new(
+                        submit.guild_id.map(u64::from),
+                        submit.user.id.into(),
+                        label.clone(),
+                        submit.id.into(),
+                    );
+                    response.write_to_log(&failure);
+                    (response, Some(failure))
+                }
+            };
+
+            let locale = locale::resolve(
+                &app_state.storage,
+                submit.guild_id.map(u64::from),
+                submit.user.id.into(),
+                &submit.locale,
+            )
+            .await;
+            respond_to_interaction(
+                InteractionRef::from(&submit),
+                &label,
+                &context,
+                response,
+                locale,
+                failure.as_ref(),
+                deferred,
+            )
+            .await;
         }
         // ping commands should not get here
         _ => unreachable!(),
     }
 }
 
+/// register the commands of a guild in a new task, if they have changed
+fn sync_in_background(context: &Context, app_state: &AppState, guild: GuildId) -> JoinHandle<()> {
+    let context = context.clone();
+    let app_state = app_state.clone();
+    tokio::task::spawn(async move {
+        // a rejection has already been logged, and is retried once the commands change again
+        let _ = sync_guild(&context, &app_state, guild.into()).await;
+    })
+}
+
 /// a handler which manages a guild, interacting with and responding to all events as required
 pub struct GuildHandler {
     /// the id of the guild being managed, generated by discord
@@ -173,21 +444,22 @@ impl GuildHandler {
             app_state.num_connected.fetch_add(1, Ordering::Relaxed);
 
             self.handle = Some(tokio::task::spawn(async move {
-                // register all commands
-                while let Err(e) = guild
-                    .set_application_commands(&context, application_command())
-                    .await
-                {
-                    error!("failed to register commands for guild {}: {}", guild, e);
-                    tokio::time::sleep(Duration::from_secs(10)).await;
-                }
-
+                let mut command_syncs = app_state.command_syncs.subscribe();
                 let mut internal_rx = internal_rx.write().await;
                 let mut task_handles = FuturesUnordered::new();
+                let mut schedule = tokio::time::interval(SCHEDULE_INTERVAL);
+
+                // register all commands, if they have changed since they were last registered.
+                // registering may be retried for a while, so interactions are handled in the meantime
+                task_handles.push(sync_in_background(&context, &app_state, guild));
 
                 loop {
                     select! {
-                        Some(message) = internal_rx.recv() => {
+                        message = internal_rx.recv() => {
+                            // every sender has gone, so no more events will arrive
+                            let Some(message) = message else {
+                                break;
+                            };
                             match message {
                                 DiscordEvent::Shutdown => {
                                     internal_rx.close();
@@ -209,11 +481,38 @@ impl GuildHandler {
                                 DiscordEvent::Message(_) => {
                                     continue; //ignore messages :)
                                 }
+                                DiscordEvent::MessageDeleted { guild_id, message_id, .. } => {
+                                    let t_ctx = context.clone();
+                                    let t_app_state = app_state.clone();
+                                    task_handles.push(tokio::task::spawn(async move {
+                                        handle_deleted_message(&t_ctx, &t_app_state, guild_id, message_id).await;
+                                    }))
+                                }
                                 e => {
                                     error!("bot ignoring unexpected event: {:?}", e);
                                 }
                             }
                         },
+                        // register the commands again once they have changed, such as when a module is disabled
+                        request = command_syncs.recv() => {
+                            match request {
+                                Ok(guild_id) if guild_id != u64::from(guild) => {}
+                                // requests were missed, so the commands of this guild may have changed
+                                Ok(_) | Err(RecvError::Lagged(_)) => {
+                                    task_handles.push(sync_in_background(&context, &app_state, guild));
+                                }
+                                // the app state of this handler holds a sender, so the channel can't close
+                                Err(RecvError::Closed) => {}
+                            }
+                        },
+                        // send any scheduled messages which have become due
+                        _ = schedule.tick() => {
+                            let t_ctx = context.clone();
+                            let t_app_state = app_state.clone();
+                            task_handles.push(tokio::task::spawn(async move {
+                                send_due_messages(&t_ctx, &t_app_state, guild.into()).await;
+                            }))
+                        },
                         // drain task handles as they complete
                         _ = task_handles.next(), if !task_handles.is_empty() => {},
                         else => break,
@@ -247,3 +546,102 @@ impl std::fmt::Debug for GuildHandler {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serenity::builder::{
+        CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage,
+    };
+
+    use crate::{
+        discord_bot::{
+            commands::CommandResponse,
+            transport::{InteractionRef, RecordingTransport, Sent},
+        },
+        locale::Locale,
+    };
+
+    use super::respond_to_interaction;
+
+    const INTERACTION: InteractionRef<'static> = InteractionRef {
+        id: 1,
+        token: "token",
+    };
+
+    /// deliver a response through the dispatcher, as if the interaction had or hadn't been deferred already
+    async fn deliver(response: CommandResponse, deferred: bool) -> Vec<Sent> {
+        let transport = RecordingTransport::default();
+        respond_to_interaction(
+            INTERACTION,
+            "command test",
+            &transport,
+            response,
+            Locale::English,
+            None,
+            deferred,
+        )
+        .await;
+        transport.sent()
+    }
+
+    #[tokio::test]
+    async fn a_handler_may_defer_on_purpose() {
+        match deliver(CommandResponse::Defer { ephemeral: true }, false)
+            .await
+            .as_slice()
+        {
+            [Sent::Response { body, .. }] => {
+                assert_eq!(body["type"], 5);
+                assert_eq!(body["data"]["flags"], 64);
+            }
+            other => panic!("expected a deferred ack, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_deferred_interaction_has_its_response_edited() {
+        match deliver(CommandResponse::BasicSuccess(String::from("done")), true)
+            .await
+            .as_slice()
+        {
+            [Sent::ResponseEdit { body, .. }] => assert_eq!(body["content"], "done"),
+            other => panic!(
+                "expected the deferred response to be edited, got {:?}",
+                other
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_public_response_to_a_deferred_interaction_is_followed_up() {
+        let response = CommandResponse::ComplexSuccess(CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content("for everyone"),
+        ));
+        match deliver(response, true).await.as_slice() {
+            [Sent::ResponseDelete { .. }, Sent::Followup { body, .. }] => {
+                assert_eq!(body["content"], "for everyone")
+            }
+            other => panic!("expected the private ack to be replaced, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn followups_are_sent_as_their_own_message() {
+        let followup = CreateInteractionResponseFollowup::new().content("later");
+        match deliver(CommandResponse::Followup(followup), false)
+            .await
+            .as_slice()
+        {
+            [Sent::Followup {
+                interaction_id,
+                body,
+                ..
+            }] => {
+                assert_eq!(*interaction_id, INTERACTION.id);
+                assert_eq!(body["content"], "later");
+            }
+            other => panic!("expected a followup, got {:?}", other),
+        }
+    }
+}
@@ -10,6 +10,7 @@ use serenity::{
     model::{
         gateway::Ready,
         guild::{Guild, Member, UnavailableGuild},
+        id::{ChannelId, GuildId, MessageId},
         prelude::Message,
     },
 };
@@ -18,6 +19,7 @@ use crate::{discord_bot::guilds::GuildHandler, state::AppState};
 
 use super::{
     manager::{DiscordEvent, InternalSender},
+    registration::sync_global,
     utils::BotDiscordId,
 };
 
@@ -58,6 +60,38 @@ impl EventHandler for Handler {
         }
     }
 
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        // only messages in guilds can be a board
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        let reader = ctx.data.read().await;
+
+        let internal_sender = match reader.get::<InternalSender>() {
+            Some(internal_sender) => internal_sender,
+            None => {
+                error!("InternalSender not found in context");
+                return;
+            }
+        };
+
+        if let Err(e) = internal_sender.send(DiscordEvent::MessageDeleted {
+            guild_id: guild_id.into(),
+            channel_id: channel_id.into(),
+            message_id: deleted_message_id.into(),
+        }) {
+            error!("Error sending deleted message to internal sender: {:?}", e);
+        }
+    }
+
     async fn guild_member_addition(&self, _ctx: Context, _new_member: Member) {
         warn!("New member joined, handler function not yet implemented");
         // todo!() //TODO: use this to readd a users roles if they have previously been verified
@@ -127,6 +161,20 @@ impl EventHandler for Handler {
         if let Err(e) = internal_sender.send(DiscordEvent::DeletedGuild(guild.id.0.into())) {
             error!("Error sending deleted guild to internal sender: {:?}", e);
         }
+
+        // the bot was removed rather than the guild going down, its commands went with it so must be registered again if it returns
+        if !guild.unavailable {
+            if let Some(app_state) = data_read.get::<AppState>() {
+                let guild_id: u64 = guild.id.into();
+                if let Err(e) = app_state
+                    .storage
+                    .update(|data| data.command_hashes.guilds.remove(&guild_id))
+                    .await
+                {
+                    error!("failed to forget the commands of guild {}: {}", guild_id, e);
+                }
+            }
+        }
     }
 
     #[allow(unused_mut)]
@@ -139,6 +187,17 @@ impl EventHandler for Handler {
             data_write.insert::<BotDiscordId>(BotDiscordId::new(ready.user.id.0.into()));
         }
 
+        // global commands are registered here rather than by each guild, ready is sent again on reconnect
+        // but they are only registered again if they have changed
+        match ctx.data.read().await.get::<AppState>() {
+            Some(app_state) => {
+                let app_state = app_state.clone();
+                let ctx = ctx.clone();
+                tokio::spawn(async move { sync_global(&ctx, &app_state).await });
+            }
+            None => error!("AppState not found in context"),
+        }
+
         ready
             .user
             .edit(
@@ -30,6 +30,12 @@ pub enum DiscordEvent {
     Interaction(Box<Interaction>),
     /// a new message received from any guild
     Message(Box<Message>),
+    /// a message was deleted in a guild
+    MessageDeleted {
+        guild_id: u64,
+        channel_id: u64,
+        message_id: u64,
+    },
     /// a shutdown command to be sent to a guild, when received the guild should cease all activity and shut down
     Shutdown,
 }
@@ -229,6 +235,19 @@ impl<T: Send + Sync + 'static + Clone + TypeMapKey<Value = T>> DiscordBot<T> {
                                     error!("failed to send message to guild handler {}", e);
                                 }
                             }
+                            DiscordEvent::MessageDeleted { guild_id, channel_id, message_id } => {
+                                let g_h = match guild_handlers.get(&guild_id) {
+                                    Some(s) => s.internal_tx.clone(),
+                                    None => {
+                                        warn!("got deleted message for non-existant guild id {}", guild_id);
+                                        continue;
+                                    }
+                                };
+
+                                if let Err(e) = g_h.send(DiscordEvent::MessageDeleted { guild_id, channel_id, message_id }) {
+                                    error!("failed to send deleted message to guild handler {}", e);
+                                }
+                            }
                             e => error!("unexpected discord event received {:?}", e),
                         }
                     },
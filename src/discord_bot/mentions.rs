@@ -0,0 +1,12 @@
+//! The mentions the bot lets discord turn into pings, set on every message containing text written by members.
+
+use serenity::builder::CreateAllowedMentions;
+
+/// the mentions allowed in a message containing text written by members, never roles, `@everyone` or `@here`,
+/// and users only if the guild lets them be pinged
+pub fn allowed_mentions(users: bool) -> CreateAllowedMentions {
+    match users {
+        true => CreateAllowedMentions::new().all_users(true),
+        false => CreateAllowedMentions::new(),
+    }
+}
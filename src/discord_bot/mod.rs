@@ -1,10 +1,17 @@
 //! This module is used for managing everything related to the actual discord server, and the bot itself.
 //! The bot is built on top of the Serenity discord crate.
 
+mod announcements;
+mod board;
 mod commands;
+mod custom_id;
 mod guilds;
 mod handler;
 mod manager;
+mod mentions;
+mod panel;
+mod registration;
+mod transport;
 mod utils;
 
 pub use manager::{DiscordBot, DiscordBotBuilder};
@@ -0,0 +1,610 @@
+//! The progress panel, the message posted by `/standup` which is kept up to date with who has submitted to the
+//! session, and which links to the digest of the session once it has been closed.
+
+use chrono::Utc;
+use log::error;
+use serenity::{
+    all::ButtonStyle,
+    builder::{CreateActionRow, CreateAllowedMentions, CreateButton, CreateMessage, EditMessage},
+};
+
+use crate::{
+    holidays::member_on_holiday,
+    locale::{self, Locale, Text},
+    mentions::neutralise,
+    standups::{StandupSession, Submission, THIS_WEEK_QUESTION},
+    state::AppState,
+    timezone::{discord_timestamp, TimestampStyle},
+};
+
+use super::{
+    board::{shorten, MemberCache},
+    custom_id::ComponentId,
+    transport::Transport,
+};
+
+/// the most members mentioned on a single line of the panel before the rest are counted instead
+const MAX_LISTED: usize = 30;
+
+/// the maximum length of a message discord will accept
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// mention a list of members, counting those past the limit rather than mentioning them
+fn mention_list(locale: Locale, ids: &[u64]) -> String {
+    let mut list = ids
+        .iter()
+        .take(MAX_LISTED)
+        .map(|id| format!("<@{}>", id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if ids.len() > MAX_LISTED {
+        list.push(' ');
+        list.push_str(
+            &Text::StandupMore.format(locale, &[("count", &(ids.len() - MAX_LISTED).to_string())]),
+        );
+    }
+    list
+}
+
+/// link to a message in a guild
+fn message_link(guild_id: u64, channel_id: u64, message_id: u64) -> String {
+    format!(
+        "https://discord.com/channels/{}/{}/{}",
+        guild_id, channel_id, message_id
+    )
+}
+
+/// find the submissions made to a session, in the order they were made
+async fn session_submissions(app_state: &AppState, session_id: u64) -> Vec<Submission> {
+    let mut submissions: Vec<Submission> = app_state
+        .storage
+        .read()
+        .await
+        .submissions
+        .values()
+        .filter(|s| s.session_id == Some(session_id))
+        .cloned()
+        .collect();
+    submissions.sort_by_key(|s| s.submitted_at);
+    submissions
+}
+
+/// find who has submitted to a session, and who is expected to but hasn't yet
+async fn progress(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    members: &MemberCache,
+    session: &StandupSession,
+    submissions: &[Submission],
+) -> (Vec<u64>, Vec<u64>) {
+    let mut submitted: Vec<u64> = vec![];
+    for submission in submissions {
+        if !submitted.contains(&submission.user_id) {
+            submitted.push(submission.user_id);
+        }
+    }
+
+    let (role, mut previous) = {
+        let data = app_state.storage.read().await;
+        let role = data
+            .guilds
+            .get(&session.guild_id)
+            .and_then(|g| g.board.as_ref())
+            .and_then(|b| b.roster_role);
+        let previous: Vec<u64> = data
+            .submissions
+            .values()
+            .filter(|s| s.guild_id == session.guild_id)
+            .map(|s| s.user_id)
+            .collect();
+        (role, previous)
+    };
+    previous.sort_unstable();
+    previous.dedup();
+
+    // without a roster role, anyone who has submitted a standup before is expected to submit again
+    let roster = match role {
+        Some(role) => members
+            .role_members(transport, session.guild_id, role)
+            .await
+            .unwrap_or(previous),
+        None => previous,
+    };
+    // members on holiday aren't expected to submit
+    let now = Utc::now();
+    let mut pending = vec![];
+    for id in roster.into_iter().filter(|id| !submitted.contains(id)) {
+        if !member_on_holiday(&app_state.storage, session.guild_id, id, now).await {
+            pending.push(id);
+        }
+    }
+
+    (submitted, pending)
+}
+
+/// render the content and buttons of the panel of a session
+fn render_panel(
+    locale: Locale,
+    session: &StandupSession,
+    submitted: &[u64],
+    pending: &[u64],
+) -> (String, Vec<CreateActionRow>) {
+    let counts = [
+        ("submitted", submitted.len().to_string()),
+        ("total", (submitted.len() + pending.len()).to_string()),
+    ];
+    let counts: Vec<(&str, &str)> = counts.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+    let mut lines = vec![];
+    match session.is_open() {
+        true => {
+            lines.push(Text::StandupPrompt.get(locale).to_string());
+            if let Some(holiday) = &session.holiday {
+                lines.push(Text::StandupHoliday.format(locale, &[("name", holiday)]));
+            }
+            lines.push(Text::StandupProgress.format(locale, &counts));
+        }
+        false => {
+            let mut closed = Text::StandupClosed.format(locale, &counts);
+            if let Some(digest) = session.digest_message_id {
+                let link = message_link(session.guild_id, session.channel_id, digest);
+                closed.push(' ');
+                closed.push_str(&Text::StandupDigestLink.format(locale, &[("link", &link)]));
+            }
+            lines.push(closed);
+        }
+    }
+    if !submitted.is_empty() {
+        lines.push(
+            Text::StandupPosted.format(locale, &[("users", &mention_list(locale, submitted))]),
+        );
+    }
+    if !pending.is_empty() {
+        lines.push(
+            Text::StandupPending.format(locale, &[("users", &mention_list(locale, pending))]),
+        );
+    }
+
+    let components = match session.is_open() {
+        true => vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(
+                ComponentId::StandupStart {
+                    session_id: session.id,
+                }
+                .encode(),
+            )
+            .style(ButtonStyle::Primary)
+            .label(Text::StandupButton.get(locale)),
+            CreateButton::new(
+                ComponentId::StandupClose {
+                    session_id: session.id,
+                }
+                .encode(),
+            )
+            .style(ButtonStyle::Secondary)
+            .label(Text::StandupCloseButton.get(locale)),
+        ])],
+        false => vec![],
+    };
+
+    (lines.join("\n"), components)
+}
+
+/// render the panel of a session which has just been started, before anyone has submitted to it.
+/// the pending members aren't listed, as finding them means fetching the members of the guild
+pub fn opening_panel(locale: Locale, session: &StandupSession) -> (String, Vec<CreateActionRow>) {
+    render_panel(locale, session, &[], &[])
+}
+
+/// render the panel of a session as it currently stands
+pub async fn panel(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    members: &MemberCache,
+    session: &StandupSession,
+) -> (String, Vec<CreateActionRow>) {
+    let locale = locale::guild_locale(&app_state.storage, session.guild_id).await;
+    let submissions = session_submissions(app_state, session.id).await;
+    let (submitted, pending) = progress(transport, app_state, members, session, &submissions).await;
+    render_panel(locale, session, &submitted, &pending)
+}
+
+/// edit the posted panel of a session, the panel lock must be held
+async fn update_panel(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    members: &MemberCache,
+    session: &StandupSession,
+) {
+    let message_id = match session.message_id {
+        Some(message_id) => message_id,
+        None => return,
+    };
+
+    let (content, components) = panel(transport, app_state, members, session).await;
+    if let Err(e) = transport
+        .edit_message(
+            session.channel_id,
+            message_id,
+            EditMessage::new()
+                .content(content)
+                .components(components)
+                .allowed_mentions(CreateAllowedMentions::new()),
+        )
+        .await
+    {
+        error!("unable to update panel of session {}: {}", session.id, e);
+    }
+}
+
+/// re-render the panel of a session after a submission to it has changed
+pub async fn refresh_panel(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    members: &MemberCache,
+    session_id: u64,
+) {
+    let _guard = app_state.panel_locks.lock(session_id).await;
+
+    let session = app_state
+        .storage
+        .read()
+        .await
+        .sessions
+        .get(&session_id)
+        .cloned();
+    if let Some(session) = session {
+        update_panel(transport, app_state, members, &session).await;
+    }
+}
+
+/// render the digest of a session, linking to each submission and listing who didn't submit
+fn render_digest(
+    locale: Locale,
+    session: &StandupSession,
+    submissions: &[Submission],
+    pending: &[u64],
+) -> String {
+    let mut lines = vec![Text::DigestTitle.format(
+        locale,
+        &[(
+            "date",
+            &discord_timestamp(session.started_at, TimestampStyle::LongDate),
+        )],
+    )];
+    if submissions.is_empty() {
+        lines.push(Text::DigestEmpty.get(locale).to_string());
+    }
+    for submission in submissions {
+        let answer = submission
+            .answer(THIS_WEEK_QUESTION)
+            .map(|a| neutralise(&shorten(a, 100)))
+            .unwrap_or_default();
+        let mut line = format!("- <@{}>: {}", submission.user_id, answer);
+        if let Some(message_id) = submission.message_id {
+            let link = message_link(submission.guild_id, submission.channel_id, message_id);
+            line.push_str(&format!(" · [↗](<{}>)", link));
+        }
+        lines.push(line);
+    }
+    if !pending.is_empty() {
+        lines.push(
+            Text::StandupPending.format(locale, &[("users", &mention_list(locale, pending))]),
+        );
+    }
+
+    let mut digest = String::new();
+    for line in lines {
+        if digest.chars().count() + line.chars().count() + 1 > MAX_MESSAGE_LENGTH {
+            break;
+        }
+        digest.push_str(&line);
+        digest.push('\n');
+    }
+    digest
+}
+
+/// close a session, posting its digest and switching its panel to the closed state
+///
+/// returns false if the session was already closed
+pub async fn close_session(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    session_id: u64,
+) -> Result<bool, String> {
+    let _guard = app_state.panel_locks.lock(session_id).await;
+
+    let mut session = match app_state
+        .storage
+        .read()
+        .await
+        .sessions
+        .get(&session_id)
+        .cloned()
+    {
+        Some(session) => session,
+        None => return Err(format!("tried to close unknown session {}", session_id)),
+    };
+    if !session.is_open() {
+        return Ok(false);
+    }
+
+    let locale = locale::guild_locale(&app_state.storage, session.guild_id).await;
+    let submissions = session_submissions(app_state, session.id).await;
+    let members = MemberCache::default();
+    let (_, pending) = progress(transport, app_state, &members, &session, &submissions).await;
+
+    session.closed_at = Some(Utc::now());
+    match transport
+        .send_message(
+            session.channel_id,
+            CreateMessage::new()
+                .content(render_digest(locale, &session, &submissions, &pending))
+                .allowed_mentions(CreateAllowedMentions::new()),
+        )
+        .await
+    {
+        Ok(message_id) => session.digest_message_id = Some(message_id),
+        Err(e) => error!("unable to post digest of session {}: {}", session.id, e),
+    }
+
+    let stored = session.clone();
+    app_state
+        .storage
+        .update(|data| data.sessions.insert(stored.id, stored))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    update_panel(transport, app_state, &members, &session).await;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::Value;
+
+    use crate::{
+        discord_bot::{
+            board::{refresh_board, MemberCache},
+            commands::fixtures::{self, CHANNEL_ID, GUILD_ID},
+            transport::{GuildMember, RecordingTransport, Sent},
+        },
+        locale::Locale,
+        standups::{BoardConfig, StandupSession, Submission},
+        state::AppState,
+    };
+
+    use super::{close_session, mention_list, progress, refresh_panel, render_panel, MAX_LISTED};
+
+    /// the role whose members are expected to submit in the tests which set one
+    const ROSTER_ROLE: u64 = 7;
+
+    /// an open session whose panel was posted as message 500
+    fn session(id: u64) -> StandupSession {
+        StandupSession {
+            id,
+            guild_id: GUILD_ID,
+            channel_id: CHANNEL_ID,
+            started_by: fixtures::USER_ID,
+            started_at: Utc::now(),
+            message_id: Some(500),
+            holiday: None,
+            closed_at: None,
+            digest_message_id: None,
+        }
+    }
+
+    /// a submission made by a user to a session
+    fn submission(id: u64, session_id: u64, user_id: u64) -> Submission {
+        Submission {
+            id,
+            guild_id: GUILD_ID,
+            channel_id: CHANNEL_ID,
+            message_id: Some(600 + id),
+            session_id: Some(session_id),
+            user_id,
+            username: format!("user {}", user_id),
+            questions: vec![],
+            answers: vec![],
+            submitted_at: Utc::now(),
+            edited_at: None,
+            references: vec![],
+        }
+    }
+
+    fn member(user_id: u64, bot: bool, roles: &[u64]) -> GuildMember {
+        GuildMember {
+            user_id,
+            bot,
+            roles: roles.to_vec(),
+        }
+    }
+
+    /// store a session and its submissions, with a board using the roster role if one is provided
+    async fn store(app_state: &AppState, roster_role: Option<u64>, submissions: Vec<Submission>) {
+        app_state
+            .storage
+            .update(|data| {
+                data.guilds.entry(GUILD_ID).or_default().board = Some(BoardConfig {
+                    channel_id: CHANNEL_ID,
+                    message_id: None,
+                    roster_role,
+                });
+                data.sessions.insert(1, session(1));
+                for submission in submissions {
+                    data.submissions.insert(submission.id, submission);
+                }
+            })
+            .await
+            .unwrap();
+    }
+
+    /// the buttons of each row of rendered components
+    fn buttons(components: &impl serde::Serialize) -> Vec<usize> {
+        match serde_json::to_value(components).unwrap() {
+            Value::Array(rows) => rows
+                .iter()
+                .map(|row| row["components"].as_array().map_or(0, Vec::len))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn members_past_the_limit_are_counted() {
+        let ids: Vec<u64> = (0..MAX_LISTED as u64 + 2).collect();
+        let list = mention_list(Locale::English, &ids);
+
+        assert!(list.starts_with("<@0>, <@1>"));
+        assert!(list.contains(&format!("<@{}>", MAX_LISTED - 1)));
+        assert!(!list.contains(&format!("<@{}>", MAX_LISTED)));
+        assert!(list.ends_with("and 2 more"));
+    }
+
+    #[test]
+    fn an_open_panel_shows_progress_and_buttons() {
+        let (content, components) = render_panel(Locale::English, &session(1), &[1], &[2, 3]);
+
+        assert!(content.contains("**1/3 submitted**"));
+        assert!(content.contains("✅ <@1>"));
+        assert!(content.contains("⏳ <@2>, <@3>"));
+        assert_eq!(buttons(&components), vec![2]);
+    }
+
+    #[test]
+    fn a_closed_panel_links_to_the_digest_without_buttons() {
+        let session = StandupSession {
+            closed_at: Some(Utc::now()),
+            digest_message_id: Some(700),
+            ..session(1)
+        };
+        let (content, components) = render_panel(Locale::English, &session, &[1], &[]);
+
+        assert!(content.contains("This standup is closed, 1/1 submitted."));
+        assert!(content.contains("(<https://discord.com/channels/10/20/700>)"));
+        assert!(buttons(&components).is_empty());
+    }
+
+    #[tokio::test]
+    async fn the_roster_role_decides_who_is_pending() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, Some(ROSTER_ROLE), vec![submission(1, 1, 1)]).await;
+        transport.set_members(
+            GUILD_ID,
+            vec![
+                member(1, false, &[ROSTER_ROLE]),
+                member(2, false, &[ROSTER_ROLE]),
+                member(3, true, &[ROSTER_ROLE]),
+                member(4, false, &[]),
+            ],
+        );
+
+        let submissions = vec![submission(1, 1, 1)];
+        let members = MemberCache::default();
+        let progress = progress(&transport, &app_state, &members, &session(1), &submissions).await;
+
+        assert_eq!(progress, (vec![1], vec![2]));
+    }
+
+    #[tokio::test]
+    async fn without_a_roster_role_previous_submitters_are_pending() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        let submissions = vec![submission(1, 1, 1), submission(2, 0, 2)];
+        store(&app_state, None, submissions.clone()).await;
+
+        let members = MemberCache::default();
+        let progress = progress(
+            &transport,
+            &app_state,
+            &members,
+            &session(1),
+            &submissions[..1],
+        )
+        .await;
+
+        assert_eq!(progress, (vec![1], vec![2]));
+        assert_eq!(transport.member_fetches(), 0);
+    }
+
+    #[tokio::test]
+    async fn the_board_and_panel_share_one_fetch_of_the_members() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, Some(ROSTER_ROLE), vec![submission(1, 1, 1)]).await;
+        transport.set_members(GUILD_ID, vec![member(1, false, &[ROSTER_ROLE])]);
+
+        let members = MemberCache::default();
+        refresh_board(&transport, &app_state, &members, GUILD_ID).await;
+        refresh_panel(&transport, &app_state, &members, 1).await;
+        assert_eq!(transport.member_fetches(), 1);
+
+        refresh_panel(&transport, &app_state, &MemberCache::default(), 1).await;
+        assert_eq!(transport.member_fetches(), 2);
+
+        let panel = transport
+            .sent()
+            .into_iter()
+            .rev()
+            .find_map(|sent| match sent {
+                Sent::Edit {
+                    message_id: 500,
+                    body,
+                    ..
+                } => Some(body),
+                _ => None,
+            });
+        assert!(panel.unwrap()["content"]
+            .as_str()
+            .unwrap()
+            .contains("**1/1 submitted**"));
+    }
+
+    #[tokio::test]
+    async fn closing_a_session_posts_the_digest_once() {
+        let app_state = fixtures::app_state().await;
+        let transport = RecordingTransport::default();
+        store(&app_state, Some(ROSTER_ROLE), vec![submission(1, 1, 1)]).await;
+        transport.set_members(
+            GUILD_ID,
+            vec![
+                member(1, false, &[ROSTER_ROLE]),
+                member(2, false, &[ROSTER_ROLE]),
+            ],
+        );
+
+        assert_eq!(close_session(&transport, &app_state, 1).await, Ok(true));
+        assert_eq!(transport.member_fetches(), 1);
+
+        let sent = transport.sent();
+        let digest_id = match &sent[0] {
+            Sent::Message {
+                channel_id: CHANNEL_ID,
+                message_id,
+                body,
+            } => {
+                let digest = body["content"].as_str().unwrap();
+                assert!(digest.starts_with("**Standup digest for "));
+                assert!(digest.contains("- <@1>: "));
+                assert!(digest.contains("⏳ <@2>"));
+                *message_id
+            }
+            other => panic!("expected the digest to be posted, found {:?}", other),
+        };
+        assert!(matches!(
+            &sent[1],
+            Sent::Edit { message_id: 500, body, .. }
+                if body["content"].as_str().unwrap().contains("This standup is closed, 1/2 submitted.")
+        ));
+
+        let session = app_state.storage.read().await.sessions[&1].clone();
+        assert!(session.closed_at.is_some());
+        assert_eq!(session.digest_message_id, Some(digest_id));
+
+        assert_eq!(close_session(&transport, &app_state, 1).await, Ok(false));
+        assert_eq!(transport.sent().len(), 2);
+    }
+}
@@ -0,0 +1,243 @@
+//! Registering the commands of the bot with discord, either globally or in each guild.
+//! The definitions last registered are hashed, so commands are only registered again once they change.
+
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use serenity::builder::CreateCommand;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    custom_commands::custom_commands,
+    modules::{disabled_modules, Module},
+    state::AppState,
+};
+
+use super::{
+    commands::{application_command, custom_application_commands},
+    transport::{Transport, TransportError},
+};
+
+/// the environment variable choosing how commands are registered
+const MODE_VAR: &str = "COMMAND_REGISTRATION";
+
+/// the time waited before retrying a failed registration, doubled after each failure
+const INITIAL_RETRY: Duration = Duration::from_secs(10);
+
+/// the longest time waited between retries
+const MAX_RETRY: Duration = Duration::from_secs(600);
+
+/// where the commands of the bot are registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationMode {
+    /// registered once for every guild, and in direct messages, changes may take a while to appear
+    Global,
+    /// registered separately in each guild, changes appear immediately
+    Guild,
+}
+
+impl RegistrationMode {
+    /// read the mode from `COMMAND_REGISTRATION`, registering in each guild unless it is `global`
+    pub fn from_env() -> Self {
+        match std::env::var(MODE_VAR).as_deref() {
+            Ok("global") => Self::Global,
+            Ok("guild") | Err(_) => Self::Guild,
+            Ok(other) => {
+                warn!(
+                    "unknown {} {:?}, registering commands in each guild",
+                    MODE_VAR, other
+                );
+                Self::Guild
+            }
+        }
+    }
+}
+
+/// hash a set of command definitions, so a change to any of them can be detected
+fn hash_commands(commands: &[CreateCommand]) -> String {
+    // converted to a value first, as its maps are sorted by key, so the hash doesn't depend on the order of a hashmap
+    let value = serde_json::to_value(commands).unwrap_or_default();
+    hex::encode(Sha256::digest(value.to_string().as_bytes()))
+}
+
+/// overwrite a set of commands, if `retry` is set, until discord accepts them, waiting longer after each failure.
+/// gives up if discord rejects the commands, as sending them again won't succeed
+async fn overwrite<F, Fut>(target: &str, retry: bool, mut set: F) -> Result<(), TransportError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(), TransportError>>,
+{
+    let mut wait = INITIAL_RETRY;
+    loop {
+        match set().await {
+            Ok(()) => return Ok(()),
+            Err(e @ TransportError::Rejected(_)) => {
+                error!("discord rejected the commands for {}: {}", target, e);
+                return Err(e);
+            }
+            Err(e) if !retry => {
+                warn!("failed to register commands for {}: {}", target, e);
+                return Err(e);
+            }
+            Err(e) => {
+                error!(
+                    "failed to register commands for {}, retrying in {}s: {}",
+                    target,
+                    wait.as_secs(),
+                    e
+                );
+                tokio::time::sleep(wait).await;
+                wait = (wait * 2).min(MAX_RETRY);
+            }
+        }
+    }
+}
+
+/// register the commands globally if they have changed, or if registering in each guild,
+/// remove the stale global commands left from when they were registered globally
+pub async fn sync_global(transport: &dyn Transport, app_state: &AppState) {
+    let registered = app_state.storage.read().await.command_hashes.global.clone();
+
+    // modules are disabled per guild, which global commands can't reflect, so all of them are registered
+    let commands = match RegistrationMode::from_env() {
+        RegistrationMode::Global => application_command(&[]),
+        RegistrationMode::Guild => vec![],
+    };
+    // as with guilds, a missing hash is unknown, so the global commands are cleared once even if none were stored
+    let hash = hash_commands(&commands);
+    if registered.as_ref() == Some(&hash) {
+        debug!("global commands are unchanged, not registering them");
+        return;
+    }
+
+    match commands.is_empty() {
+        true => info!("removing stale global commands"),
+        false => info!("registering commands globally"),
+    }
+    if overwrite("global", true, || {
+        transport.set_global_commands(commands.clone())
+    })
+    .await
+    .is_err()
+    {
+        return;
+    }
+
+    if let Err(e) = app_state
+        .storage
+        .update(|data| data.command_hashes.global = Some(hash))
+        .await
+    {
+        error!("failed to store the hash of the global commands: {}", e);
+    }
+}
+
+/// ask the handler of a guild to register its commands again in the background, once they have changed
+pub fn request_sync(app_state: &AppState, guild_id: u64) {
+    // there is no handler listening while the bot is starting, or in tests, and each guild is synced once it starts
+    let _ = app_state.command_syncs.send(guild_id);
+}
+
+/// register the commands of the modules enabled in a guild and its custom commands if they have changed, until discord accepts them.
+/// if registering globally, only the custom commands are registered in the guild, removing any stale built in commands.
+/// fails if discord rejects the commands, in which case the stored hash is left as it was
+pub async fn sync_guild(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    guild_id: u64,
+) -> Result<(), TransportError> {
+    register_guild(transport, app_state, guild_id, true).await
+}
+
+/// register the commands of a guild if they have changed, trying only once, for a change a member is waiting on
+pub async fn try_sync_guild(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    guild_id: u64,
+) -> Result<(), TransportError> {
+    register_guild(transport, app_state, guild_id, false).await
+}
+
+/// register the commands of a guild if they have changed, retrying failures other than rejections if `retry` is set
+async fn register_guild(
+    transport: &dyn Transport,
+    app_state: &AppState,
+    guild_id: u64,
+    retry: bool,
+) -> Result<(), TransportError> {
+    // syncs of a guild are run one at a time, so the stored hash is always that of the last commands registered
+    let _guard = app_state.registration_locks.lock(guild_id).await;
+
+    let registered = app_state
+        .storage
+        .read()
+        .await
+        .command_hashes
+        .guilds
+        .get(&guild_id)
+        .cloned();
+
+    let disabled = disabled_modules(&app_state.storage, Some(guild_id)).await;
+    let mut commands = match RegistrationMode::from_env() {
+        RegistrationMode::Guild => application_command(&disabled),
+        RegistrationMode::Global => vec![],
+    };
+    if !disabled.contains(&Module::Utility) {
+        let custom = custom_commands(&app_state.storage, guild_id).await;
+        commands.extend(custom_application_commands(&custom));
+    }
+
+    // a guild without a stored hash may still have commands from before they were hashed, so it is always registered,
+    // and an empty set of commands is hashed too, so the stale commands are only removed once
+    let hash = hash_commands(&commands);
+    if registered.as_ref() == Some(&hash) {
+        debug!(
+            "commands for guild {} are unchanged, not registering them",
+            guild_id
+        );
+        return Ok(());
+    }
+
+    match commands.is_empty() {
+        true => info!("removing stale commands from guild {}", guild_id),
+        false => info!("registering commands for guild {}", guild_id),
+    }
+    overwrite(&format!("guild {}", guild_id), retry, || {
+        transport.set_guild_commands(guild_id, commands.clone())
+    })
+    .await?;
+
+    if let Err(e) = app_state
+        .storage
+        .update(|data| {
+            data.command_hashes.guilds.insert(guild_id, hash);
+        })
+        .await
+    {
+        error!(
+            "failed to store the hash of the commands for guild {}: {}",
+            guild_id, e
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn rejected_commands_are_not_retried() {
+        let attempts = AtomicUsize::new(0);
+        let res = overwrite("guild 1", true, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(TransportError::Rejected(String::from("invalid name"))) }
+        })
+        .await;
+
+        assert!(matches!(res, Err(TransportError::Rejected(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
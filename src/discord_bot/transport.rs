@@ -0,0 +1,701 @@
+//! The outbound operations the bot performs on discord, behind a trait so handlers can be run without a connection.
+//! A serenity [Context] performs them against discord, while tests use a [RecordingTransport] which records them.
+
+use std::fmt::Display;
+
+use serde_json::Value;
+use serenity::{
+    all::{Command, CommandInteraction, ComponentInteraction, ModalInteraction},
+    async_trait,
+    builder::{CreateCommand, CreateInteractionResponse, CreateMessage, EditMessage},
+    client::Context,
+    model::id::{ChannelId, GuildId, InteractionId, MessageId, UserId},
+};
+
+/// the most members discord returns in a single request
+const MEMBER_PAGE_SIZE: u64 = 1000;
+
+/// why an operation on discord failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    /// the message, channel or interaction no longer exists
+    NotFound,
+    /// discord refused the request itself, so sending it again won't succeed
+    Rejected(String),
+    /// any other failure, described by discord or the http client
+    Other(String),
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::NotFound => write!(f, "not found"),
+            TransportError::Rejected(e) => write!(f, "rejected: {}", e),
+            TransportError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<serenity::Error> for TransportError {
+    fn from(e: serenity::Error) -> Self {
+        match &e {
+            serenity::Error::Http(http)
+                if http.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                TransportError::NotFound
+            }
+            // rate limits are client errors too, but succeed once the limit resets
+            serenity::Error::Http(http)
+                if http.status_code().map_or(false, |status| {
+                    status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                }) =>
+            {
+                TransportError::Rejected(e.to_string())
+            }
+            _ => TransportError::Other(e.to_string()),
+        }
+    }
+}
+
+/// the interaction a response is sent to
+#[derive(Debug, Clone, Copy)]
+pub struct InteractionRef<'a> {
+    pub id: u64,
+    pub token: &'a str,
+}
+
+impl<'a> From<&'a CommandInteraction> for InteractionRef<'a> {
+    fn from(interaction: &'a CommandInteraction) -> Self {
+        Self {
+            id: interaction.id.into(),
+            token: &interaction.token,
+        }
+    }
+}
+
+impl<'a> From<&'a ComponentInteraction> for InteractionRef<'a> {
+    fn from(interaction: &'a ComponentInteraction) -> Self {
+        Self {
+            id: interaction.id.into(),
+            token: &interaction.token,
+        }
+    }
+}
+
+impl<'a> From<&'a ModalInteraction> for InteractionRef<'a> {
+    fn from(interaction: &'a ModalInteraction) -> Self {
+        Self {
+            id: interaction.id.into(),
+            token: &interaction.token,
+        }
+    }
+}
+
+/// the parts of a guild member the bot looks at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildMember {
+    pub user_id: u64,
+    pub bot: bool,
+    pub roles: Vec<u64>,
+}
+
+/// the operations the bot performs on discord, returning the id of any message created
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// respond to an interaction
+    async fn respond(
+        &self,
+        interaction: InteractionRef<'_>,
+        response: CreateInteractionResponse,
+    ) -> Result<(), TransportError>;
+
+    /// get the id of the message sent in response to an interaction
+    async fn response_message(
+        &self,
+        interaction: InteractionRef<'_>,
+    ) -> Result<u64, TransportError>;
+
+    /// replace the response to an interaction, such as one which was deferred.
+    /// takes the body of a message, which a response, an edit and a followup share
+    async fn edit_response(
+        &self,
+        interaction: InteractionRef<'_>,
+        body: &Value,
+    ) -> Result<(), TransportError>;
+
+    /// delete the response to an interaction
+    async fn delete_response(&self, interaction: InteractionRef<'_>) -> Result<(), TransportError>;
+
+    /// send a message following up an interaction which has already been responded to
+    async fn followup(
+        &self,
+        interaction: InteractionRef<'_>,
+        body: &Value,
+    ) -> Result<u64, TransportError>;
+
+    /// send a message to a channel
+    async fn send_message(
+        &self,
+        channel_id: u64,
+        message: CreateMessage,
+    ) -> Result<u64, TransportError>;
+
+    /// edit a message sent by the bot
+    async fn edit_message(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        message: EditMessage,
+    ) -> Result<(), TransportError>;
+
+    /// check if a message was sent by the bot
+    async fn is_own_message(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+    ) -> Result<bool, TransportError>;
+
+    /// delete a message
+    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<(), TransportError>;
+
+    /// pin a message in its channel
+    async fn pin_message(&self, channel_id: u64, message_id: u64) -> Result<(), TransportError>;
+
+    /// send a direct message to a user
+    async fn direct_message(
+        &self,
+        user_id: u64,
+        message: CreateMessage,
+    ) -> Result<u64, TransportError>;
+
+    /// fetch the members of a guild
+    async fn members(&self, guild_id: u64) -> Result<Vec<GuildMember>, TransportError>;
+
+    /// replace the commands registered in a guild
+    async fn set_guild_commands(
+        &self,
+        guild_id: u64,
+        commands: Vec<CreateCommand>,
+    ) -> Result<(), TransportError>;
+
+    /// replace the commands registered globally
+    async fn set_global_commands(&self, commands: Vec<CreateCommand>)
+        -> Result<(), TransportError>;
+}
+
+#[async_trait]
+impl Transport for Context {
+    async fn respond(
+        &self,
+        interaction: InteractionRef<'_>,
+        response: CreateInteractionResponse,
+    ) -> Result<(), TransportError> {
+        self.http
+            .create_interaction_response(
+                InteractionId::new(interaction.id),
+                interaction.token,
+                &response,
+                vec![],
+            )
+            .await
+            .map_err(TransportError::from)
+    }
+
+    async fn response_message(
+        &self,
+        interaction: InteractionRef<'_>,
+    ) -> Result<u64, TransportError> {
+        let message = self
+            .http
+            .get_original_interaction_response(interaction.token)
+            .await?;
+        Ok(message.id.into())
+    }
+
+    async fn edit_response(
+        &self,
+        interaction: InteractionRef<'_>,
+        body: &Value,
+    ) -> Result<(), TransportError> {
+        self.http
+            .edit_original_interaction_response(interaction.token, body, vec![])
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_response(&self, interaction: InteractionRef<'_>) -> Result<(), TransportError> {
+        self.http
+            .delete_original_interaction_response(interaction.token)
+            .await
+            .map_err(TransportError::from)
+    }
+
+    async fn followup(
+        &self,
+        interaction: InteractionRef<'_>,
+        body: &Value,
+    ) -> Result<u64, TransportError> {
+        let message = self
+            .http
+            .create_followup_message(interaction.token, body, vec![])
+            .await?;
+        Ok(message.id.into())
+    }
+
+    async fn send_message(
+        &self,
+        channel_id: u64,
+        message: CreateMessage,
+    ) -> Result<u64, TransportError> {
+        let message = ChannelId::new(channel_id)
+            .send_message(self, message)
+            .await?;
+        Ok(message.id.into())
+    }
+
+    async fn edit_message(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        message: EditMessage,
+    ) -> Result<(), TransportError> {
+        ChannelId::new(channel_id)
+            .edit_message(self, MessageId::new(message_id), message)
+            .await?;
+        Ok(())
+    }
+
+    async fn is_own_message(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+    ) -> Result<bool, TransportError> {
+        let message = ChannelId::new(channel_id)
+            .message(self, MessageId::new(message_id))
+            .await?;
+        Ok(message.author.id == self.cache.current_user().id)
+    }
+
+    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<(), TransportError> {
+        ChannelId::new(channel_id)
+            .delete_message(self, MessageId::new(message_id))
+            .await
+            .map_err(TransportError::from)
+    }
+
+    async fn pin_message(&self, channel_id: u64, message_id: u64) -> Result<(), TransportError> {
+        ChannelId::new(channel_id)
+            .pin(self, MessageId::new(message_id))
+            .await
+            .map_err(TransportError::from)
+    }
+
+    async fn direct_message(
+        &self,
+        user_id: u64,
+        message: CreateMessage,
+    ) -> Result<u64, TransportError> {
+        let message = UserId::new(user_id).direct_message(self, message).await?;
+        Ok(message.id.into())
+    }
+
+    async fn members(&self, guild_id: u64) -> Result<Vec<GuildMember>, TransportError> {
+        // discord returns at most a page of members at a time, so keep asking for those after the last one
+        let mut members = vec![];
+        let mut after: Option<UserId> = None;
+        loop {
+            let page = GuildId::new(guild_id)
+                .members(self, Some(MEMBER_PAGE_SIZE), after)
+                .await?;
+            let full = page.len() as u64 == MEMBER_PAGE_SIZE;
+            after = page.last().map(|m| m.user.id);
+            members.extend(page.into_iter().map(|m| GuildMember {
+                user_id: m.user.id.into(),
+                bot: m.user.bot,
+                roles: m.roles.iter().map(|r| u64::from(*r)).collect(),
+            }));
+            if !full || after.is_none() {
+                return Ok(members);
+            }
+        }
+    }
+
+    async fn set_guild_commands(
+        &self,
+        guild_id: u64,
+        commands: Vec<CreateCommand>,
+    ) -> Result<(), TransportError> {
+        GuildId::new(guild_id)
+            .set_application_commands(self, commands)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_global_commands(
+        &self,
+        commands: Vec<CreateCommand>,
+    ) -> Result<(), TransportError> {
+        Command::set_global_application_commands(self, commands).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub use fake::{RecordingTransport, Sent};
+
+/// an in-memory transport for tests, which records everything sent instead of sending it
+#[cfg(test)]
+mod fake {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Mutex,
+        },
+    };
+
+    use serde_json::Value;
+    use serenity::{
+        async_trait,
+        builder::{CreateCommand, CreateInteractionResponse, CreateMessage, EditMessage},
+    };
+
+    use super::{GuildMember, InteractionRef, Transport, TransportError};
+
+    /// the first id given to a message created by the fake
+    const FIRST_MESSAGE_ID: u64 = 1000;
+
+    /// an operation recorded by the fake, with the body it would have sent to discord
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Sent {
+        Response {
+            interaction_id: u64,
+            body: Value,
+        },
+        ResponseEdit {
+            interaction_id: u64,
+            body: Value,
+        },
+        ResponseDelete {
+            interaction_id: u64,
+        },
+        Followup {
+            interaction_id: u64,
+            message_id: u64,
+            body: Value,
+        },
+        Message {
+            channel_id: u64,
+            message_id: u64,
+            body: Value,
+        },
+        Edit {
+            channel_id: u64,
+            message_id: u64,
+            body: Value,
+        },
+        Delete {
+            channel_id: u64,
+            message_id: u64,
+        },
+        Pin {
+            channel_id: u64,
+            message_id: u64,
+        },
+        DirectMessage {
+            user_id: u64,
+            message_id: u64,
+            body: Value,
+        },
+        GuildCommands {
+            guild_id: u64,
+            commands: Value,
+        },
+        GlobalCommands {
+            commands: Value,
+        },
+    }
+
+    /// a transport which records what it is asked to send, giving each created message a new id
+    #[derive(Debug)]
+    pub struct RecordingTransport {
+        sent: Mutex<Vec<Sent>>,
+        /// the message created by the response to each interaction
+        responses: Mutex<HashMap<u64, u64>>,
+        members: Mutex<HashMap<u64, Vec<GuildMember>>>,
+        /// the number of times the members of a guild have been fetched
+        member_fetches: AtomicU64,
+        /// messages which were deleted on discord, so editing them fails as not found
+        deleted: Mutex<HashSet<u64>>,
+        next_id: AtomicU64,
+        /// whether every operation should fail, as if discord were unreachable
+        failing: AtomicBool,
+        /// whether every operation should be rejected, as if discord refused what was sent
+        rejecting: AtomicBool,
+    }
+
+    impl Default for RecordingTransport {
+        fn default() -> Self {
+            Self {
+                sent: Mutex::default(),
+                responses: Mutex::default(),
+                members: Mutex::default(),
+                member_fetches: AtomicU64::new(0),
+                deleted: Mutex::default(),
+                next_id: AtomicU64::new(FIRST_MESSAGE_ID),
+                failing: AtomicBool::new(false),
+                rejecting: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl RecordingTransport {
+        /// everything sent so far, in order
+        pub fn sent(&self) -> Vec<Sent> {
+            self.sent.lock().unwrap().clone()
+        }
+
+        /// the bodies of the responses sent to interactions, in order
+        pub fn responses(&self) -> Vec<Value> {
+            self.sent()
+                .into_iter()
+                .filter_map(|s| match s {
+                    Sent::Response { body, .. } => Some(body),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// set the members returned for a guild
+        pub fn set_members(&self, guild_id: u64, members: Vec<GuildMember>) {
+            self.members.lock().unwrap().insert(guild_id, members);
+        }
+
+        /// the number of times the members of any guild have been fetched
+        pub fn member_fetches(&self) -> u64 {
+            self.member_fetches.load(Ordering::SeqCst)
+        }
+
+        /// delete a message as if someone else had, so editing it fails as not found
+        pub fn delete(&self, message_id: u64) {
+            self.deleted.lock().unwrap().insert(message_id);
+        }
+
+        /// make every following operation fail
+        pub fn fail(&self) {
+            self.failing.store(true, Ordering::SeqCst);
+        }
+
+        /// make every following operation be rejected
+        pub fn reject(&self) {
+            self.rejecting.store(true, Ordering::SeqCst);
+        }
+
+        /// record an operation, or fail it if the transport is failing or rejecting
+        fn record(&self, sent: Sent) -> Result<(), TransportError> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(TransportError::Other(String::from("transport is failing")));
+            }
+            if self.rejecting.load(Ordering::SeqCst) {
+                return Err(TransportError::Rejected(String::from(
+                    "transport is rejecting",
+                )));
+            }
+            self.sent.lock().unwrap().push(sent);
+            Ok(())
+        }
+
+        fn next_id(&self) -> u64 {
+            self.next_id.fetch_add(1, Ordering::SeqCst)
+        }
+    }
+
+    /// serialise a builder into the body discord would receive
+    fn body(builder: &impl serde::Serialize) -> Value {
+        serde_json::to_value(builder).unwrap_or_default()
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        async fn respond(
+            &self,
+            interaction: InteractionRef<'_>,
+            response: CreateInteractionResponse,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::Response {
+                interaction_id: interaction.id,
+                body: body(&response),
+            })?;
+            let message_id = self.next_id();
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(interaction.id, message_id);
+            Ok(())
+        }
+
+        async fn response_message(
+            &self,
+            interaction: InteractionRef<'_>,
+        ) -> Result<u64, TransportError> {
+            self.responses
+                .lock()
+                .unwrap()
+                .get(&interaction.id)
+                .copied()
+                .ok_or(TransportError::NotFound)
+        }
+
+        async fn edit_response(
+            &self,
+            interaction: InteractionRef<'_>,
+            body: &Value,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::ResponseEdit {
+                interaction_id: interaction.id,
+                body: body.clone(),
+            })
+        }
+
+        async fn delete_response(
+            &self,
+            interaction: InteractionRef<'_>,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::ResponseDelete {
+                interaction_id: interaction.id,
+            })
+        }
+
+        async fn followup(
+            &self,
+            interaction: InteractionRef<'_>,
+            body: &Value,
+        ) -> Result<u64, TransportError> {
+            let message_id = self.next_id();
+            self.record(Sent::Followup {
+                interaction_id: interaction.id,
+                message_id,
+                body: body.clone(),
+            })?;
+            Ok(message_id)
+        }
+
+        async fn send_message(
+            &self,
+            channel_id: u64,
+            message: CreateMessage,
+        ) -> Result<u64, TransportError> {
+            let message_id = self.next_id();
+            self.record(Sent::Message {
+                channel_id,
+                message_id,
+                body: body(&message),
+            })?;
+            Ok(message_id)
+        }
+
+        async fn edit_message(
+            &self,
+            channel_id: u64,
+            message_id: u64,
+            message: EditMessage,
+        ) -> Result<(), TransportError> {
+            if self.deleted.lock().unwrap().contains(&message_id) {
+                return Err(TransportError::NotFound);
+            }
+            self.record(Sent::Edit {
+                channel_id,
+                message_id,
+                body: body(&message),
+            })
+        }
+
+        async fn is_own_message(
+            &self,
+            channel_id: u64,
+            message_id: u64,
+        ) -> Result<bool, TransportError> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(TransportError::Other(String::from("transport is failing")));
+            }
+            // the fake only knows of the messages it sent, any other message doesn't exist
+            let sent = self.sent().into_iter().any(|s| {
+                matches!(s, Sent::Message { channel_id: c, message_id: m, .. } if c == channel_id && m == message_id)
+            });
+            match sent {
+                true => Ok(true),
+                false => Err(TransportError::NotFound),
+            }
+        }
+
+        async fn delete_message(
+            &self,
+            channel_id: u64,
+            message_id: u64,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::Delete {
+                channel_id,
+                message_id,
+            })
+        }
+
+        async fn pin_message(
+            &self,
+            channel_id: u64,
+            message_id: u64,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::Pin {
+                channel_id,
+                message_id,
+            })
+        }
+
+        async fn direct_message(
+            &self,
+            user_id: u64,
+            message: CreateMessage,
+        ) -> Result<u64, TransportError> {
+            let message_id = self.next_id();
+            self.record(Sent::DirectMessage {
+                user_id,
+                message_id,
+                body: body(&message),
+            })?;
+            Ok(message_id)
+        }
+
+        async fn members(&self, guild_id: u64) -> Result<Vec<GuildMember>, TransportError> {
+            self.member_fetches.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(TransportError::Other(String::from("transport is failing")));
+            }
+            Ok(self
+                .members
+                .lock()
+                .unwrap()
+                .get(&guild_id)
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        async fn set_guild_commands(
+            &self,
+            guild_id: u64,
+            commands: Vec<CreateCommand>,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::GuildCommands {
+                guild_id,
+                commands: body(&commands),
+            })
+        }
+
+        async fn set_global_commands(
+            &self,
+            commands: Vec<CreateCommand>,
+        ) -> Result<(), TransportError> {
+            self.record(Sent::GlobalCommands {
+                commands: body(&commands),
+            })
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Holidays on which standups should not be expected, either for a whole guild or for an individual user
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{storage::Storage, timezone::user_timezone};
+
+/// a holiday spanning one or more whole days
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Holiday {
+    /// the first day of the holiday
+    pub start: NaiveDate,
+    /// the last day of the holiday, inclusive
+    pub end: NaiveDate,
+    /// a human readable name for the holiday
+    pub name: String,
+}
+
+impl Holiday {
+    /// check if the provided date falls within this holiday
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+/// parse a date in the `YYYY-MM-DD` format used by the holiday commands
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+/// find the holiday a guild, or optionally a specific user in that guild, is observing on a date
+pub async fn holiday_on(
+    storage: &Storage,
+    guild_id: u64,
+    user_id: Option<u64>,
+    date: NaiveDate,
+) -> Option<Holiday> {
+    let data = storage.read().await;
+
+    let guild_holidays = data.guilds.get(&guild_id).map(|g| g.holidays.iter());
+    let user_holidays = user_id
+        .and_then(|id| data.users.get(&id))
+        .map(|u| u.holidays.iter());
+
+    guild_holidays
+        .into_iter()
+        .flatten()
+        .chain(user_holidays.into_iter().flatten())
+        .find(|h| h.contains(date))
+        .cloned()
+}
+
+/// check if a member of a guild is on holiday at an instant, taking the date in the member's own zone
+pub async fn member_on_holiday(
+    storage: &Storage,
+    guild_id: u64,
+    user_id: u64,
+    at: DateTime<Utc>,
+) -> bool {
+    let today = at
+        .with_timezone(&user_timezone(storage, user_id).await)
+        .date_naive();
+    holiday_on(storage, guild_id, Some(user_id), today)
+        .await
+        .is_some()
+}
+
+/// parse the all-day events out of an ICS calendar file.
+/// only `DTSTART`, `DTEND` and `SUMMARY` are read, which is all that public holiday calendars use
+pub fn parse_ics(raw: &str) -> Vec<Holiday> {
+    // lines starting with whitespace are continuations of the previous line
+    let mut lines: Vec<String> = vec![];
+    for line in raw.lines() {
+        match line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')) {
+            Some(continuation) => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(continuation);
+                }
+            }
+            None => lines.push(line.trim_end().to_string()),
+        }
+    }
+
+    let mut holidays = vec![];
+    let mut start = None;
+    let mut end = None;
+    let mut all_day = false;
+    let mut name = None;
+
+    for line in lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        // strip parameters, e.g. `DTSTART;VALUE=DATE`
+        let mut params = key.split(';');
+        let key = params.next().unwrap_or_default();
+
+        match key {
+            "BEGIN" if value == "VEVENT" => {
+                start = None;
+                end = None;
+                all_day = false;
+                name = None;
+            }
+            "DTSTART" => start = parse_ics_date(value),
+            "DTEND" => {
+                end = parse_ics_date(value);
+                all_day =
+                    params.any(|p| p.eq_ignore_ascii_case("VALUE=DATE")) || !value.contains('T');
+            }
+            "SUMMARY" => name = Some(value.replace("\\,", ",").replace("\\;", ";")),
+            "END" if value == "VEVENT" => {
+                if let Some(start) = start {
+                    // the end date of an all-day event is exclusive, the end of a timed event is within its last day
+                    let end = end
+                        .map(|end| match all_day {
+                            true => end - Duration::days(1),
+                            false => end,
+                        })
+                        .filter(|end| *end >= start)
+                        .unwrap_or(start);
+                    holidays.push(Holiday {
+                        start,
+                        end,
+                        name: name.take().unwrap_or_else(|| String::from("Holiday")),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    holidays
+}
+
+/// parse the date portion of an ICS date or date-time value, e.g. `20230206` or `20230206T000000Z`
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::parse_ics;
+
+    fn date(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn all_day_events_end_the_day_before_dtend() {
+        let holidays = parse_ics(
+            "BEGIN:VCALENDAR\r\n\
+             BEGIN:VEVENT\r\n\
+             DTSTART;VALUE=DATE:20231225\r\n\
+             DTEND;VALUE=DATE:20231227\r\n\
+             SUMMARY:Christmas\\, Boxing Day\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             DTSTART;VALUE=DATE:20240206\r\n\
+             DTEND;VALUE=DATE:20240207\r\n\
+             SUMMARY:Waitangi\r\n  \
+             Day\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n",
+        );
+
+        assert_eq!(holidays.len(), 2);
+        assert_eq!(holidays[0].start, date("2023-12-25"));
+        assert_eq!(holidays[0].end, date("2023-12-26"));
+        assert_eq!(holidays[0].name, "Christmas, Boxing Day");
+        assert_eq!(holidays[1].start, date("2024-02-06"));
+        assert_eq!(holidays[1].end, date("2024-02-06"));
+        assert_eq!(holidays[1].name, "Waitangi Day");
+    }
+
+    #[test]
+    fn timed_events_include_the_day_they_end() {
+        let holidays = parse_ics(
+            "BEGIN:VEVENT\n\
+             DTSTART:20240301T090000Z\n\
+             DTEND:20240302T170000Z\n\
+             SUMMARY:Offsite\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:20240305T090000Z\n\
+             END:VEVENT\n",
+        );
+
+        assert_eq!(holidays.len(), 2);
+        assert_eq!(holidays[0].start, date("2024-03-01"));
+        assert_eq!(holidays[0].end, date("2024-03-02"));
+        assert_eq!(holidays[1].start, date("2024-03-05"));
+        assert_eq!(holidays[1].end, date("2024-03-05"));
+        assert_eq!(holidays[1].name, "Holiday");
+    }
+}
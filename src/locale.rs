@@ -0,0 +1,1043 @@
+//! The message catalogue for the bot, every string which is shown to a user is defined here
+//! along with its translation into each supported locale.
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+/// a language which the bot is able to respond in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "mi")]
+    Maori,
+}
+
+impl Locale {
+    /// every locale supported by the bot
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::German, Locale::Maori];
+
+    /// the short code used to identify this locale
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::German => "de",
+            Locale::Maori => "mi",
+        }
+    }
+
+    /// the name of this locale, written in the language itself
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::German => "Deutsch",
+            Locale::Maori => "Te Reo Māori",
+        }
+    }
+
+    /// the discord locale codes which map onto this locale.
+    /// discord does not offer te reo Māori as a client language, so it can only be chosen explicitly
+    pub fn discord_codes(self) -> &'static [&'static str] {
+        match self {
+            Locale::English => &["en-US", "en-GB"],
+            Locale::German => &["de"],
+            Locale::Maori => &[],
+        }
+    }
+
+    /// parse either one of our own locale codes, or a discord locale code
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|locale| {
+            locale.code().eq_ignore_ascii_case(code)
+                || locale
+                    .discord_codes()
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(code))
+        })
+    }
+}
+
+/// find the locale to respond to a user in.
+/// a user's own preference is used first, then the default of the guild, then the locale of the users discord client
+pub async fn resolve(
+    storage: &Storage,
+    guild_id: Option<u64>,
+    user_id: u64,
+    interaction_locale: &str,
+) -> Locale {
+    let data = storage.read().await;
+
+    let user_locale = data.users.get(&user_id).and_then(|u| u.locale);
+    let guild_locale = guild_id
+        .and_then(|id| data.guilds.get(&id))
+        .and_then(|g| g.locale);
+
+    user_locale
+        .or(guild_locale)
+        .or_else(|| Locale::from_code(interaction_locale))
+        .unwrap_or_default()
+}
+
+/// find the locale to use for messages shown to a whole guild, rather than a specific user
+pub async fn guild_locale(storage: &Storage, guild_id: u64) -> Locale {
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .and_then(|g| g.locale)
+        .unwrap_or_default()
+}
+
+/// declare the messages in the catalogue, along with a list of every message so the tests can check each one
+macro_rules! catalogue {
+    ($(#[$meta:meta])* pub enum $name:ident { $($(#[$variant_meta:meta])* $variant:ident,)* }) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+        }
+
+        impl $name {
+            /// every message in the catalogue
+            #[cfg(test)]
+            pub const ALL: &'static [$name] = &[$($name::$variant),*];
+        }
+    };
+}
+
+catalogue! {
+/// a message which may be sent to a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    InternalError,
+    /// takes `{id}`
+    ErrorReference,
+    Pong,
+    StandupPrompt,
+    StandupButton,
+    StandupModalTitle,
+    StandupTooLong,
+    /// takes `{user}`
+    StandupSubmission,
+    SayFailed,
+    /// takes `{channel}`
+    SaySent,
+    /// takes `{id}` and `{time}`
+    SayScheduled,
+    SayEmpty,
+    SayInvalidColour,
+    SayInvalidTime,
+    SayTimeInPast,
+    SayInvalidLink,
+    SayNotOwnMessage,
+    SayMessageMissing,
+    SayEdited,
+    SayDeleted,
+    /// takes `{channel}`
+    SayChannelDenied,
+    /// takes `{size}`
+    SayAttachmentTooLarge,
+    /// takes `{count}`
+    SayScheduleLimit,
+    SayScheduledNone,
+    /// takes `{id}`
+    SayScheduledCancelled,
+    /// takes `{id}`
+    SayScheduledMissing,
+    /// takes `{channel}`
+    SayScheduledFailed,
+    /// takes `{user}` and `{channel}`
+    SayLogSent,
+    /// takes `{user}` and `{channel}`
+    SayLogScheduled,
+    /// takes `{user}` and `{channel}`
+    SayLogCancelled,
+    /// takes `{user}` and `{channel}`
+    SayLogEdited,
+    /// takes `{user}` and `{channel}`
+    SayLogDeleted,
+    SayLogNone,
+    /// takes `{page}` and `{pages}`
+    SayLogPage,
+    /// takes `{pages}`
+    SayLogPageMissing,
+    /// takes `{channel}`
+    SayAuditChannelSet,
+    SayAuditChannelCleared,
+    /// takes `{language}`
+    LanguageUserSet,
+    /// takes `{language}`
+    LanguageGuildSet,
+    ManageGuildRequired,
+    /// takes `{timezone}` and `{time}`
+    TimezoneSet,
+    /// takes `{timezone}`
+    TimezoneCleared,
+    /// takes `{timezone}`
+    TimezoneInvalid,
+    /// takes `{date}`
+    StandupWeekOf,
+    /// takes `{name}`
+    StandupHoliday,
+    StandupEditButton,
+    StandupDeleteButton,
+    StandupNotYours,
+    StandupMissing,
+    StandupSaveNotYours,
+    StandupSaveEmpty,
+    StandupDeleted,
+    /// takes `{submitted}` and `{total}`
+    StandupProgress,
+    /// takes `{users}`
+    StandupPosted,
+    /// takes `{users}`
+    StandupPending,
+    /// takes `{count}`
+    StandupMore,
+    StandupCloseButton,
+    /// takes `{submitted}` and `{total}`
+    StandupClosed,
+    /// takes `{link}`
+    StandupDigestLink,
+    StandupAlreadyClosed,
+    StandupCloseDenied,
+    StandupClosedReply,
+    /// takes `{date}`
+    DigestTitle,
+    DigestEmpty,
+    /// takes `{user}` and `{question}`
+    HistoryHeader,
+    /// takes `{user}`
+    HistoryNone,
+    /// takes `{count}`
+    HistoryUnknownQuestion,
+    /// takes `{count}`
+    ExportReady,
+    ExportNone,
+    /// takes `{option}`
+    OptionMissing,
+    /// takes `{option}` and `{bound}`
+    OptionTooShort,
+    /// takes `{option}` and `{bound}`
+    OptionTooLong,
+    /// takes `{option}` and `{bound}`
+    OptionTooSmall,
+    /// takes `{option}` and `{bound}`
+    OptionTooLarge,
+    /// takes `{capability}`
+    CapabilityMissing,
+    /// takes `{seconds}`
+    CooldownActive,
+    ComponentExpired,
+    /// takes `{role}` and `{capability}`
+    PermissionGranted,
+    /// takes `{role}` and `{capability}`
+    PermissionRevoked,
+    PermissionsNone,
+    UserMentionsAllowed,
+    UserMentionsBlocked,
+    /// takes `{module}`
+    ModuleUnavailable,
+    /// takes `{module}`
+    ModuleEnabled,
+    /// takes `{module}`
+    ModuleDisabled,
+    /// takes `{module}`
+    ModuleRequired,
+    ModuleStateEnabled,
+    ModuleStateDisabled,
+    /// takes `{name}`
+    CustomCommandCreated,
+    /// takes `{name}`
+    CustomCommandUpdated,
+    /// takes `{name}`
+    CustomCommandDeleted,
+    /// takes `{name}`
+    CustomCommandMissing,
+    /// takes `{name}`
+    CustomCommandExists,
+    /// takes `{name}`
+    CustomCommandReserved,
+    CustomCommandInvalidName,
+    /// takes `{name}`
+    CustomCommandRejected,
+    /// takes `{count}`
+    CustomCommandLimit,
+    CustomCommandsNone,
+    /// takes `{name}`
+    HolidayAdded,
+    /// takes `{date}`
+    HolidayRemoved,
+    /// takes `{count}`
+    HolidaysImported,
+    HolidayInvalidDate,
+    HolidayInvalidFile,
+    HolidaysGuildHeader,
+    HolidaysUserHeader,
+    HolidaysNone,
+    /// takes `{url}` and `{secret}`
+    WebhookSet,
+    WebhookCleared,
+    WebhookInvalidUrl,
+    WebhookNotConfigured,
+    /// takes `{status}`
+    WebhookTestSucceeded,
+    /// takes `{error}`
+    WebhookTestFailed,
+    /// takes `{pattern}`
+    LinkAdded,
+    /// takes `{pattern}`
+    LinkRemoved,
+    /// takes `{error}`
+    LinkInvalidPattern,
+    LinkInvalidUrl,
+    LinksNone,
+    BoardTitle,
+    BoardEmpty,
+    BoardNoUpdate,
+    /// takes `{date}`
+    BoardFinish,
+    BoardBlocked,
+    /// takes `{time}`
+    BoardUpdated,
+    /// takes `{channel}`
+    BoardSet,
+    BoardCleared,
+}
+}
+
+impl Text {
+    /// get the raw text of this message in the provided locale
+    pub fn get(self, locale: Locale) -> &'static str {
+        use Locale::*;
+        use Text::*;
+
+        match (self, locale) {
+            (InternalError, English) => "An internal error occurred.",
+            (InternalError, German) => "Ein interner Fehler ist aufgetreten.",
+            (InternalError, Maori) => "I puta he hapa ā-roto.",
+
+            (ErrorReference, English) => "If this keeps happening, let the bot maintainers know: `error {id}`",
+            (ErrorReference, German) => "Falls das weiterhin passiert, melde es den Bot-Verantwortlichen: `error {id}`",
+            (ErrorReference, Maori) => "Mēnā ka haere tonu tēnei, whakamōhiotia ngā kaitiaki o te bot: `error {id}`",
+
+            (Pong, _) => "Pong!",
+
+            (StandupPrompt, English) => "Click the button to start the standup",
+            (StandupPrompt, German) => "Klicke auf den Button, um das Standup zu starten",
+            (StandupPrompt, Maori) => "Pāwhiria te pātene kia tīmata ai te hui tū",
+
+            (StandupButton, English) => "Start Standup",
+            (StandupButton, German) => "Standup starten",
+            (StandupButton, Maori) => "Tīmatahia te Hui Tū",
+
+            (StandupModalTitle, English) => "Standups",
+            (StandupModalTitle, German) => "Standups",
+            (StandupModalTitle, Maori) => "Ngā Hui Tū",
+
+            (StandupTooLong, English) => "Your standup is too long, please shorten it",
+            (StandupTooLong, German) => "Dein Standup ist zu lang, bitte kürze es",
+            (StandupTooLong, Maori) => "He roa rawa tō hui tū, me whakapoto koa",
+
+            (StandupSubmission, English) => "**Standup Submission by {user}:**",
+            (StandupSubmission, German) => "**Standup von {user}:**",
+            (StandupSubmission, Maori) => "**Hui tū nā {user}:**",
+
+            (SayFailed, English) => "Failed to use /say due to error",
+            (SayFailed, German) => "/say konnte wegen eines Fehlers nicht ausgeführt werden",
+            (SayFailed, Maori) => "Kāore i taea te whakamahi i te /say nā tētahi hapa",
+
+            (SaySent, English) => "Sent your message to <#{channel}>.",
+            (SaySent, German) => "Deine Nachricht wurde in <#{channel}> gesendet.",
+            (SaySent, Maori) => "Kua tukuna tō karere ki <#{channel}>.",
+
+            (SayScheduled, English) => "Scheduled message `{id}` for {time}, cancel it with `/say cancel`.",
+            (SayScheduled, German) => "Nachricht `{id}` ist für {time} geplant, mit `/say cancel` kannst du sie abbrechen.",
+            (SayScheduled, Maori) => "Kua whakaritea te karere `{id}` mō {time}, whakakorea ki `/say cancel`.",
+
+            (SayEmpty, English) => "Provide some text, an embed title or body, or an attachment to send.",
+            (SayEmpty, German) => "Gib einen Text, einen Titel oder Inhalt für ein Embed oder einen Anhang an.",
+            (SayEmpty, Maori) => "Tukuna mai he kupu, he taitara, he tinana rānei mō te tāpiri, he kōnae rānei.",
+
+            (SayInvalidColour, English) => "Colours must be written as hex, e.g. #ff8800.",
+            (SayInvalidColour, German) => "Farben müssen hexadezimal angegeben werden, z. B. #ff8800.",
+            (SayInvalidColour, Maori) => "Me tuhi ngā tae hei hex, hei tauira #ff8800.",
+
+            (SayInvalidTime, English) => "Times must be written as YYYY-MM-DD HH:MM in your timezone, e.g. 2023-02-06 09:30.",
+            (SayInvalidTime, German) => "Zeiten müssen als JJJJ-MM-TT HH:MM in deiner Zeitzone angegeben werden, z. B. 2023-02-06 09:30.",
+            (SayInvalidTime, Maori) => "Me tuhi ngā wā hei TTTT-MM-RR HH:MM i tō rohe wā, hei tauira 2023-02-06 09:30.",
+
+            (SayTimeInPast, English) => "That time has already passed.",
+            (SayTimeInPast, German) => "Dieser Zeitpunkt ist bereits vergangen.",
+            (SayTimeInPast, Maori) => "Kua pahure kē taua wā.",
+
+            (SayInvalidLink, English) => "That is not a link to a message on this server.",
+            (SayInvalidLink, German) => "Das ist kein Link zu einer Nachricht auf diesem Server.",
+            (SayInvalidLink, Maori) => "Ehara tēnā i te hono ki tētahi karere i tēnei tūmau.",
+
+            (SayNotOwnMessage, English) => "I can only change messages sent with `/say`.",
+            (SayNotOwnMessage, German) => "Ich kann nur Nachrichten ändern, die mit `/say` gesendet wurden.",
+            (SayNotOwnMessage, Maori) => "Ka taea e au anake te whakarerekē i ngā karere i tukuna mā `/say`.",
+
+            (SayMessageMissing, English) => "That message no longer exists.",
+            (SayMessageMissing, German) => "Diese Nachricht existiert nicht mehr.",
+            (SayMessageMissing, Maori) => "Kua kore kē taua karere.",
+
+            (SayEdited, English) => "Edited the message.",
+            (SayEdited, German) => "Die Nachricht wurde bearbeitet.",
+            (SayEdited, Maori) => "Kua whakatikaina te karere.",
+
+            (SayDeleted, English) => "Deleted the message.",
+            (SayDeleted, German) => "Die Nachricht wurde gelöscht.",
+            (SayDeleted, Maori) => "Kua mukua te karere.",
+
+            (SayChannelDenied, English) => "You can't send messages in <#{channel}>.",
+            (SayChannelDenied, German) => "Du kannst in <#{channel}> keine Nachrichten senden.",
+            (SayChannelDenied, Maori) => "Kāore e taea e koe te tuku karere ki <#{channel}>.",
+
+            (SayAttachmentTooLarge, English) => "Attachments of scheduled messages can be at most {size} MB.",
+            (SayAttachmentTooLarge, German) => "Anhänge geplanter Nachrichten dürfen höchstens {size} MB groß sein.",
+            (SayAttachmentTooLarge, Maori) => "Ko te rahi mōrahi o ngā kōnae o ngā karere kua whakaritea he {size} MB.",
+
+            (SayScheduleLimit, English) => "This server already has {count} messages waiting to be sent, cancel one first.",
+            (SayScheduleLimit, German) => "Auf diesem Server warten bereits {count} Nachrichten auf den Versand, brich zuerst eine ab.",
+            (SayScheduleLimit, Maori) => "Kua {count} kē ngā karere e tatari ana kia tukuna, whakakorea tētahi i te tuatahi.",
+
+            (SayScheduledNone, English) => "No messages are waiting to be sent.",
+            (SayScheduledNone, German) => "Es warten keine Nachrichten auf den Versand.",
+            (SayScheduledNone, Maori) => "Kāore he karere e tatari ana kia tukuna.",
+
+            (SayScheduledCancelled, English) => "Cancelled scheduled message `{id}`.",
+            (SayScheduledCancelled, German) => "Die geplante Nachricht `{id}` wurde abgebrochen.",
+            (SayScheduledCancelled, Maori) => "Kua whakakorea te karere kua whakaritea `{id}`.",
+
+            (SayScheduledMissing, English) => "There is no scheduled message `{id}`.",
+            (SayScheduledMissing, German) => "Es gibt keine geplante Nachricht `{id}`.",
+            (SayScheduledMissing, Maori) => "Kāore he karere kua whakaritea `{id}`.",
+
+            (SayScheduledFailed, English) => "Your scheduled message could not be sent to <#{channel}>.",
+            (SayScheduledFailed, German) => "Deine geplante Nachricht konnte nicht in <#{channel}> gesendet werden.",
+            (SayScheduledFailed, Maori) => "Kāore i taea te tuku i tō karere kua whakaritea ki <#{channel}>.",
+
+            (SayLogSent, English) => "<@{user}> had the bot send a message in <#{channel}>",
+            (SayLogSent, German) => "<@{user}> ließ den Bot eine Nachricht in <#{channel}> senden",
+            (SayLogSent, Maori) => "I tukuna e <@{user}> he karere mā te bot ki <#{channel}>",
+
+            (SayLogScheduled, English) => "<@{user}> scheduled a message in <#{channel}>",
+            (SayLogScheduled, German) => "<@{user}> plante eine Nachricht in <#{channel}>",
+            (SayLogScheduled, Maori) => "I whakaritea e <@{user}> he karere ki <#{channel}>",
+
+            (SayLogCancelled, English) => "<@{user}> cancelled a scheduled message in <#{channel}>",
+            (SayLogCancelled, German) => "<@{user}> brach eine geplante Nachricht in <#{channel}> ab",
+            (SayLogCancelled, Maori) => "I whakakorea e <@{user}> he karere kua whakaritea ki <#{channel}>",
+
+            (SayLogEdited, English) => "<@{user}> edited a message of the bot in <#{channel}>",
+            (SayLogEdited, German) => "<@{user}> bearbeitete eine Nachricht des Bots in <#{channel}>",
+            (SayLogEdited, Maori) => "I whakatikaina e <@{user}> he karere a te bot ki <#{channel}>",
+
+            (SayLogDeleted, English) => "<@{user}> deleted a message of the bot in <#{channel}>",
+            (SayLogDeleted, German) => "<@{user}> löschte eine Nachricht des Bots in <#{channel}>",
+            (SayLogDeleted, Maori) => "I mukua e <@{user}> he karere a te bot ki <#{channel}>",
+
+            (SayLogNone, English) => "The bot hasn't sent any messages on anyone's behalf yet.",
+            (SayLogNone, German) => "Der Bot hat noch keine Nachrichten im Namen anderer gesendet.",
+            (SayLogNone, Maori) => "Kāore anō te bot kia tuku karere mō tētahi.",
+
+            (SayLogPage, English) => "Page {page} of {pages}",
+            (SayLogPage, German) => "Seite {page} von {pages}",
+            (SayLogPage, Maori) => "Whārangi {page} o {pages}",
+
+            (SayLogPageMissing, English) => "The log only has {pages} pages.",
+            (SayLogPageMissing, German) => "Das Protokoll hat nur {pages} Seiten.",
+            (SayLogPageMissing, Maori) => "E {pages} noa iho ngā whārangi o te rārangi.",
+
+            (SayAuditChannelSet, English) => "Every message sent with /say will now be logged in <#{channel}>.",
+            (SayAuditChannelSet, German) => "Jede mit /say gesendete Nachricht wird jetzt in <#{channel}> protokolliert.",
+            (SayAuditChannelSet, Maori) => "Ka tuhia ia karere i tukuna mā /say ki <#{channel}> ināianei.",
+
+            (SayAuditChannelCleared, English) => "Messages sent with /say will no longer be logged in a channel.",
+            (SayAuditChannelCleared, German) => "Mit /say gesendete Nachrichten werden nicht mehr in einem Kanal protokolliert.",
+            (SayAuditChannelCleared, Maori) => "Kāore e tuhia anō ngā karere i tukuna mā /say ki tētahi hongere.",
+
+            (LanguageUserSet, English) => "I will now respond to you in {language}.",
+            (LanguageUserSet, German) => "Ich antworte dir ab jetzt auf {language}.",
+            (LanguageUserSet, Maori) => "Ka kōrero au ki a koe i te {language} ināianei.",
+
+            (LanguageGuildSet, English) => "{language} is now the default language for this server.",
+            (LanguageGuildSet, German) => "{language} ist jetzt die Standardsprache dieses Servers.",
+            (LanguageGuildSet, Maori) => "Ko te {language} te reo taunoa mō tēnei tūmau ināianei.",
+
+            (ManageGuildRequired, English) => {
+                "You need the Manage Server permission to change the settings of this server."
+            }
+            (ManageGuildRequired, German) => {
+                "Du benötigst die Berechtigung „Server verwalten“, um die Einstellungen dieses Servers zu ändern."
+            }
+            (ManageGuildRequired, Maori) => {
+                "Me whai koe i te mana Manage Server kia huri ai i ngā tautuhinga o tēnei tūmau."
+            }
+
+            (TimezoneSet, English) => "Your timezone is now {timezone}, where it is currently {time}.",
+            (TimezoneSet, German) => "Deine Zeitzone ist jetzt {timezone}, dort ist es gerade {time}.",
+            (TimezoneSet, Maori) => "Ko {timezone} tō rohe wā ināianei, ko {time} te wā i reira.",
+
+            (TimezoneCleared, English) => "Your timezone has been cleared, {timezone} will be used instead.",
+            (TimezoneCleared, German) => "Deine Zeitzone wurde entfernt, stattdessen wird {timezone} verwendet.",
+            (TimezoneCleared, Maori) => "Kua mukua tō rohe wā, ka whakamahia a {timezone}.",
+
+            (TimezoneInvalid, English) => "{timezone} is not a known timezone, try a name such as Pacific/Auckland.",
+            (TimezoneInvalid, German) => "{timezone} ist keine bekannte Zeitzone, versuche einen Namen wie Europe/Berlin.",
+            (TimezoneInvalid, Maori) => "Kāore i te mōhiotia a {timezone}, whakamātauria he ingoa pēnei i Pacific/Auckland.",
+
+            (StandupWeekOf, English) => "Week of {date}",
+            (StandupWeekOf, German) => "Woche vom {date}",
+            (StandupWeekOf, Maori) => "Te wiki o {date}",
+
+            (StandupHoliday, English) => "Today is {name}, so this standup is optional.",
+            (StandupHoliday, German) => "Heute ist {name}, daher ist dieses Standup freiwillig.",
+            (StandupHoliday, Maori) => "Ko {name} tēnei rā, nō reira kāore e herea tēnei hui tū.",
+
+            (StandupEditButton, English) => "Edit",
+            (StandupEditButton, German) => "Bearbeiten",
+            (StandupEditButton, Maori) => "Whakatika",
+
+            (StandupDeleteButton, English) => "Delete",
+            (StandupDeleteButton, German) => "Löschen",
+            (StandupDeleteButton, Maori) => "Mukua",
+
+            (StandupNotYours, English) => "Only the author of a standup can change it.",
+            (StandupNotYours, German) => "Nur die Person, die das Standup verfasst hat, kann es ändern.",
+            (StandupNotYours, Maori) => "Mā te kaituhi anake o te hui tū e whakarerekē.",
+
+            (StandupMissing, English) => "That standup no longer exists.",
+            (StandupMissing, German) => "Dieses Standup existiert nicht mehr.",
+            (StandupMissing, Maori) => "Kua kore kē tēnā hui tū.",
+
+            (StandupSaveNotYours, English) => "Only the author of a message can save it as their standup.",
+            (StandupSaveNotYours, German) => "Nur die Person, die die Nachricht geschrieben hat, kann sie als Standup speichern.",
+            (StandupSaveNotYours, Maori) => "Mā te kaituhi anake o te karere e tiaki hei hui tū māna.",
+
+            (StandupSaveEmpty, English) => "That message has no text to save as a standup.",
+            (StandupSaveEmpty, German) => "Diese Nachricht enthält keinen Text, der als Standup gespeichert werden kann.",
+            (StandupSaveEmpty, Maori) => "Kāore he kupu o taua karere hei tiaki hei hui tū.",
+
+            (StandupDeleted, English) => "Your standup has been deleted.",
+            (StandupDeleted, German) => "Dein Standup wurde gelöscht.",
+            (StandupDeleted, Maori) => "Kua mukua tō hui tū.",
+
+            (HolidayAdded, English) => "Added the holiday {name}.",
+            (HolidayAdded, German) => "Der Feiertag {name} wurde hinzugefügt.",
+            (HolidayAdded, Maori) => "Kua tāpirihia te hararei {name}.",
+
+            (HolidayRemoved, English) => "Removed any holidays on {date}.",
+            (HolidayRemoved, German) => "Alle Feiertage am {date} wurden entfernt.",
+            (HolidayRemoved, Maori) => "Kua tangohia ngā hararei katoa i te {date}.",
+
+            (HolidaysImported, English) => "Imported {count} holidays.",
+            (HolidaysImported, German) => "{count} Feiertage wurden importiert.",
+            (HolidaysImported, Maori) => "Kua kawemaihia ngā hararei {count}.",
+
+            (HolidayInvalidDate, English) => "Dates must be written as YYYY-MM-DD, e.g. 2023-02-06.",
+            (HolidayInvalidDate, German) => "Daten müssen als JJJJ-MM-TT angegeben werden, z. B. 2023-02-06.",
+            (HolidayInvalidDate, Maori) => "Me tuhi ngā rā hei TTTT-MM-RR, hei tauira 2023-02-06.",
+
+            (HolidayInvalidFile, English) => "That file could not be read as an ICS calendar.",
+            (HolidayInvalidFile, German) => "Diese Datei konnte nicht als ICS-Kalender gelesen werden.",
+            (HolidayInvalidFile, Maori) => "Kāore i taea te pānui i tēnā kōnae hei maramataka ICS.",
+
+            (HolidaysGuildHeader, English) => "**Server holidays**",
+            (HolidaysGuildHeader, German) => "**Feiertage des Servers**",
+            (HolidaysGuildHeader, Maori) => "**Ngā hararei o te tūmau**",
+
+            (HolidaysUserHeader, English) => "**Your holidays**",
+            (HolidaysUserHeader, German) => "**Deine Feiertage**",
+            (HolidaysUserHeader, Maori) => "**Āu hararei**",
+
+            (HolidaysNone, English) => "No holidays have been added.",
+            (HolidaysNone, German) => "Es wurden keine Feiertage hinzugefügt.",
+            (HolidaysNone, Maori) => "Kāore anō kia tāpirihia he hararei.",
+
+            (WebhookSet, English) => {
+                "Submissions will now be sent to {url}.\nPayloads are signed with the secret `{secret}`."
+            }
+            (WebhookSet, German) => {
+                "Standups werden jetzt an {url} gesendet.\nNutzdaten werden mit dem Geheimnis `{secret}` signiert."
+            }
+            (WebhookSet, Maori) => {
+                "Ka tukuna ngā hui tū ki {url} ināianei.\nKa hainatia ngā utanga ki te kupu huna `{secret}`."
+            }
+
+            (WebhookCleared, English) => "Submissions will no longer be sent to a webhook.",
+            (WebhookCleared, German) => "Standups werden nicht mehr an einen Webhook gesendet.",
+            (WebhookCleared, Maori) => "Kāore e tukuna ngā hui tū ki tētahi webhook ā muri ake nei.",
+
+            (WebhookInvalidUrl, English) => "Webhook urls must start with http:// or https://.",
+            (WebhookInvalidUrl, German) => "Webhook-URLs müssen mit http:// oder https:// beginnen.",
+            (WebhookInvalidUrl, Maori) => "Me tīmata ngā url webhook ki te http:// , ki te https:// rānei.",
+
+            (WebhookNotConfigured, English) => "No webhook has been set for this server.",
+            (WebhookNotConfigured, German) => "Für diesen Server wurde kein Webhook festgelegt.",
+            (WebhookNotConfigured, Maori) => "Kāore anō kia whakaritea he webhook mō tēnei tūmau.",
+
+            (WebhookTestSucceeded, English) => "The webhook responded with status {status}.",
+            (WebhookTestSucceeded, German) => "Der Webhook antwortete mit Status {status}.",
+            (WebhookTestSucceeded, Maori) => "I urupare te webhook ki te tūnga {status}.",
+
+            (WebhookTestFailed, English) => "The test payload could not be delivered: {error}",
+            (WebhookTestFailed, German) => "Die Testnachricht konnte nicht zugestellt werden: {error}",
+            (WebhookTestFailed, Maori) => "Kāore i taea te tuku i te utanga whakamātautau: {error}",
+
+            (LinkAdded, English) => "References matching `{pattern}` will now be linked.",
+            (LinkAdded, German) => "Verweise, die auf `{pattern}` passen, werden jetzt verlinkt.",
+            (LinkAdded, Maori) => "Ka hono ngā tohutoro e ōrite ana ki `{pattern}` ināianei.",
+
+            (LinkRemoved, English) => "References matching `{pattern}` will no longer be linked.",
+            (LinkRemoved, German) => "Verweise, die auf `{pattern}` passen, werden nicht mehr verlinkt.",
+            (LinkRemoved, Maori) => "Kāore e hono ngā tohutoro e ōrite ana ki `{pattern}`.",
+
+            (LinkInvalidPattern, English) => "That pattern is not a valid regex: {error}",
+            (LinkInvalidPattern, German) => "Dieses Muster ist kein gültiger regulärer Ausdruck: {error}",
+            (LinkInvalidPattern, Maori) => "Ehara tēnā tauira i te regex tika: {error}",
+
+            (LinkInvalidUrl, English) => "Link urls must start with http:// or https://.",
+            (LinkInvalidUrl, German) => "Link-URLs müssen mit http:// oder https:// beginnen.",
+            (LinkInvalidUrl, Maori) => "Me tīmata ngā url hono ki te http:// , ki te https:// rānei.",
+
+            (LinksNone, English) => "No references are being linked.",
+            (LinksNone, German) => "Es werden keine Verweise verlinkt.",
+            (LinksNone, Maori) => "Kāore he tohutoro e honoa ana.",
+
+            (BoardTitle, English) => "Who's working on what",
+            (BoardTitle, German) => "Wer arbeitet woran",
+            (BoardTitle, Maori) => "Ko wai e mahi ana i te aha",
+
+            (BoardEmpty, English) => "Nobody has submitted a standup yet.",
+            (BoardEmpty, German) => "Es wurde noch kein Standup eingereicht.",
+            (BoardEmpty, Maori) => "Kāore anō tētahi kia tuku hui tū.",
+
+            (BoardNoUpdate, English) => "_no update this week_",
+            (BoardNoUpdate, German) => "_diese Woche kein Update_",
+            (BoardNoUpdate, Maori) => "_kāore he kōrero i tēnei wiki_",
+
+            (BoardFinish, English) => "finishing {date}",
+            (BoardFinish, German) => "fertig {date}",
+            (BoardFinish, Maori) => "ka oti {date}",
+
+            (BoardBlocked, English) => "🚫 blocked",
+            (BoardBlocked, German) => "🚫 blockiert",
+            (BoardBlocked, Maori) => "🚫 kua aukatia",
+
+            (BoardUpdated, English) => "updated {time}",
+            (BoardUpdated, German) => "aktualisiert {time}",
+            (BoardUpdated, Maori) => "i whakahōuhia {time}",
+
+            (BoardSet, English) => "The board will be kept up to date in {channel}.",
+            (BoardSet, German) => "Die Übersicht wird in {channel} aktuell gehalten.",
+            (BoardSet, Maori) => "Ka whakahōuhia te papa i {channel}.",
+
+            (BoardCleared, English) => "The board will no longer be updated.",
+            (BoardCleared, German) => "Die Übersicht wird nicht mehr aktualisiert.",
+            (BoardCleared, Maori) => "Kāore e whakahōuhia te papa ā muri ake nei.",
+
+            (StandupProgress, English) => "**{submitted}/{total} submitted**",
+            (StandupProgress, German) => "**{submitted}/{total} eingereicht**",
+            (StandupProgress, Maori) => "**{submitted}/{total} kua tukuna**",
+
+            (StandupPosted, English) => "✅ {users}",
+            (StandupPosted, German) => "✅ {users}",
+            (StandupPosted, Maori) => "✅ {users}",
+
+            (StandupPending, English) => "⏳ {users}",
+            (StandupPending, German) => "⏳ {users}",
+            (StandupPending, Maori) => "⏳ {users}",
+
+            (StandupMore, English) => "and {count} more",
+            (StandupMore, German) => "und {count} weitere",
+            (StandupMore, Maori) => "me ētahi atu {count}",
+
+            (StandupCloseButton, English) => "Close",
+            (StandupCloseButton, German) => "Beenden",
+            (StandupCloseButton, Maori) => "Katia",
+
+            (StandupClosed, English) => "This standup is closed, {submitted}/{total} submitted.",
+            (StandupClosed, German) => "Dieses Standup ist beendet, {submitted}/{total} eingereicht.",
+            (StandupClosed, Maori) => "Kua katia tēnei hui tū, {submitted}/{total} kua tukuna.",
+
+            (StandupDigestLink, English) => "[Read the digest](<{link}>)",
+            (StandupDigestLink, German) => "[Zur Zusammenfassung](<{link}>)",
+            (StandupDigestLink, Maori) => "[Pānuihia te whakarāpopototanga](<{link}>)",
+
+            (StandupAlreadyClosed, English) => "This standup has already been closed.",
+            (StandupAlreadyClosed, German) => "Dieses Standup wurde bereits beendet.",
+            (StandupAlreadyClosed, Maori) => "Kua katia kē tēnei hui tū.",
+
+            (StandupCloseDenied, English) => "Only the person who started the standup or a server manager can close it.",
+            (StandupCloseDenied, German) => "Nur die Person, die das Standup gestartet hat, oder eine Serververwaltung kann es beenden.",
+            (StandupCloseDenied, Maori) => "Mā te tangata nāna i tīmata te hui tū, mā tētahi kaiwhakahaere rānei e kati.",
+
+            (StandupClosedReply, English) => "The standup has been closed.",
+            (StandupClosedReply, German) => "Das Standup wurde beendet.",
+            (StandupClosedReply, Maori) => "Kua katia te hui tū.",
+
+            (DigestTitle, English) => "**Standup digest for {date}**",
+            (DigestTitle, German) => "**Standup-Zusammenfassung vom {date}**",
+            (DigestTitle, Maori) => "**Whakarāpopototanga hui tū mō {date}**",
+
+            (DigestEmpty, English) => "Nobody submitted to this standup.",
+            (DigestEmpty, German) => "Niemand hat zu diesem Standup etwas eingereicht.",
+            (DigestEmpty, Maori) => "Kāore tētahi i tuku ki tēnei hui tū.",
+
+            (HistoryHeader, English) => "**{question}** — {user}",
+            (HistoryHeader, German) => "**{question}** — {user}",
+            (HistoryHeader, Maori) => "**{question}** — {user}",
+
+            (HistoryNone, English) => "{user} hasn't submitted any standups yet.",
+            (HistoryNone, German) => "{user} hat noch keine Standups eingereicht.",
+            (HistoryNone, Maori) => "Kāore anō {user} kia tuku hui tū.",
+
+
+            (HistoryUnknownQuestion, English) => "There are only {count} questions, pick one from the list.",
+            (HistoryUnknownQuestion, German) => "Es gibt nur {count} Fragen, wähle eine aus der Liste.",
+            (HistoryUnknownQuestion, Maori) => "E {count} noa iho ngā pātai, kōwhiria tētahi i te rārangi.",
+
+            (ExportReady, English) => "Exported {count} standups.",
+            (ExportReady, German) => "{count} Standups exportiert.",
+            (ExportReady, Maori) => "Kua kaweake ngā hui tū e {count}.",
+
+            (ExportNone, English) => "No standups have been submitted in this server yet.",
+            (ExportNone, German) => "Auf diesem Server wurden noch keine Standups eingereicht.",
+            (ExportNone, Maori) => "Kāore anō kia tukuna he hui tū ki tēnei tūmau.",
+
+            (OptionMissing, English) => "You need to provide `{option}`.",
+            (OptionMissing, German) => "Du musst `{option}` angeben.",
+            (OptionMissing, Maori) => "Me whakarato koe i te `{option}`.",
+
+            (OptionTooShort, English) => "`{option}` must be at least {bound} characters long.",
+            (OptionTooShort, German) => "`{option}` muss mindestens {bound} Zeichen lang sein.",
+            (OptionTooShort, Maori) => "Kia {bound} ngā pū o `{option}`, neke atu.",
+
+            (OptionTooLong, English) => "`{option}` can be at most {bound} characters long.",
+            (OptionTooLong, German) => "`{option}` darf höchstens {bound} Zeichen lang sein.",
+            (OptionTooLong, Maori) => "Kia {bound} ngā pū o `{option}`, heke iho.",
+
+            (OptionTooSmall, English) => "`{option}` must be at least {bound}.",
+            (OptionTooSmall, German) => "`{option}` muss mindestens {bound} sein.",
+            (OptionTooSmall, Maori) => "Kia {bound} te iti rawa o `{option}`.",
+
+            (OptionTooLarge, English) => "`{option}` can be at most {bound}.",
+            (OptionTooLarge, German) => "`{option}` darf höchstens {bound} sein.",
+            (OptionTooLarge, Maori) => "Kia {bound} te nui rawa o `{option}`.",
+
+            (CapabilityMissing, English) => "You need the `{capability}` permission to do that, ask a server manager to grant it to one of your roles.",
+            (CapabilityMissing, German) => "Dafür brauchst du die Berechtigung `{capability}`, bitte eine Serververwaltung, sie einer deiner Rollen zu geben.",
+            (CapabilityMissing, Maori) => "Me whai koe i te whakaaetanga `{capability}`, tonoa tētahi kaiwhakahaere kia hoatu ki tētahi o ō tūranga.",
+
+            (CooldownActive, English) => "You're doing that too often, try again in {seconds}s.",
+            (CooldownActive, German) => "Du machst das zu oft, versuche es in {seconds}s noch einmal.",
+            (CooldownActive, Maori) => "He auau rawa tēnā, whakamātau anō ā te {seconds}s.",
+
+            (ComponentExpired, English) => "This button has expired, use the latest message or run the command again.",
+            (ComponentExpired, German) => "Dieser Button ist abgelaufen, verwende die neueste Nachricht oder führe den Befehl erneut aus.",
+            (ComponentExpired, Maori) => "Kua pau te wā o tēnei pātene, whakamahia te karere hou rawa, whakahaerehia anō rānei te tono.",
+
+            (PermissionGranted, English) => "{role} can now use `{capability}`. If the command is hidden from them, allow it under Server Settings → Integrations.",
+            (PermissionGranted, German) => "{role} kann jetzt `{capability}` verwenden. Falls der Befehl für sie verborgen ist, erlaube ihn unter Servereinstellungen → Integrationen.",
+            (PermissionGranted, Maori) => "Ka taea e {role} te whakamahi i `{capability}`. Mēnā e huna ana te tono, whakaaetia i ngā Tautuhinga Tūmau → Ngā Hononga.",
+
+            (PermissionRevoked, English) => "{role} can no longer use `{capability}`.",
+            (PermissionRevoked, German) => "{role} kann `{capability}` nicht mehr verwenden.",
+            (PermissionRevoked, Maori) => "Kāore e taea e {role} te whakamahi i `{capability}`.",
+
+            (PermissionsNone, English) => "No capabilities have been granted to any roles, only server managers can use them.",
+            (PermissionsNone, German) => "Es wurden keinen Rollen Berechtigungen gegeben, nur die Serververwaltung kann sie verwenden.",
+            (PermissionsNone, Maori) => "Kāore anō he whakaaetanga kia hoatu ki ētahi tūranga, mā ngā kaiwhakahaere anake.",
+
+            (UserMentionsAllowed, English) => "Messages members make the bot post will now ping the users they mention. Roles, @everyone and @here are never pinged.",
+            (UserMentionsAllowed, German) => "Nachrichten, die Mitglieder über den Bot posten, pingen jetzt die erwähnten Nutzer. Rollen, @everyone und @here werden nie gepingt.",
+            (UserMentionsAllowed, Maori) => "Ka pingtia ināianei ngā kaiwhakamahi ka whakahuatia i ngā karere ka tukuna e ngā mema mā te bot. Kāore rawa ngā tūranga, @everyone me @here e pingtia.",
+
+            (UserMentionsBlocked, English) => "Messages members make the bot post will no longer ping anyone.",
+            (UserMentionsBlocked, German) => "Nachrichten, die Mitglieder über den Bot posten, pingen niemanden mehr.",
+            (UserMentionsBlocked, Maori) => "Kāore e pingtia anō tētahi e ngā karere ka tukuna e ngā mema mā te bot.",
+
+            (ModuleUnavailable, English) => "The `{module}` module is disabled on this server.",
+            (ModuleUnavailable, German) => "Das Modul `{module}` ist auf diesem Server deaktiviert.",
+            (ModuleUnavailable, Maori) => "Kua monoa te kōwae `{module}` i tēnei tūmau.",
+
+            (ModuleEnabled, English) => "The `{module}` module is now enabled, its commands will appear shortly.",
+            (ModuleEnabled, German) => "Das Modul `{module}` ist jetzt aktiviert, seine Befehle erscheinen in Kürze.",
+            (ModuleEnabled, Maori) => "Kua whakahohea te kōwae `{module}`, ka puta ōna tono ā tōna wā.",
+
+            (ModuleDisabled, English) => "The `{module}` module is now disabled, its commands will disappear shortly.",
+            (ModuleDisabled, German) => "Das Modul `{module}` ist jetzt deaktiviert, seine Befehle verschwinden in Kürze.",
+            (ModuleDisabled, Maori) => "Kua monoa te kōwae `{module}`, ka ngaro ōna tono ā tōna wā.",
+
+            (ModuleRequired, English) => "The `{module}` module can't be disabled.",
+            (ModuleRequired, German) => "Das Modul `{module}` kann nicht deaktiviert werden.",
+            (ModuleRequired, Maori) => "Kāore e taea te mono i te kōwae `{module}`.",
+
+            (ModuleStateEnabled, English) => "enabled",
+            (ModuleStateEnabled, German) => "aktiviert",
+            (ModuleStateEnabled, Maori) => "hohe",
+
+            (ModuleStateDisabled, English) => "disabled",
+            (ModuleStateDisabled, German) => "deaktiviert",
+            (ModuleStateDisabled, Maori) => "mono",
+
+            (CustomCommandCreated, English) => "Created `/{name}`, it will appear shortly.",
+            (CustomCommandCreated, German) => "`/{name}` wurde erstellt und erscheint in Kürze.",
+            (CustomCommandCreated, Maori) => "Kua hangaia a `/{name}`, ka puta ā tōna wā.",
+
+            (CustomCommandUpdated, English) => "Updated `/{name}`.",
+            (CustomCommandUpdated, German) => "`/{name}` wurde aktualisiert.",
+            (CustomCommandUpdated, Maori) => "Kua whakahōutia a `/{name}`.",
+
+            (CustomCommandDeleted, English) => "Deleted `/{name}`, it will disappear shortly.",
+            (CustomCommandDeleted, German) => "`/{name}` wurde gelöscht und verschwindet in Kürze.",
+            (CustomCommandDeleted, Maori) => "Kua mukua a `/{name}`, ka ngaro ā tōna wā.",
+
+            (CustomCommandMissing, English) => "This server has no custom command `/{name}`.",
+            (CustomCommandMissing, German) => "Auf diesem Server gibt es keinen eigenen Befehl `/{name}`.",
+            (CustomCommandMissing, Maori) => "Kāore he tono ritenga `/{name}` i tēnei tūmau.",
+
+            (CustomCommandExists, English) => "`/{name}` already exists, edit it instead.",
+            (CustomCommandExists, German) => "`/{name}` existiert bereits, bearbeite ihn stattdessen.",
+            (CustomCommandExists, Maori) => "Kei te tīari kē a `/{name}`, me whakatika kē.",
+
+            (CustomCommandReserved, English) => "`/{name}` is a built in command, choose another name.",
+            (CustomCommandReserved, German) => "`/{name}` ist ein eingebauter Befehl, wähle einen anderen Namen.",
+            (CustomCommandReserved, Maori) => "He tono ake a `/{name}`, kōwhiria he ingoa kē.",
+
+            (CustomCommandInvalidName, English) => "Command names must be 1 to 32 lowercase letters, numbers, dashes or underscores.",
+            (CustomCommandInvalidName, German) => "Befehlsnamen müssen aus 1 bis 32 Kleinbuchstaben, Ziffern, Binde- oder Unterstrichen bestehen.",
+            (CustomCommandInvalidName, Maori) => "Me noho ngā ingoa tono hei pū iti, tau, tohuhono, tohuraro rānei, 1 ki te 32 te roa.",
+
+            (CustomCommandRejected, English) => "Discord refused `/{name}`, so the change was undone.",
+            (CustomCommandRejected, German) => "Discord hat `/{name}` abgelehnt, daher wurde die Änderung rückgängig gemacht.",
+            (CustomCommandRejected, Maori) => "I whakakāhoretia e Discord a `/{name}`, nō reira kua wetekina te panonitanga.",
+
+            (CustomCommandLimit, English) => "This server already has {count} custom commands, delete one first.",
+            (CustomCommandLimit, German) => "Dieser Server hat bereits {count} eigene Befehle, lösche zuerst einen.",
+            (CustomCommandLimit, Maori) => "Kua {count} kē ngā tono ritenga o tēnei tūmau, mukua tētahi i te tuatahi.",
+
+            (CustomCommandsNone, English) => "This server has no custom commands.",
+            (CustomCommandsNone, German) => "Dieser Server hat keine eigenen Befehle.",
+            (CustomCommandsNone, Maori) => "Kāore he tono ritenga o tēnei tūmau.",
+        }
+    }
+
+    /// get the text of this message in the provided locale, replacing each `{key}` with its value
+    pub fn format(self, locale: Locale, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.get(locale).to_string(), |text, (key, value)| {
+                text.replace(&format!("{{{}}}", key), value)
+            })
+    }
+}
+
+/// the questions asked during a standup, every locale must provide the same number of questions in the same order
+pub fn standup_questions(locale: Locale) -> &'static [&'static str] {
+    match locale {
+        Locale::English => &[
+            "What did you work on last week?",
+            "What are you working on this week?",
+            "When are you aiming to finish?",
+            "Is there anything blocking you?",
+            "is there anything you need help with?",
+        ],
+        Locale::German => &[
+            "Woran hast du letzte Woche gearbeitet?",
+            "Woran arbeitest du diese Woche?",
+            "Wann planst du, fertig zu sein?",
+            "Blockiert dich gerade etwas?",
+            "Brauchst du bei etwas Hilfe?",
+        ],
+        Locale::Maori => &[
+            "He aha tāu mahi i tērā wiki?",
+            "He aha tāu mahi i tēnei wiki?",
+            "Āhea koe whakaoti ai?",
+            "He aha ngā mea e aukati ana i a koe?",
+            "He āwhina e hiahiatia ana e koe?",
+        ],
+    }
+}
+
+/// the translated name and description of a command for a locale, as `(locale, name, description)`.
+/// the english name and description are provided by the command itself
+pub fn command_localizations(command: &str) -> &'static [(Locale, &'static str, &'static str)] {
+    match command {
+        "hide" => &[(
+            Locale::German,
+            "verbergen",
+            "Erstellt eine lange Nachricht, um vorherige Nachrichten im Chat zu verbergen",
+        )],
+        "permissions" => &[(
+            Locale::German,
+            "berechtigungen",
+            "Legt fest, welche Rollen welche Teile des Bots verwenden können",
+        )],
+        "modules" => &[(
+            Locale::German,
+            "module",
+            "Legt fest, welche Befehle auf diesem Server verfügbar sind",
+        )],
+        "customcmd" => &[(
+            Locale::German,
+            "eigenerbefehl",
+            "Verwaltet die eigenen Befehle dieses Servers",
+        )],
+        "ping" => &[(
+            Locale::German,
+            "ping",
+            "Pingt den Bot an, erwarte ein Pong als Antwort.",
+        )],
+        "say" => &[(Locale::German, "sagen", "Sagt, was immer du willst!")],
+        "standup" => &[(
+            Locale::German,
+            "standup",
+            "Startet das wöchentliche Standup",
+        )],
+        "language" => &[(
+            Locale::German,
+            "sprache",
+            "Legt die Sprache fest, in der der Bot antwortet",
+        )],
+        "history" => &[(
+            Locale::German,
+            "verlauf",
+            "Zeigt die bisherigen Standups einer Person",
+        )],
+        "export" => &[(
+            Locale::German,
+            "exportieren",
+            "Exportiert alle Standups dieses Servers als Tabelle",
+        )],
+        "holidays" => &[(
+            Locale::German,
+            "feiertage",
+            "Verwaltet die Feiertage, an denen keine Standups stattfinden",
+        )],
+        "standup-config" => &[(
+            Locale::German,
+            "standup-einstellungen",
+            "Legt fest, wie Standups auf diesem Server funktionieren",
+        )],
+        "View standup history" => &[(Locale::German, "Standup-Verlauf anzeigen", "")],
+        "Save as standup update" => &[(Locale::German, "Als Standup-Update speichern", "")],
+        "timezone" => &[(
+            Locale::German,
+            "zeitzone",
+            "Legt die Zeitzone fest, in der deine Standups geplant werden",
+        )],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use regex::Regex;
+
+    use crate::storage::Storage;
+
+    use super::{resolve, standup_questions, Locale, Text};
+
+    const GUILD_ID: u64 = 10;
+    const USER_ID: u64 = 30;
+
+    /// the placeholders a message takes, such as `{name}`
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        let placeholder = Regex::new(r"\{[a-z_]+\}").unwrap();
+        placeholder.find_iter(text).map(|m| m.as_str()).collect()
+    }
+
+    #[test]
+    fn every_message_is_translated() {
+        for text in Text::ALL {
+            for locale in Locale::ALL {
+                assert!(
+                    !text.get(locale).trim().is_empty(),
+                    "{:?} is empty in {:?}",
+                    text,
+                    locale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_translation_takes_the_same_placeholders() {
+        for text in Text::ALL {
+            let english = placeholders(text.get(Locale::English));
+            for locale in Locale::ALL {
+                assert_eq!(
+                    placeholders(text.get(locale)),
+                    english,
+                    "{:?} takes different placeholders in {:?}",
+                    text,
+                    locale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_locale_asks_the_same_number_of_questions() {
+        let count = standup_questions(Locale::English).len();
+        for locale in Locale::ALL {
+            assert_eq!(standup_questions(locale).len(), count, "{:?}", locale);
+        }
+    }
+
+    #[test]
+    fn locale_codes_are_parsed() {
+        assert_eq!(Locale::from_code("mi"), Some(Locale::Maori));
+        assert_eq!(Locale::from_code("en-GB"), Some(Locale::English));
+        assert_eq!(Locale::from_code("DE"), Some(Locale::German));
+        assert_eq!(Locale::from_code("fr"), None);
+    }
+
+    #[tokio::test]
+    async fn users_choice_beats_guilds_default_beats_their_client() {
+        let dir =
+            std::env::temp_dir().join(format!("standup-bot-locale-{:016x}", rand::random::<u64>()));
+        let storage = Storage::load(dir.join("storage.json")).await.unwrap();
+
+        // nothing chosen, so the client's locale is used, falling back to english for one we don't speak
+        assert_eq!(
+            resolve(&storage, Some(GUILD_ID), USER_ID, "de").await,
+            Locale::German
+        );
+        assert_eq!(
+            resolve(&storage, Some(GUILD_ID), USER_ID, "fr").await,
+            Locale::English
+        );
+
+        storage
+            .update(|data| data.guilds.entry(GUILD_ID).or_default().locale = Some(Locale::Maori))
+            .await
+            .unwrap();
+        assert_eq!(
+            resolve(&storage, Some(GUILD_ID), USER_ID, "de").await,
+            Locale::Maori
+        );
+        // outside of the guild its default doesn't apply
+        assert_eq!(resolve(&storage, None, USER_ID, "de").await, Locale::German);
+
+        storage
+            .update(|data| data.users.entry(USER_ID).or_default().locale = Some(Locale::German))
+            .await
+            .unwrap();
+        assert_eq!(
+            resolve(&storage, Some(GUILD_ID), USER_ID, "en-US").await,
+            Locale::German
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
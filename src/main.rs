@@ -1,14 +1,29 @@
+mod access;
+mod announcements;
+mod cooldown;
+mod custom_commands;
 mod discord_bot;
 
 mod healthcheck;
 
+mod holidays;
+mod locale;
 mod logging;
+mod mentions;
+mod modules;
+mod references;
+mod standups;
 mod state;
+mod storage;
+mod timezone;
+mod webhook;
 
 use log::{error, info};
 use std::process::exit;
 
-use crate::{discord_bot::DiscordBot, logging::configure_logger, state::AppState};
+use crate::{
+    discord_bot::DiscordBot, logging::configure_logger, state::AppState, webhook::WebhookDispatcher,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -62,8 +77,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("healthcheck server shut down");
     });
 
+    info!("spawning webhook dispatcher");
+    let webhook_state = state.clone();
+    let webhook_handle = tokio::task::spawn(async move {
+        WebhookDispatcher::new(webhook_state).run().await;
+    });
+
     tokio::pin!(discord_handle);
     tokio::pin!(healthcheck_handle);
+    tokio::pin!(webhook_handle);
 
     loop {
         tokio::select! {
@@ -78,10 +100,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 break;
             }
 
-            _ = healthcheck_handle => {
+            _ = &mut healthcheck_handle => {
                 info!("healthcheck server shut down");
                 break;
             }
+
+            _ = &mut webhook_handle => {
+                info!("webhook dispatcher shut down");
+                break;
+            }
         }
     }
 
@@ -0,0 +1,85 @@
+//! Keeping text written by members from pinging people or advertising servers when the bot posts it.
+//! The text is neutralised, and the mentions discord may turn into pings are set by each message, see
+//! `discord_bot::mentions`.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// inserted to break up a mention or link, it isn't shown but stops discord recognising what it splits
+const ZERO_WIDTH_SPACE: &str = "\u{200B}";
+
+/// neutralise the mass mentions and invite links in text written by a member, so they show as plain text.
+/// user and role mentions are left as written, whether they ping is decided by the allowed mentions of the message
+pub fn neutralise(text: &str) -> String {
+    // markdown between the `@` and the name is hidden once rendered, so `@**everyone**` still reads as a mention
+    static MASS_MENTIONS: OnceLock<Regex> = OnceLock::new();
+    let mass_mentions = MASS_MENTIONS.get_or_init(|| {
+        Regex::new(r"@([*_~|\\]*(?:everyone|here))").expect("mass mention pattern is valid")
+    });
+    let text = mass_mentions.replace_all(text, format!("@{}${{1}}", ZERO_WIDTH_SPACE));
+
+    static INVITES: OnceLock<Regex> = OnceLock::new();
+    let invites = INVITES.get_or_init(|| {
+        Regex::new(r"(?i)((?:ptb\.|canary\.)?discord(?:app)?\.com/invite|discord\.gg)/")
+            .expect("invite pattern is valid")
+    });
+    invites
+        .replace_all(&text, format!("${{1}}{}/", ZERO_WIDTH_SPACE))
+        .into_owned()
+}
+
+/// check if a guild lets text written by its members ping the users it mentions, no guild does unless it opts in
+pub async fn user_mentions_allowed(storage: &Storage, guild_id: Option<u64>) -> bool {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return false,
+    };
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .map_or(false, |g| g.allow_user_mentions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::neutralise;
+
+    #[test]
+    fn mass_mentions_and_invites_are_neutralised() {
+        let original = "@everyone @here @**everyone** @\\*\\*here** @||everyone|| join https://discord.gg/abc, \
+                        discord.com/invite/xyz, https://ptb.discord.com/invite/p, canary.discord.com/invite/c \
+                        or DiscordApp.com/invite/d";
+        let text = neutralise(original);
+        for mention in [
+            "@everyone",
+            "@here",
+            "@**everyone",
+            "@\\*\\*here",
+            "@||everyone",
+        ] {
+            assert!(!text.contains(mention), "{} wasn't neutralised", mention);
+        }
+        for invite in [
+            "discord.gg/",
+            "discord.com/invite/",
+            "ptb.discord.com/invite/",
+            "canary.discord.com/invite/",
+            "DiscordApp.com/invite/",
+        ] {
+            assert!(!text.contains(invite), "{} wasn't neutralised", invite);
+        }
+        assert_eq!(text.replace('\u{200B}', ""), original);
+    }
+
+    #[test]
+    fn other_text_is_unchanged() {
+        let text =
+            "<@123> finished <@&456> and emailed me@example.com about discord.com/channels/1";
+        assert_eq!(neutralise(text), text);
+    }
+}
@@ -0,0 +1,64 @@
+//! Command modules, groups of commands which a guild can enable or disable
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+/// a group of commands which is enabled or disabled as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Module {
+    /// the basics of the bot, such as language and timezone preferences
+    Core,
+    /// running standups, and looking back over them
+    Standup,
+    /// fun and utility commands, such as `/say` and `/hide`
+    Utility,
+    /// managing the bot in a guild
+    Admin,
+}
+
+impl Module {
+    /// every module
+    pub const ALL: [Module; 4] = [
+        Module::Core,
+        Module::Standup,
+        Module::Utility,
+        Module::Admin,
+    ];
+
+    /// the name used to refer to this module
+    pub fn name(self) -> &'static str {
+        match self {
+            Module::Core => "core",
+            Module::Standup => "standup",
+            Module::Utility => "utility",
+            Module::Admin => "admin",
+        }
+    }
+
+    /// parse the name of a module
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    /// whether a guild may disable this module, admin commands are always kept so modules can be enabled again
+    pub fn can_disable(self) -> bool {
+        !matches!(self, Module::Core | Module::Admin)
+    }
+}
+
+/// get the modules a guild has disabled, none are disabled outside of a guild
+pub async fn disabled_modules(storage: &Storage, guild_id: Option<u64>) -> Vec<Module> {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return vec![],
+    };
+    storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .map(|g| g.disabled_modules.clone())
+        .unwrap_or_default()
+}
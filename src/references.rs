@@ -0,0 +1,187 @@
+//! Automatic linking of issue tracker references, such as `#482` or `PAY-1234`, found in standup answers
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// the largest compiled size allowed for a pattern, to stop a guild from configuring a pathological regex
+const PATTERN_SIZE_LIMIT: usize = 1 << 16;
+
+/// a pattern configured by a guild, matching references and turning them into links
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPattern {
+    /// the regex matching a reference, e.g. `#(\d+)`
+    pub pattern: String,
+    /// the url a match links to, with `$1` or `${name}` replaced by the captures of the pattern
+    pub url: String,
+}
+
+/// a reference found in an answer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    /// the text which was matched, e.g. `PAY-1234`
+    pub text: String,
+    /// the url the reference links to
+    pub url: String,
+}
+
+/// compile a pattern, returning a description of the problem if it is not valid
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// the patterns of a guild, compiled so they can be matched against answers
+#[derive(Debug, Default)]
+pub struct CompiledPatterns {
+    /// the patterns these were compiled from, so a change to them can be noticed
+    source: Vec<LinkPattern>,
+    /// each valid pattern and the url its matches link to, in priority order
+    compiled: Vec<(Regex, String)>,
+}
+
+impl CompiledPatterns {
+    /// compile the patterns of a guild, leaving out any which are not valid
+    pub fn new(patterns: &[LinkPattern]) -> Self {
+        Self {
+            source: patterns.to_vec(),
+            compiled: patterns
+                .iter()
+                .filter_map(|link| Some((compile(&link.pattern).ok()?, link.url.clone())))
+                .collect(),
+        }
+    }
+}
+
+/// the compiled patterns of each guild, so they are only compiled again once the guild changes them
+#[derive(Debug, Default)]
+pub struct PatternCache {
+    guilds: Mutex<HashMap<u64, Arc<CompiledPatterns>>>,
+}
+
+impl PatternCache {
+    /// get the compiled form of the current patterns of a guild
+    pub fn get(&self, guild_id: u64, patterns: &[LinkPattern]) -> Arc<CompiledPatterns> {
+        let mut guilds = match self.guilds.lock() {
+            Ok(guilds) => guilds,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match guilds.get(&guild_id) {
+            Some(compiled) if compiled.source == patterns => compiled.clone(),
+            _ => {
+                let compiled = Arc::new(CompiledPatterns::new(patterns));
+                guilds.insert(guild_id, compiled.clone());
+                compiled
+            }
+        }
+    }
+}
+
+/// percent-encode text so it can be placed in a url without changing its meaning
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// fill the captures of a match into a url, as `$1`, `${name}` or `$name`, with `$$` for a literal `$`.
+/// each capture is percent-encoded, so text written by a member can't change where the link goes
+fn expand_url(url: &str, captures: &Captures) -> String {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| {
+        Regex::new(r"\$(?:(\$)|\{([^}]+)\}|([A-Za-z0-9_]+))").expect("placeholder pattern is valid")
+    });
+
+    placeholder
+        .replace_all(url, |c: &Captures| {
+            if c.get(1).is_some() {
+                return String::from("$");
+            }
+            let name = c.get(2).or_else(|| c.get(3)).map_or("", |m| m.as_str());
+            let value = match name.parse::<usize>() {
+                Ok(i) => captures.get(i),
+                Err(_) => captures.name(name),
+            };
+            value.map_or_else(String::new, |m| percent_encode(m.as_str()))
+        })
+        .into_owned()
+}
+
+/// find every reference in some text, earlier patterns take priority when matches overlap
+pub fn find_references(patterns: &CompiledPatterns, text: &str) -> Vec<(usize, usize, Reference)> {
+    let mut found: Vec<(usize, usize, Reference)> = vec![];
+
+    for (regex, url) in &patterns.compiled {
+        for captures in regex.captures_iter(text) {
+            let whole = match captures.get(0) {
+                Some(m) if !m.as_str().is_empty() => m,
+                _ => continue,
+            };
+            if found
+                .iter()
+                .any(|(start, end, _)| whole.start() < *end && *start < whole.end())
+            {
+                continue;
+            }
+
+            let url = expand_url(url, &captures);
+            found.push((
+                whole.start(),
+                whole.end(),
+                Reference {
+                    text: whole.as_str().to_string(),
+                    url,
+                },
+            ));
+        }
+    }
+
+    found.sort_by_key(|(start, _, _)| *start);
+    found
+}
+
+/// replace every reference in some text with a markdown link
+pub fn link_references(patterns: &CompiledPatterns, text: &str) -> String {
+    let mut linked = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for (start, end, reference) in find_references(patterns, text) {
+        linked.push_str(&text[last..start]);
+        linked.push_str(&format!("[{}](<{}>)", reference.text, reference.url));
+        last = end;
+    }
+    linked.push_str(&text[last..]);
+
+    linked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{link_references, CompiledPatterns, LinkPattern};
+
+    #[test]
+    fn captures_are_encoded_in_links() {
+        let patterns = CompiledPatterns::new(&[LinkPattern {
+            pattern: String::from(r"\[(?P<key>[^\]]+)\]"),
+            url: String::from("https://example.com/search?q=${key}&cost=$$1"),
+        }]);
+
+        assert_eq!(
+            link_references(&patterns, "see [a b?x=1#y]"),
+            "see [[a b?x=1#y]](<https://example.com/search?q=a%20b%3Fx%3D1%23y&cost=$1>)"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! The records kept of each standup session, and of the submissions made to them
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::references::Reference;
+
+/// the position of the question asking what a member is working on this week
+pub const THIS_WEEK_QUESTION: usize = 1;
+/// the position of the question asking when a member is aiming to finish
+pub const FINISH_QUESTION: usize = 2;
+/// the position of the question asking if anything is blocking a member
+pub const BLOCKERS_QUESTION: usize = 3;
+
+/// answers to the blockers question which mean nothing is blocking, compared ignoring case and trailing punctuation
+const NOT_BLOCKED_ANSWERS: &[&str] = &[
+    "", "-", "no", "nope", "nah", "none", "nothing", "n/a", "na", "nein", "nichts", "kāore",
+    "kaore", "kao",
+];
+
+/// the message a guild keeps up to date with what each member of its roster is working on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardConfig {
+    /// the channel the board is posted in
+    pub channel_id: u64,
+    /// the message the board is currently shown in, if it has been posted
+    pub message_id: Option<u64>,
+    /// the role whose members are shown on the board, if not set everyone who has submitted is shown
+    pub roster_role: Option<u64>,
+}
+
+/// a standup which was started with `/standup`, and which members submit to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandupSession {
+    /// the id of the session, taken from the interaction which started it
+    pub id: u64,
+    /// the guild the session was started in
+    pub guild_id: u64,
+    /// the channel the session was started in
+    pub channel_id: u64,
+    /// the user who started the session
+    pub started_by: u64,
+    /// when the session was started
+    pub started_at: DateTime<Utc>,
+    /// the message showing the progress of the session, if it was posted successfully
+    #[serde(default)]
+    pub message_id: Option<u64>,
+    /// the name of the holiday the session was started on, if any
+    #[serde(default)]
+    pub holiday: Option<String>,
+    /// when the session was closed, if it has been closed
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    /// the message the digest of the session was posted as, once it has been closed
+    #[serde(default)]
+    pub digest_message_id: Option<u64>,
+}
+
+impl StandupSession {
+    /// check if members can still submit to this session
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none()
+    }
+}
+
+/// a set of answers submitted by a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    /// the id of the submission, taken from the modal interaction which created it
+    pub id: u64,
+    /// the guild the submission was made in
+    pub guild_id: u64,
+    /// the channel the submission was posted to
+    pub channel_id: u64,
+    /// the message the submission was posted as, if it was posted successfully
+    pub message_id: Option<u64>,
+    /// the session this submission was made to, submissions from before sessions were tracked have none
+    pub session_id: Option<u64>,
+    /// the user who made the submission
+    pub user_id: u64,
+    /// the name of the user at the time of submission
+    pub username: String,
+    /// the questions which were asked, in the language they were asked in
+    pub questions: Vec<String>,
+    /// the answer to each question, in the same order as the questions
+    pub answers: Vec<String>,
+    /// when the submission was first made
+    pub submitted_at: DateTime<Utc>,
+    /// when the submission was last edited, if it has been edited
+    pub edited_at: Option<DateTime<Utc>>,
+    /// the issue tracker references found in the answers
+    #[serde(default)]
+    pub references: Vec<Reference>,
+}
+
+impl Submission {
+    /// get the answer to the question at a position, if it was answered
+    pub fn answer(&self, question: usize) -> Option<&str> {
+        self.answers
+            .get(question)
+            .map(|a| a.trim())
+            .filter(|a| !a.is_empty())
+    }
+
+    /// check if the member said something is blocking them
+    pub fn is_blocked(&self) -> bool {
+        let answer = self
+            .answer(BLOCKERS_QUESTION)
+            .unwrap_or_default()
+            .trim_end_matches(|c: char| c.is_ascii_punctuation() && c != '/')
+            .to_lowercase();
+        !NOT_BLOCKED_ANSWERS.contains(&answer.as_str())
+    }
+
+    /// when this submission was last changed
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.edited_at.unwrap_or(self.submitted_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{Submission, BLOCKERS_QUESTION};
+
+    /// a submission which answered the blockers question, or left it out if there is no answer
+    fn submission(blockers: Option<&str>) -> Submission {
+        let mut answers = vec![String::from("answer"); BLOCKERS_QUESTION];
+        answers.extend(blockers.map(String::from));
+        Submission {
+            id: 1,
+            guild_id: 10,
+            channel_id: 20,
+            message_id: None,
+            session_id: None,
+            user_id: 30,
+            username: String::from("tester"),
+            questions: vec![String::from("question"); answers.len()],
+            answers,
+            submitted_at: Utc::now(),
+            edited_at: None,
+            references: vec![],
+        }
+    }
+
+    #[test]
+    fn answers_meaning_nothing_arent_blockers() {
+        assert!(!submission(None).is_blocked());
+        for answer in [
+            "", "  ", "-", "No", "none.", "Nothing!", "N/A", "nope", "Kāore", "nein",
+        ] {
+            assert!(!submission(Some(answer)).is_blocked(), "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn anything_else_is_a_blocker() {
+        for answer in ["waiting on review", "not yet", "no access to staging", "/"] {
+            assert!(submission(Some(answer)).is_blocked(), "{:?}", answer);
+        }
+    }
+}
@@ -1,22 +1,87 @@
 use std::{
+    collections::HashMap,
     error::Error,
     sync::{atomic::AtomicU64, Arc},
 };
 
 use serenity::prelude::TypeMapKey;
+use tokio::sync::{broadcast, Mutex, OwnedMutexGuard};
+
+use crate::{cooldown::Cooldowns, references::PatternCache, storage::Storage};
+
+/// the default location of the persisted bot data, used if `STORAGE_PATH` is not set
+const DEFAULT_STORAGE_PATH: &str = "./data/storage.json";
+
+/// the number of sync requests kept for a guild handler which hasn't received them yet
+const COMMAND_SYNC_CAPACITY: usize = 64;
+
+/// the number of locks kept before those which aren't held are dropped
+const PRUNE_LOCKS_AFTER: usize = 1000;
+
+/// a lock for each key, such as a guild or a session, so work on one key doesn't wait on work on another
+#[derive(Debug, Default)]
+pub struct KeyedLocks {
+    locks: std::sync::Mutex<HashMap<u64, Arc<Mutex<()>>>>,
+}
+
+impl KeyedLocks {
+    /// wait for the lock of a key, it is held until the guard is dropped
+    pub async fn lock(&self, key: u64) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = match self.locks.lock() {
+                Ok(locks) => locks,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            // a lock only referenced by the map isn't held or waited on by anyone
+            if locks.len() > PRUNE_LOCKS_AFTER {
+                locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            }
+            locks.entry(key).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
 
 /// A connection to the database, representing the stored "state" of the app
 pub struct AppState {
     pub start_time: std::time::Instant,
     pub num_connected: Arc<AtomicU64>,
+    pub storage: Arc<Storage>,
+    /// held for a guild while its board is being updated, so concurrent submissions can't post duplicate boards
+    pub board_locks: Arc<KeyedLocks>,
+    /// held for a session while its progress panel is being updated, so concurrent submissions are shown in order
+    pub panel_locks: Arc<KeyedLocks>,
+    /// the remaining uses of each rate limited command
+    pub cooldowns: Arc<Cooldowns>,
+    /// the link patterns of each guild, compiled when they were last used
+    pub link_patterns: Arc<PatternCache>,
+    /// held for a guild while its commands are being registered, so the hash stored is of the last commands registered
+    pub registration_locks: Arc<KeyedLocks>,
+    /// the guilds whose commands have changed, registered again in the background by the handler of each guild
+    pub command_syncs: broadcast::Sender<u64>,
 }
 
 impl AppState {
     pub async fn new() -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
+        let storage_path =
+            std::env::var("STORAGE_PATH").unwrap_or_else(|_| DEFAULT_STORAGE_PATH.to_string());
+
+        Ok(Self::with_storage(Storage::load(storage_path).await?))
+    }
+
+    /// create the state of the app around an already loaded store
+    pub fn with_storage(storage: Storage) -> Self {
+        Self {
             start_time: std::time::Instant::now(),
             num_connected: Arc::new(AtomicU64::new(0)),
-        })
+            storage: Arc::new(storage),
+            board_locks: Arc::new(KeyedLocks::default()),
+            panel_locks: Arc::new(KeyedLocks::default()),
+            cooldowns: Arc::new(Cooldowns::default()),
+            link_patterns: Arc::new(PatternCache::default()),
+            registration_locks: Arc::new(KeyedLocks::default()),
+            command_syncs: broadcast::channel(COMMAND_SYNC_CAPACITY).0,
+        }
     }
 }
 
@@ -31,6 +96,13 @@ impl Clone for AppState {
         Self {
             start_time: self.start_time,
             num_connected: self.num_connected.clone(),
+            storage: self.storage.clone(),
+            board_locks: self.board_locks.clone(),
+            panel_locks: self.panel_locks.clone(),
+            cooldowns: self.cooldowns.clone(),
+            link_patterns: self.link_patterns.clone(),
+            registration_locks: self.registration_locks.clone(),
+            command_syncs: self.command_syncs.clone(),
         }
     }
 }
@@ -0,0 +1,214 @@
+//! A small persistent store for per-guild and per-user settings, serialised as json to a file on disk.
+//! The whole store is kept in memory, and written back to disk after every update.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::{
+    access::Capability,
+    announcements::{SayLogEntry, ScheduledMessage},
+    custom_commands::CustomCommand,
+    holidays::Holiday,
+    locale::Locale,
+    modules::Module,
+    references::LinkPattern,
+    standups::{BoardConfig, StandupSession, Submission},
+    webhook::{WebhookConfig, WebhookDelivery},
+};
+
+/// settings which are configured for an entire guild
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GuildSettings {
+    /// the locale used for members of this guild who have not chosen their own
+    #[serde(default)]
+    pub locale: Option<Locale>,
+    /// days on which the whole guild is on holiday
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+    /// the webhook notified of every change to a submission
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    /// patterns used to turn issue tracker references in answers into links
+    #[serde(default)]
+    pub link_patterns: Vec<LinkPattern>,
+    /// the board showing what each member is working on
+    #[serde(default)]
+    pub board: Option<BoardConfig>,
+    /// the roles each capability has been granted to, members who can manage the guild have every capability
+    #[serde(default)]
+    pub role_capabilities: HashMap<Capability, Vec<u64>>,
+    /// the command modules which have been disabled, every other module is enabled
+    #[serde(default)]
+    pub disabled_modules: Vec<Module>,
+    /// the slash commands this guild has defined for itself
+    #[serde(default)]
+    pub custom_commands: Vec<CustomCommand>,
+    /// messages made with `/say` which are waiting to be sent
+    #[serde(default)]
+    pub scheduled_messages: Vec<ScheduledMessage>,
+    /// the messages the bot sent, edited or deleted on behalf of members, oldest first
+    #[serde(default)]
+    pub say_log: Vec<SayLogEntry>,
+    /// the channel each entry of the log is mirrored to, if any
+    #[serde(default)]
+    pub audit_channel: Option<u64>,
+    /// whether text written by members may ping the users it mentions when the bot posts it
+    #[serde(default)]
+    pub allow_user_mentions: bool,
+}
+
+/// settings which a user has configured for themselves, these apply across all guilds
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UserSettings {
+    /// the locale this user would like the bot to respond in
+    #[serde(default)]
+    pub locale: Option<Locale>,
+    /// the timezone this user lives in, used to compute times relative to them
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    /// regional holidays observed by this user, in addition to those of their guilds
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+}
+
+/// the hashes of the command definitions last registered with discord, so unchanged commands aren't registered again
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CommandHashes {
+    /// the commands registered globally, unknown if there is no hash
+    #[serde(default)]
+    pub global: Option<String>,
+    /// the commands registered in each guild, keyed by guild id
+    #[serde(default)]
+    pub guilds: HashMap<u64, String>,
+}
+
+/// the data which is persisted to disk
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StorageData {
+    /// settings for each guild, keyed by guild id
+    #[serde(default)]
+    pub guilds: HashMap<u64, GuildSettings>,
+    /// settings for each user, keyed by user id
+    #[serde(default)]
+    pub users: HashMap<u64, UserSettings>,
+    /// every standup session which has been started, keyed by session id
+    #[serde(default)]
+    pub sessions: HashMap<u64, StandupSession>,
+    /// every submission which has not been deleted, keyed by submission id
+    #[serde(default)]
+    pub submissions: HashMap<u64, Submission>,
+    /// webhook payloads waiting to be delivered
+    #[serde(default)]
+    pub webhook_queue: Vec<WebhookDelivery>,
+    /// the commands which have been registered with discord
+    #[serde(default)]
+    pub command_hashes: CommandHashes,
+}
+
+/// A handle to the persisted data of the bot
+pub struct Storage {
+    /// the file the data is persisted to
+    path: PathBuf,
+    /// the in-memory copy of the data
+    data: RwLock<StorageData>,
+}
+
+impl Storage {
+    /// load the storage from the provided path, if the file does not exist an empty store is created
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+
+        let data = match tokio::fs::read(&path).await {
+            Ok(raw) => serde_json::from_slice(&raw)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StorageData::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        Ok(Self {
+            path,
+            data: RwLock::new(data),
+        })
+    }
+
+    /// the directory the store is kept in, where files too large for the store are kept next to it
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new("."))
+    }
+
+    /// get read access to the stored data
+    pub async fn read(&self) -> RwLockReadGuard<'_, StorageData> {
+        self.data.read().await
+    }
+
+    /// modify the stored data, and then write the result back to disk.
+    /// the change is made to a copy which only replaces the data once it is written, so a failed write changes nothing
+    pub async fn update<R>(
+        &self,
+        f: impl FnOnce(&mut StorageData) -> R,
+    ) -> Result<R, Box<dyn Error + Send + Sync>> {
+        let mut data = self.data.write().await;
+        let mut updated = data.clone();
+        let result = f(&mut updated);
+
+        // write to a temporary file first, so a crash mid-write can't corrupt the store
+        let raw = serde_json::to_vec_pretty(&updated)?;
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, raw).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+
+        *data = updated;
+        Ok(result)
+    }
+}
+
+impl std::fmt::Debug for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Storage").field("path", &self.path).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Storage;
+
+    #[tokio::test]
+    async fn failed_write_leaves_data_unchanged() {
+        let dir = std::env::temp_dir().join(format!(
+            "standup-bot-storage-{:016x}",
+            rand::random::<u64>()
+        ));
+        let path = dir.join("storage.json");
+        let storage = Storage::load(&path).await.unwrap();
+
+        storage
+            .update(|data| {
+                data.guilds.entry(1).or_default();
+            })
+            .await
+            .unwrap();
+        // a directory in the way of the store makes the write fail
+        tokio::fs::remove_file(&path).await.unwrap();
+        tokio::fs::create_dir(&path).await.unwrap();
+        let failed = storage
+            .update(|data| {
+                data.guilds.entry(2).or_default();
+            })
+            .await;
+
+        assert!(failed.is_err());
+        assert!(storage.read().await.guilds.contains_key(&1));
+        assert!(!storage.read().await.guilds.contains_key(&2));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! Utilities for working with the timezones of users, so that times can be computed in each user's own zone
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::storage::Storage;
+
+/// the zone used for users who have not set one, read from the `TZ` environment variable
+pub fn default_timezone() -> Tz {
+    std::env::var("TZ")
+        .ok()
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// parse an IANA timezone name, such as `Pacific/Auckland`, ignoring case
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    let name = name.trim();
+    name.parse().ok().or_else(|| {
+        chrono_tz::TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(name))
+            .copied()
+    })
+}
+
+/// find the timezones matching part of a name, those whose city starts with the query are listed first
+pub fn search_timezones(query: &str, limit: usize) -> Vec<Tz> {
+    let query = query.trim().to_lowercase().replace(' ', "_");
+    let city = |tz: &Tz| {
+        tz.name()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+
+    let mut matches: Vec<Tz> = chrono_tz::TZ_VARIANTS
+        .iter()
+        .filter(|tz| tz.name().to_lowercase().contains(&query))
+        .copied()
+        .collect();
+    matches.sort_by_key(|tz| (!city(tz).starts_with(&query), tz.name()));
+    matches.truncate(limit);
+    matches
+}
+
+/// get the timezone a user has chosen, or the default timezone if they have not chosen one
+pub async fn user_timezone(storage: &Storage, user_id: u64) -> Tz {
+    storage
+        .read()
+        .await
+        .users
+        .get(&user_id)
+        .and_then(|u| u.timezone)
+        .unwrap_or_else(default_timezone)
+}
+
+/// get the date of the monday of the week containing the provided instant, in the provided zone
+pub fn monday_of_week(tz: Tz, at: DateTime<Utc>) -> NaiveDate {
+    let local = at.with_timezone(&tz).date_naive();
+    local - Duration::days(local.weekday().num_days_from_monday().into())
+}
+
+/// get the start of the week (midnight on monday) containing the provided instant, in the provided zone
+pub fn start_of_week(tz: Tz, at: DateTime<Utc>) -> DateTime<Utc> {
+    start_of_day(tz, monday_of_week(tz, at))
+}
+
+/// get the first instant of a date in the provided zone.
+/// if midnight does not exist on that date due to a daylight savings transition, the earliest valid time is used
+pub fn start_of_day(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::default());
+    (0..24)
+        .find_map(|hour| {
+            tz.from_local_datetime(&(midnight + Duration::hours(hour)))
+                .earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+/// the way a discord timestamp should be rendered by the client
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum TimestampStyle {
+    /// e.g. `16:20`
+    ShortTime,
+    /// e.g. `20/04/2021`
+    ShortDate,
+    /// e.g. `20 April 2021`
+    LongDate,
+    /// e.g. `20 April 2021 16:20`
+    ShortDateTime,
+    /// e.g. `2 months ago`
+    Relative,
+}
+
+/// format an instant as a discord timestamp, which each user's client displays in their own zone
+pub fn discord_timestamp(at: DateTime<Utc>, style: TimestampStyle) -> String {
+    let style = match style {
+        TimestampStyle::ShortTime => 't',
+        TimestampStyle::ShortDate => 'd',
+        TimestampStyle::LongDate => 'D',
+        TimestampStyle::ShortDateTime => 'f',
+        TimestampStyle::Relative => 'R',
+    };
+    format!("<t:{}:{}>", at.timestamp(), style)
+}
@@ -0,0 +1,669 @@
+//! Outbound webhooks, which notify an external service of every change to a standup submission.
+//! Deliveries are queued in storage so they survive restarts, and retried with an exponential backoff.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{debug, error, warn};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    references::Reference,
+    standups::{StandupSession, Submission},
+    state::AppState,
+    storage::{Storage, StorageData},
+};
+
+/// the version of the payload format, incremented whenever a breaking change is made to it
+pub const PAYLOAD_VERSION: u32 = 1;
+
+/// the number of times a delivery is attempted before it is dropped
+const MAX_ATTEMPTS: u32 = 8;
+
+/// how often the queue is checked for deliveries which are due
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// how long to wait for a receiver to respond
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the webhook configured for a guild
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// the url payloads are POSTed to
+    pub url: String,
+    /// the secret used to sign each payload
+    pub secret: String,
+}
+
+/// the kind of change a payload describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "submission.created")]
+    SubmissionCreated,
+    #[serde(rename = "submission.edited")]
+    SubmissionEdited,
+    #[serde(rename = "submission.deleted")]
+    SubmissionDeleted,
+    #[serde(rename = "test")]
+    Test,
+}
+
+impl WebhookEvent {
+    /// the name of the event, as sent in the payload and headers
+    pub fn name(self) -> &'static str {
+        match self {
+            WebhookEvent::SubmissionCreated => "submission.created",
+            WebhookEvent::SubmissionEdited => "submission.edited",
+            WebhookEvent::SubmissionDeleted => "submission.deleted",
+            WebhookEvent::Test => "test",
+        }
+    }
+}
+
+/// a payload which has been queued for delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    /// a unique id for this delivery, which receivers can use to ignore duplicates
+    pub id: String,
+    /// the guild whose webhook this should be delivered to
+    pub guild_id: u64,
+    /// the kind of change the payload describes
+    pub event: WebhookEvent,
+    /// the serialised payload
+    pub body: String,
+    /// the number of failed attempts made so far
+    pub attempts: u32,
+    /// the earliest time the next attempt may be made
+    pub next_attempt: DateTime<Utc>,
+}
+
+/// the json body sent to a receiver, ids are sent as strings as they do not fit in a javascript number
+#[derive(Debug, Serialize)]
+struct WebhookPayload {
+    version: u32,
+    event: WebhookEvent,
+    delivery_id: String,
+    created_at: DateTime<Utc>,
+    guild_id: String,
+    session: Option<SessionPayload>,
+    user: Option<UserPayload>,
+    submission: Option<SubmissionPayload>,
+}
+
+#[derive(Debug, Serialize)]
+#[allow(clippy::missing_docs_in_private_items)]
+struct SessionPayload {
+    id: String,
+    channel_id: String,
+    started_by: String,
+    started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[allow(clippy::missing_docs_in_private_items)]
+struct UserPayload {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[allow(clippy::missing_docs_in_private_items)]
+struct SubmissionPayload {
+    id: String,
+    channel_id: String,
+    message_id: Option<String>,
+    questions: Vec<String>,
+    answers: Vec<String>,
+    submitted_at: DateTime<Utc>,
+    edited_at: Option<DateTime<Utc>>,
+    references: Vec<Reference>,
+}
+
+/// generate a random id for a delivery
+fn delivery_id() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+/// generate a random secret for signing payloads
+pub fn generate_secret() -> String {
+    let mut rng = rand::thread_rng();
+    format!("{:032x}{:032x}", rng.gen::<u128>(), rng.gen::<u128>())
+}
+
+/// sign a payload, so receivers can verify it came from this bot.
+/// the signature is a hex encoded HMAC-SHA256 of `{timestamp}.{body}`
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("hmac can be created with a key of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// queue a payload describing a change to a submission, if the guild has a webhook configured
+pub async fn enqueue(
+    storage: &Storage,
+    event: WebhookEvent,
+    session: Option<&StandupSession>,
+    submission: &Submission,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let guild_id = submission.guild_id;
+    let configured = storage
+        .read()
+        .await
+        .guilds
+        .get(&guild_id)
+        .map_or(false, |g| g.webhook.is_some());
+    if !configured {
+        return Ok(());
+    }
+
+    let id = delivery_id();
+    let delivery = WebhookDelivery {
+        id: id.clone(),
+        guild_id,
+        event,
+        body: serde_json::to_string(&submission_payload(id, event, session, submission))?,
+        attempts: 0,
+        next_attempt: Utc::now(),
+    };
+
+    storage
+        .update(|data| data.webhook_queue.push(delivery))
+        .await
+}
+
+/// the payload describing a change to a submission
+fn submission_payload(
+    id: String,
+    event: WebhookEvent,
+    session: Option<&StandupSession>,
+    submission: &Submission,
+) -> WebhookPayload {
+    WebhookPayload {
+        version: PAYLOAD_VERSION,
+        event,
+        delivery_id: id,
+        created_at: Utc::now(),
+        guild_id: submission.guild_id.to_string(),
+        session: session.map(|s| SessionPayload {
+            id: s.id.to_string(),
+            channel_id: s.channel_id.to_string(),
+            started_by: s.started_by.to_string(),
+            started_at: s.started_at,
+        }),
+        user: Some(UserPayload {
+            id: submission.user_id.to_string(),
+            name: submission.username.clone(),
+        }),
+        submission: Some(SubmissionPayload {
+            id: submission.id.to_string(),
+            channel_id: submission.channel_id.to_string(),
+            message_id: submission.message_id.map(|id| id.to_string()),
+            questions: submission.questions.clone(),
+            answers: submission.answers.clone(),
+            submitted_at: submission.submitted_at,
+            edited_at: submission.edited_at,
+            references: submission.references.clone(),
+        }),
+    }
+}
+
+/// POST a payload to a webhook, returning the status code of a successful delivery
+async fn deliver(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    id: &str,
+    event: WebhookEvent,
+    body: &str,
+) -> Result<u16, String> {
+    let timestamp = Utc::now().timestamp();
+
+    let response = client
+        .post(&config.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Standup-Event", event.name())
+        .header("X-Standup-Delivery", id)
+        .header("X-Standup-Timestamp", timestamp.to_string())
+        .header(
+            "X-Standup-Signature",
+            format!("sha256={}", sign(&config.secret, timestamp, body)),
+        )
+        .body(body.to_string())
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    match status.is_success() {
+        true => Ok(status.as_u16()),
+        false => Err(format!("receiver responded with {}", status)),
+    }
+}
+
+/// send a test payload to a webhook immediately, without queueing it
+pub async fn send_test(config: &WebhookConfig, guild_id: u64) -> Result<u16, String> {
+    let id = delivery_id();
+    let payload = WebhookPayload {
+        version: PAYLOAD_VERSION,
+        event: WebhookEvent::Test,
+        delivery_id: id.clone(),
+        created_at: Utc::now(),
+        guild_id: guild_id.to_string(),
+        session: None,
+        user: None,
+        submission: None,
+    };
+    let body = serde_json::to_string(&payload).map_err(|e| e.to_string())?;
+
+    deliver(
+        &reqwest::Client::new(),
+        config,
+        &id,
+        WebhookEvent::Test,
+        &body,
+    )
+    .await
+}
+
+/// check if a url is suitable for use as a webhook
+pub fn valid_url(url: &str) -> bool {
+    reqwest::Url::parse(url).map_or(false, |u| matches!(u.scheme(), "http" | "https"))
+}
+
+/// the delay before retrying a delivery which has failed the provided number of times
+fn backoff(attempts: u32) -> chrono::Duration {
+    let seconds = 10i64.saturating_mul(2i64.saturating_pow(attempts));
+    chrono::Duration::seconds(seconds.min(60 * 60))
+}
+
+/// the outcome of a single delivery attempt
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// the delivery succeeded, or can never succeed, and should be removed from the queue
+    Done,
+    /// the delivery failed and should be tried again later
+    Retry,
+}
+
+/// attempt a queued delivery to the webhook of its guild, if it still has one
+async fn attempt(
+    client: &reqwest::Client,
+    delivery: &WebhookDelivery,
+    config: Option<WebhookConfig>,
+) -> Outcome {
+    // the webhook was removed after this was queued
+    let config = match config {
+        Some(config) => config,
+        None => return Outcome::Done,
+    };
+
+    match deliver(
+        client,
+        &config,
+        &delivery.id,
+        delivery.event,
+        &delivery.body,
+    )
+    .await
+    {
+        Ok(status) => {
+            debug!("delivered webhook {} with status {}", delivery.id, status);
+            Outcome::Done
+        }
+        Err(e) if delivery.attempts + 1 >= MAX_ATTEMPTS => {
+            error!(
+                "dropping webhook {} for guild {} after {} attempts: {}",
+                delivery.id,
+                delivery.guild_id,
+                delivery.attempts + 1,
+                e
+            );
+            Outcome::Done
+        }
+        Err(e) => {
+            warn!(
+                "failed to deliver webhook {} for guild {}: {}",
+                delivery.id, delivery.guild_id, e
+            );
+            Outcome::Retry
+        }
+    }
+}
+
+/// remove the finished deliveries from the queue, and push back those which will be retried
+async fn record_outcomes(storage: &Storage, outcomes: Vec<(String, Outcome)>) {
+    let result = storage
+        .update(|data| {
+            for (id, outcome) in outcomes {
+                match outcome {
+                    Outcome::Done => data.webhook_queue.retain(|d| d.id != id),
+                    Outcome::Retry => {
+                        if let Some(d) = data.webhook_queue.iter_mut().find(|d| d.id == id) {
+                            d.attempts += 1;
+                            d.next_attempt = Utc::now() + backoff(d.attempts);
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("failed to update webhook queue: {}", e);
+    }
+}
+
+/// the deliveries of a guild which are due, with its webhook
+type GuildDeliveries = Vec<(WebhookDelivery, Option<WebhookConfig>)>;
+
+/// find the deliveries which are due for each guild without deliveries in progress.
+/// a guild's deliveries are taken in the order they were queued, up to the first which isn't due yet,
+/// so one waiting to be retried holds back those queued after it
+fn due_deliveries(
+    data: &StorageData,
+    now: DateTime<Utc>,
+    in_flight: &HashSet<u64>,
+) -> HashMap<u64, GuildDeliveries> {
+    let mut due: HashMap<u64, GuildDeliveries> = HashMap::new();
+    let mut held: HashSet<u64> = in_flight.clone();
+    for delivery in &data.webhook_queue {
+        if held.contains(&delivery.guild_id) {
+            continue;
+        }
+        if delivery.next_attempt > now {
+            held.insert(delivery.guild_id);
+            continue;
+        }
+        let config = data
+            .guilds
+            .get(&delivery.guild_id)
+            .and_then(|g| g.webhook.clone());
+        due.entry(delivery.guild_id)
+            .or_default()
+            .push((delivery.clone(), config));
+    }
+    due
+}
+
+/// attempt the deliveries of a guild in order, stopping at the first which has to be retried
+/// so none are delivered ahead of it
+async fn deliver_in_order(
+    client: &reqwest::Client,
+    deliveries: GuildDeliveries,
+) -> Vec<(String, Outcome)> {
+    let mut outcomes = vec![];
+    for (delivery, config) in deliveries {
+        let outcome = attempt(client, &delivery, config).await;
+        let retry = outcome == Outcome::Retry;
+        outcomes.push((delivery.id, outcome));
+        if retry {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// marks the deliveries of a guild as in progress until it is dropped, so the next poll doesn't start them again
+struct InFlight {
+    /// the guilds with deliveries in progress
+    guilds: Arc<Mutex<HashSet<u64>>>,
+    /// the guild this marks
+    guild_id: u64,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut guilds) = self.guilds.lock() {
+            guilds.remove(&self.guild_id);
+        }
+    }
+}
+
+/// a background task which works through the webhook queue
+pub struct WebhookDispatcher {
+    /// the state holding the queue
+    state: AppState,
+    /// the client used for every delivery
+    client: reqwest::Client,
+    /// the guilds whose deliveries are currently being made
+    in_flight: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl WebhookDispatcher {
+    /// create a new dispatcher for the queue held in the provided state
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            client: reqwest::Client::new(),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// deliver queued payloads as they become due, this never returns.
+    /// the deliveries of each guild are made in order, a failed one being retried before any queued after it,
+    /// but guilds don't wait on each other, so a receiver which is down only holds up its own guild
+    pub async fn run(&mut self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let due = {
+                let data = self.state.storage.read().await;
+                let in_flight = match self.in_flight.lock() {
+                    Ok(in_flight) => in_flight.clone(),
+                    Err(e) => {
+                        error!("webhook dispatcher state was poisoned: {}", e);
+                        return;
+                    }
+                };
+                due_deliveries(&data, Utc::now(), &in_flight)
+            };
+
+            for (guild_id, deliveries) in due {
+                if let Ok(mut in_flight) = self.in_flight.lock() {
+                    in_flight.insert(guild_id);
+                }
+                let marker = InFlight {
+                    guilds: self.in_flight.clone(),
+                    guild_id,
+                };
+                let client = self.client.clone();
+                let state = self.state.clone();
+
+                tokio::spawn(async move {
+                    let _marker = marker;
+                    let outcomes = deliver_in_order(&client, deliveries).await;
+                    record_outcomes(&state.storage, outcomes).await;
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    /// a delivery for a guild, due at a number of seconds from `now`
+    fn delivery(id: &str, guild_id: u64, due_in: i64) -> WebhookDelivery {
+        WebhookDelivery {
+            id: id.to_string(),
+            guild_id,
+            event: WebhookEvent::SubmissionCreated,
+            body: String::from("{}"),
+            attempts: 0,
+            next_attempt: Utc::now() + chrono::Duration::seconds(due_in),
+        }
+    }
+
+    /// a webhook nothing is listening on, so every delivery to it fails without leaving the machine
+    fn unreachable() -> WebhookConfig {
+        WebhookConfig {
+            url: String::from("http://127.0.0.1:1/"),
+            secret: String::from("secret"),
+        }
+    }
+
+    #[test]
+    fn signatures_are_an_hmac_of_the_timestamp_and_body() {
+        // computed with python's hmac module
+        assert_eq!(
+            sign("secret", 1_700_000_000, r#"{"hello":"world"}"#),
+            "654f06c856baf080af3fa272934823257a542d35cf1f88099338f850a60601a4"
+        );
+        assert_ne!(
+            sign("secret", 1_700_000_001, r#"{"hello":"world"}"#),
+            sign("secret", 1_700_000_000, r#"{"hello":"world"}"#)
+        );
+    }
+
+    #[test]
+    fn payloads_send_ids_as_strings() {
+        let at = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+        let session = StandupSession {
+            id: 1,
+            guild_id: 10,
+            channel_id: 20,
+            started_by: 30,
+            started_at: at,
+            message_id: None,
+            holiday: None,
+            closed_at: None,
+            digest_message_id: None,
+        };
+        let submission = Submission {
+            id: 2,
+            guild_id: 10,
+            channel_id: 20,
+            message_id: Some(3),
+            session_id: Some(1),
+            user_id: 40,
+            username: String::from("tester"),
+            questions: vec![String::from("What?")],
+            answers: vec![String::from("PAY-1")],
+            submitted_at: at,
+            edited_at: None,
+            references: vec![Reference {
+                text: String::from("PAY-1"),
+                url: String::from("https://example.com/PAY-1"),
+            }],
+        };
+
+        let payload = submission_payload(
+            String::from("delivery"),
+            WebhookEvent::SubmissionEdited,
+            Some(&session),
+            &submission,
+        );
+        let mut body = serde_json::to_value(payload).unwrap();
+        assert!(body["created_at"].is_string());
+        body["created_at"] = json!(null);
+
+        assert_eq!(
+            body,
+            json!({
+                "version": PAYLOAD_VERSION,
+                "event": "submission.edited",
+                "delivery_id": "delivery",
+                "created_at": null,
+                "guild_id": "10",
+                "session": {
+                    "id": "1",
+                    "channel_id": "20",
+                    "started_by": "30",
+                    "started_at": "2023-01-02T09:00:00Z",
+                },
+                "user": { "id": "40", "name": "tester" },
+                "submission": {
+                    "id": "2",
+                    "channel_id": "20",
+                    "message_id": "3",
+                    "questions": ["What?"],
+                    "answers": ["PAY-1"],
+                    "submitted_at": "2023-01-02T09:00:00Z",
+                    "edited_at": null,
+                    "references": [{ "text": "PAY-1", "url": "https://example.com/PAY-1" }],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_an_hour() {
+        let schedule: Vec<i64> = (0..10).map(|a| backoff(a).num_seconds()).collect();
+        assert_eq!(schedule, [10, 20, 40, 80, 160, 320, 640, 1280, 2560, 3600]);
+        assert_eq!(backoff(u32::MAX).num_seconds(), 3600);
+    }
+
+    #[test]
+    fn deliveries_wait_behind_an_earlier_one_of_their_guild() {
+        let data = StorageData {
+            webhook_queue: vec![
+                delivery("first", 1, 60),
+                delivery("second", 1, -60),
+                delivery("third", 2, -60),
+                delivery("fourth", 2, -30),
+                delivery("fifth", 3, -60),
+            ],
+            ..Default::default()
+        };
+        let in_flight = HashSet::from([3]);
+
+        let due = due_deliveries(&data, Utc::now(), &in_flight);
+        let ids = |guild_id: u64| {
+            due.get(&guild_id)
+                .map(|d| d.iter().map(|(d, _)| d.id.as_str()).collect::<Vec<_>>())
+        };
+        assert_eq!(ids(1), None);
+        assert_eq!(ids(2), Some(vec!["third", "fourth"]));
+        assert_eq!(ids(3), None);
+    }
+
+    #[tokio::test]
+    async fn a_failed_delivery_stays_queued_and_holds_back_the_rest() {
+        let dir = std::env::temp_dir().join(format!(
+            "standup-bot-webhook-{:016x}",
+            rand::random::<u64>()
+        ));
+        let path = dir.join("storage.json");
+        let storage = Storage::load(&path).await.unwrap();
+        storage
+            .update(|data| {
+                data.webhook_queue = vec![delivery("first", 1, -1), delivery("second", 1, -1)];
+            })
+            .await
+            .unwrap();
+
+        let deliveries = storage.read().await.webhook_queue.clone();
+        let deliveries = deliveries
+            .into_iter()
+            .map(|d| (d, Some(unreachable())))
+            .collect();
+        let outcomes = deliver_in_order(&reqwest::Client::new(), deliveries).await;
+        assert_eq!(outcomes, vec![(String::from("first"), Outcome::Retry)]);
+        record_outcomes(&storage, outcomes).await;
+
+        // the queue is read back from disk, as it would be after a restart
+        let reloaded = Storage::load(&path).await.unwrap();
+        let queue = reloaded.read().await.webhook_queue.clone();
+        assert_eq!(queue.len(), 2);
+        assert_eq!((queue[0].id.as_str(), queue[0].attempts), ("first", 1));
+        assert!(queue[0].next_attempt > Utc::now());
+        assert_eq!((queue[1].id.as_str(), queue[1].attempts), ("second", 0));
+        assert!(due_deliveries(&reloaded.read().await, Utc::now(), &HashSet::new()).is_empty());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}